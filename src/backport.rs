@@ -0,0 +1,67 @@
+use thiserror::Error;
+
+use crate::git::CommitInfo;
+use crate::hooks;
+use crate::release::describe_conflicts;
+
+#[derive(Debug, Error)]
+pub enum BackportError {
+    #[error("No commit of {1} mentions {0} and no branch was found for it")]
+    NoCommits(String, String),
+    #[error("{0} cannot be backported to every branch, {}, please cherry-pick the commits yourself on the backport branches kept for them", describe_conflicts(.1))]
+    Conflicts(String, Vec<(String, Vec<String>)>),
+    #[error("{0} cannot be backported to {}, see the errors above", .1.join(", "))]
+    Failed(String, Vec<String>),
+}
+
+/// Prefix of the branches created by backport
+pub const PREFIX: &str = "backport/";
+
+/// Name of the branch backporting an issue to `target`, e.g. `backport/PROJ-1-release-1.2`
+pub fn branch_name(key: &str, target: &str) -> String {
+    format!("{}{}-{}", PREFIX, key, target.replace('/', "-"))
+}
+
+/// Commits mentioning the issue key, oldest first, from commits listed newest first
+pub fn issue_commits(commits: Vec<CommitInfo>, key: &str) -> Vec<CommitInfo> {
+    commits
+        .into_iter()
+        .rev()
+        .filter(|commit| hooks::contains_issue_key(&commit.message, key))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commit(id: &str, message: &str) -> CommitInfo {
+        CommitInfo {
+            id: id.to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_branch_name() {
+        assert_eq!(
+            branch_name("PROJ-1", "release/1.2"),
+            "backport/PROJ-1-release-1.2"
+        );
+    }
+
+    #[test]
+    fn test_issue_commits_oldest_first() {
+        let commits = vec![
+            commit("c3", "fix: keep the session\n\nRefs PROJ-1"),
+            commit("c2", "PROJ-12 Add a logout button"),
+            commit("c1", "PROJ-1 Fix the login"),
+        ];
+
+        let ids: Vec<String> = issue_commits(commits, "PROJ-1")
+            .into_iter()
+            .map(|commit| commit.id)
+            .collect();
+        assert_eq!(ids, vec!["c1", "c3"]);
+    }
+}
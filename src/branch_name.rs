@@ -0,0 +1,371 @@
+use regex::Regex;
+
+use crate::{
+    config::BranchNameConfig,
+    git::{to_branch_name, GitError, GitRepository},
+    jira::issue_key_from_branch,
+    tracker::{Issue, IssueTracker},
+};
+
+pub const DEFAULT_BRANCH_TEMPLATE: &str = "{key}-{slug}";
+
+/// Values available to branch name templates
+pub struct BranchNameContext<'a> {
+    pub issue: &'a Issue,
+    pub user: Option<&'a str>,
+    pub date: &'a str,
+}
+
+/// Today's date as used by the `{date}` placeholder
+pub fn today() -> String {
+    chrono::Local::now().format("%Y-%m-%d").to_string()
+}
+
+/// Name of the current system user as used by the `{user}` placeholder
+pub fn current_user() -> Option<String> {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .ok()
+        .map(|user| to_branch_name(&user))
+        .filter(|user| !user.is_empty())
+}
+
+/// Build a branch name from the template of the configuration.
+///
+/// Supported placeholders are `{key}`, `{slug}`, `{type}`, `{prefix}`, `{user}` and `{date}`.
+/// When a maximum length is configured, words are removed from the end of the slug until the
+/// name fits.
+pub fn render_branch_name(
+    config: Option<&BranchNameConfig>,
+    context: &BranchNameContext,
+) -> String {
+    let template = config
+        .and_then(|c| c.template.as_deref())
+        .unwrap_or(DEFAULT_BRANCH_TEMPLATE);
+    let max_length = config.and_then(|c| c.max_length);
+
+    let slug = to_branch_name(&context.issue.summary);
+    let words: Vec<&str> = slug.split('-').filter(|w| !w.is_empty()).collect();
+
+    let render = |word_count: usize| -> String {
+        let slug = words[..word_count].join("-");
+        cleanup(&fill(template, config, context, &slug))
+    };
+
+    let full = render(words.len());
+    let max_length = match max_length {
+        Some(max_length) if full.chars().count() > max_length => max_length,
+        _ => return full,
+    };
+
+    for word_count in (0..words.len()).rev() {
+        let name = render(word_count);
+        if name.chars().count() <= max_length {
+            return name;
+        }
+    }
+
+    // Even without slug the name is too long, cut it
+    let cut: String = render(0).chars().take(max_length).collect();
+    cleanup(&cut)
+}
+
+fn fill(
+    template: &str,
+    config: Option<&BranchNameConfig>,
+    context: &BranchNameContext,
+    slug: &str,
+) -> String {
+    let issue_type = context.issue.issue_type.as_deref().unwrap_or_default();
+
+    let prefix = config
+        .and_then(|c| {
+            c.prefixes
+                .iter()
+                .find(|(t, _)| t.eq_ignore_ascii_case(issue_type))
+                .map(|(_, prefix)| prefix.clone())
+        })
+        .unwrap_or_else(|| to_branch_name(issue_type));
+
+    template
+        .replace("{key}", &context.issue.key)
+        .replace("{slug}", slug)
+        .replace("{type}", &to_branch_name(issue_type))
+        .replace("{prefix}", &prefix)
+        .replace("{user}", context.user.unwrap_or_default())
+        .replace("{date}", context.date)
+}
+
+/// Find the issue key in a branch name built from the template.
+///
+/// Separators of the template are optional as they are removed along with empty placeholders.
+/// Returns `None` when the template has no `{key}` or the branch does not match it.
+pub fn issue_key_from_template(config: Option<&BranchNameConfig>, branch: &str) -> Option<String> {
+    let template = config
+        .and_then(|c| c.template.as_deref())
+        .unwrap_or(DEFAULT_BRANCH_TEMPLATE);
+    if !template.contains("{key}") {
+        return None;
+    }
+
+    let mut pattern = String::from("^");
+    let mut rest = template;
+    while let Some((literal, placeholder, after)) = next_placeholder(rest) {
+        push_literal(&mut pattern, literal);
+        pattern.push_str(match placeholder {
+            "key" => r"(?P<key>[A-Z][A-Z0-9]*-\d+|\d+)",
+            "date" => r"(?:\d{4}-\d{2}-\d{2})?",
+            "slug" => r".*?",
+            _ => r"[^/]*?",
+        });
+        rest = after;
+    }
+    push_literal(&mut pattern, rest);
+    pattern.push('$');
+
+    let regex = Regex::new(&pattern).ok()?;
+    regex
+        .captures(branch)
+        .map(|captures| captures["key"].to_string())
+}
+
+/// Split a template on its first placeholder: text before, placeholder name and text after
+fn next_placeholder(template: &str) -> Option<(&str, &str, &str)> {
+    let start = template.find('{')?;
+    let end = start + template[start..].find('}')?;
+    Some((
+        &template[..start],
+        &template[start + 1..end],
+        &template[end + 1..],
+    ))
+}
+
+fn push_literal(pattern: &mut String, literal: &str) {
+    if literal.is_empty() {
+        return;
+    }
+
+    let escaped = regex::escape(literal);
+    if literal.chars().all(|c| matches!(c, '/' | '-' | '_' | '.')) {
+        pattern.push_str(&format!("(?:{})?", escaped));
+    } else {
+        pattern.push_str(&escaped);
+    }
+}
+
+/// Issue key of a branch, from the branch name template or else from the tracker key format
+pub fn issue_key_for_branch(
+    config: Option<&BranchNameConfig>,
+    tracker: Option<&dyn IssueTracker>,
+    branch: &str,
+) -> Option<String> {
+    issue_key_from_template(config, branch).or_else(|| match tracker {
+        Some(tracker) => tracker.find_issue_key(branch),
+        None => issue_key_from_branch(branch),
+    })
+}
+
+/// Issue key of the branch currently checked out
+pub fn current_issue_key(
+    repo: &impl GitRepository,
+    config: Option<&BranchNameConfig>,
+    tracker: Option<&dyn IssueTracker>,
+) -> Result<Option<String>, GitError> {
+    Ok(issue_key_for_branch(
+        config,
+        tracker,
+        &repo.current_branch()?,
+    ))
+}
+
+/// Remove separators left behind by empty placeholders
+fn cleanup(name: &str) -> String {
+    let mut result = String::with_capacity(name.len());
+    for c in name.chars() {
+        let previous = result.chars().last();
+        let is_separator = |c: char| c == '/' || c == '-';
+        match previous {
+            Some(p) if is_separator(p) && is_separator(c) => {
+                // Keep the slash as it is the stronger separator
+                if c == '/' {
+                    result.pop();
+                    result.push(c);
+                }
+            }
+            None if is_separator(c) => {}
+            _ => result.push(c),
+        }
+    }
+
+    result.trim_end_matches(['/', '-', '.']).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    fn issue(issue_type: Option<&str>) -> Issue {
+        Issue {
+            key: "PROJ-123".to_string(),
+            summary: "Fix the login page crash".to_string(),
+            status: "To Do".to_string(),
+            issue_type: issue_type.map(String::from),
+            url: "".to_string(),
+        }
+    }
+
+    fn config(template: &str, max_length: Option<usize>) -> BranchNameConfig {
+        BranchNameConfig {
+            template: Some(template.to_string()),
+            prefixes: BTreeMap::from([
+                ("Bug".to_string(), "bugfix".to_string()),
+                ("Story".to_string(), "feature".to_string()),
+            ]),
+            max_length,
+        }
+    }
+
+    #[test]
+    fn test_default_template() {
+        let issue = issue(None);
+        let context = BranchNameContext {
+            issue: &issue,
+            user: None,
+            date: "2023-09-01",
+        };
+        assert_eq!(
+            render_branch_name(None, &context),
+            "PROJ-123-fix-the-login-page-crash"
+        );
+    }
+
+    #[test]
+    fn test_template_with_prefix() {
+        let issue = issue(Some("bug"));
+        let context = BranchNameContext {
+            issue: &issue,
+            user: None,
+            date: "2023-09-01",
+        };
+        let config = config("{prefix}/{key}-{slug}", None);
+        assert_eq!(
+            render_branch_name(Some(&config), &context),
+            "bugfix/PROJ-123-fix-the-login-page-crash"
+        );
+    }
+
+    #[test]
+    fn test_template_with_unmapped_type() {
+        let issue = issue(Some("Technical Task"));
+        let context = BranchNameContext {
+            issue: &issue,
+            user: None,
+            date: "2023-09-01",
+        };
+        let config = config("{prefix}/{key}", None);
+        assert_eq!(
+            render_branch_name(Some(&config), &context),
+            "technical-task/PROJ-123"
+        );
+    }
+
+    #[test]
+    fn test_template_with_user_and_date() {
+        let issue = issue(None);
+        let context = BranchNameContext {
+            issue: &issue,
+            user: Some("jdoe"),
+            date: "2023-09-01",
+        };
+        let config = config("{user}/{date}-{key}", None);
+        assert_eq!(
+            render_branch_name(Some(&config), &context),
+            "jdoe/2023-09-01-PROJ-123"
+        );
+    }
+
+    #[test]
+    fn test_empty_placeholders_are_cleaned() {
+        let issue = issue(None);
+        let context = BranchNameContext {
+            issue: &issue,
+            user: None,
+            date: "2023-09-01",
+        };
+        let config = config("{user}/{type}/{key}", None);
+        assert_eq!(render_branch_name(Some(&config), &context), "PROJ-123");
+    }
+
+    #[test]
+    fn test_truncate_on_word_boundary() {
+        let issue = issue(Some("Story"));
+        let context = BranchNameContext {
+            issue: &issue,
+            user: None,
+            date: "2023-09-01",
+        };
+        let config = config("{prefix}/{key}-{slug}", Some(30));
+        assert_eq!(
+            render_branch_name(Some(&config), &context),
+            "feature/PROJ-123-fix-the-login"
+        );
+    }
+
+    #[test]
+    fn test_key_from_default_template() {
+        assert_eq!(
+            issue_key_from_template(None, "PROJ-123-fix-the-login"),
+            Some("PROJ-123".to_string())
+        );
+        assert_eq!(
+            issue_key_from_template(None, "PROJ-123"),
+            Some("PROJ-123".to_string())
+        );
+    }
+
+    #[test]
+    fn test_key_from_template_with_prefix() {
+        let config = config("{prefix}/{key}-{slug}", None);
+        assert_eq!(
+            issue_key_from_template(Some(&config), "bugfix/PROJ-1-fix-2-bugs"),
+            Some("PROJ-1".to_string())
+        );
+        assert_eq!(
+            issue_key_from_template(Some(&config), "PROJ-1-no-prefix"),
+            Some("PROJ-1".to_string())
+        );
+    }
+
+    #[test]
+    fn test_key_from_template_with_user_and_number_key() {
+        let config = config("{user}/{date}-{key}-{slug}", None);
+        assert_eq!(
+            issue_key_from_template(Some(&config), "jdoe/2023-09-01-42-fix"),
+            Some("42".to_string())
+        );
+    }
+
+    #[test]
+    fn test_key_from_branch_not_matching_template() {
+        let config = config("feature/{key}", None);
+        assert_eq!(issue_key_from_template(Some(&config), "develop"), None);
+        assert_eq!(
+            issue_key_for_branch(Some(&config), None, "hotfix/PROJ-9-crash"),
+            Some("PROJ-9".to_string())
+        );
+    }
+
+    #[test]
+    fn test_truncate_without_slug() {
+        let issue = issue(None);
+        let context = BranchNameContext {
+            issue: &issue,
+            user: Some("someone"),
+            date: "2023-09-01",
+        };
+        let config = config("{user}/{key}-{slug}", Some(10));
+        assert_eq!(render_branch_name(Some(&config), &context), "someone/PR");
+    }
+}
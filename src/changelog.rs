@@ -0,0 +1,183 @@
+use std::sync::OnceLock;
+
+use regex::{Captures, Regex};
+use url::Url;
+
+use crate::conventional;
+use crate::git::CommitInfo;
+use crate::jira::browse_url;
+
+/// Section listing the breaking changes, before all the others
+const BREAKING_CHANGES: &str = "Breaking Changes";
+/// Section listing the commits not following Conventional Commits, after all the others
+const OTHER_CHANGES: &str = "Other Changes";
+
+/// Jira issue key, compiled once
+static ISSUE_KEY: OnceLock<Option<Regex>> = OnceLock::new();
+
+/// Title of the section of a commit type, the type itself when it has no known title
+fn section_title(kind: &str) -> &str {
+    match kind {
+        "feat" => "Features",
+        "fix" => "Bug Fixes",
+        "perf" => "Performance",
+        "refactor" => "Refactoring",
+        "docs" => "Documentation",
+        "test" => "Tests",
+        "build" => "Build",
+        "ci" => "Continuous Integration",
+        "chore" => "Chores",
+        "style" => "Style",
+        "revert" => "Reverts",
+        other => other,
+    }
+}
+
+/// Render commits as a Markdown changelog with a section by type, in the order of `types`.
+///
+/// Breaking changes come first and commits not following Conventional Commits last. Jira issue
+/// keys are linked to their page on `jira_host` when it is given.
+pub fn render(
+    title: &str,
+    commits: &[CommitInfo],
+    types: &[String],
+    jira_host: Option<&Url>,
+) -> String {
+    let mut sections: Vec<(&str, Vec<String>)> = vec![(BREAKING_CHANGES, Vec::new())];
+    sections.extend(types.iter().map(|kind| (section_title(kind), Vec::new())));
+    let mut others = Vec::new();
+
+    for commit in commits {
+        let (section, text) = match conventional::parse(&commit.message) {
+            Ok(parsed) => {
+                let section = match parsed.breaking {
+                    true => BREAKING_CHANGES,
+                    false => section_title(parsed.kind),
+                };
+                let text = match parsed.scope {
+                    Some(scope) => format!("**{}:** {}", scope, parsed.description),
+                    None => parsed.description.to_string(),
+                };
+                (Some(section), text)
+            }
+            Err(_) => (None, commit.subject().to_string()),
+        };
+        let entry = format!(
+            "- {} ({})",
+            link_issue_keys(&text, &commit.message, jira_host),
+            commit.short_id()
+        );
+
+        match section {
+            Some(section) => match sections.iter_mut().find(|(title, _)| *title == section) {
+                Some((_, entries)) => entries.push(entry),
+                None => sections.push((section, vec![entry])),
+            },
+            None => others.push(entry),
+        }
+    }
+    sections.push((OTHER_CHANGES, others));
+
+    let mut changelog = format!("## {}\n", title);
+    if commits.is_empty() {
+        changelog.push_str("\nNo changes.\n");
+    }
+    for (section, entries) in sections.iter().filter(|(_, entries)| !entries.is_empty()) {
+        changelog.push_str(&format!("\n### {}\n\n{}\n", section, entries.join("\n")));
+    }
+    changelog
+}
+
+/// Link the issue keys of `text` to Jira, keys only found in the rest of the message are appended
+fn link_issue_keys(text: &str, message: &str, jira_host: Option<&Url>) -> String {
+    let regex = ISSUE_KEY.get_or_init(|| Regex::new(r"\b[A-Z][A-Z0-9]+-\d+\b").ok());
+    let (host, regex) = match (jira_host, regex) {
+        (Some(host), Some(regex)) => (host, regex),
+        _ => return text.to_string(),
+    };
+    let link = |key: &str| match browse_url(host, key) {
+        Ok(url) => format!("[{}]({})", key, url),
+        Err(_) => key.to_string(),
+    };
+
+    let linked = regex.replace_all(text, |captures: &Captures| link(&captures[0]));
+    let mut other_keys: Vec<&str> = Vec::new();
+    for key in regex.find_iter(message).map(|key| key.as_str()) {
+        let in_text = regex.find_iter(text).any(|k| k.as_str() == key);
+        if !in_text && !other_keys.contains(&key) {
+            other_keys.push(key);
+        }
+    }
+
+    match other_keys.is_empty() {
+        true => linked.to_string(),
+        false => {
+            let links: Vec<String> = other_keys.into_iter().map(link).collect();
+            format!("{} ({})", linked, links.join(", "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commit(id: &str, message: &str) -> CommitInfo {
+        CommitInfo {
+            id: id.to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    fn types() -> Vec<String> {
+        vec!["feat".to_string(), "fix".to_string()]
+    }
+
+    #[test]
+    fn test_render_sections() {
+        let commits = [
+            commit("a1b2c3d4e5", "fix(login): keep the session"),
+            commit("b2c3d4e5f6", "Update the readme"),
+            commit("c3d4e5f6a7", "feat!: drop the v1 api"),
+            commit("d4e5f6a7b8", "docs: explain SSO"),
+            commit("e5f6a7b8c9", "feat(login): add SSO"),
+        ];
+
+        assert_eq!(
+            render("v1.1.0", &commits, &types(), None),
+            "## v1.1.0\n\
+             \n### Breaking Changes\n\n- drop the v1 api (c3d4e5f)\n\
+             \n### Features\n\n- **login:** add SSO (e5f6a7b)\n\
+             \n### Bug Fixes\n\n- **login:** keep the session (a1b2c3d)\n\
+             \n### Documentation\n\n- explain SSO (d4e5f6a)\n\
+             \n### Other Changes\n\n- Update the readme (b2c3d4e)\n"
+        );
+    }
+
+    #[test]
+    fn test_render_jira_links() {
+        let commits = [
+            commit("a1b2c3d4e5", "fix: keep the session (PROJ-2)"),
+            commit("b2c3d4e5f6", "feat: add SSO\n\nRefs PROJ-1, PROJ-3"),
+        ];
+
+        let host = Url::parse("https://jira.example.com/").unwrap();
+
+        assert_eq!(
+            render("Unreleased", &commits, &types(), Some(&host)),
+            "## Unreleased\n\
+             \n### Features\n\n- add SSO ([PROJ-1](https://jira.example.com/browse/PROJ-1), \
+             [PROJ-3](https://jira.example.com/browse/PROJ-3)) (b2c3d4e)\n\
+             \n### Bug Fixes\n\n- keep the session ([PROJ-2](https://jira.example.com/browse/PROJ-2)) \
+             (a1b2c3d)\n"
+        );
+    }
+
+    #[test]
+    fn test_render_without_commits() {
+        assert_eq!(
+            render("Unreleased", &[], &types(), None),
+            "## Unreleased\n\nNo changes.\n"
+        );
+    }
+}
@@ -1,31 +1,227 @@
 use clap::{Args, Parser, Subcommand};
 
+use crate::command::DirtyTreeAction;
+use crate::config::{MergeStrategy, StartFrom, SyncStrategy};
+use crate::hooks::Hook;
+use crate::release::Bump;
+
 #[derive(Debug, Parser)]
 #[command(name = "wf")]
 #[command(about = "A tool to automate some common dev tasks", long_about = None)]
 pub struct WfArgs {
     #[command(subcommand)]
     pub command: WfCommands,
+    /// Never prompt, use the given options and the defaults instead
+    #[arg(
+        long,
+        visible_alias = "yes",
+        short = 'y',
+        global = true,
+        env = "WF_NON_INTERACTIVE"
+    )]
+    pub non_interactive: bool,
 }
 
 #[derive(Debug, Subcommand, PartialEq, Eq)]
 pub enum WfCommands {
     /// Initialize workflow app settings
-    Init,
+    Init(WfInitArgs),
     /// Testing subcommands
     #[command(subcommand, hide = true)]
     Test(WfTestCommands),
     /// Start a new workflow in the current repository
     Start {
-        #[arg(required = true, help = "Ticket id to create the branch from")]
-        ticket_id: String,
+        #[arg(help = "Ticket id to create the branch from, pick one of your issues if omitted")]
+        ticket_id: Option<String>,
+        /// Name of the new branch instead of the one built from the issue
+        #[arg(long, env = "WF_BRANCH_NAME")]
+        branch_name: Option<String>,
+        /// Branch to start from instead of the configured base branch
+        #[arg(long, env = "WF_BASE_BRANCH")]
+        base: Option<String>,
+        /// How to update the base branch from the remote instead of the configured one
+        #[arg(long, value_enum)]
+        from: Option<StartFrom>,
+        /// What to do with uncommitted changes, asked when omitted
+        #[arg(long, value_enum)]
+        dirty: Option<DirtyTreeAction>,
+        /// Create a worktree for the new branch instead of checking it out in place
+        #[arg(long)]
+        worktree: bool,
     },
     /// Push current work branch to remote repository
-    Push,
+    Push {
+        /// Overwrite the remote branch, only if it is still where it was at the last fetch
+        #[arg(long)]
+        force_with_lease: bool,
+    },
+    /// Open a pull request for the current work branch
+    Pr {
+        /// Title of the pull request instead of the one built from the issue
+        #[arg(long, env = "WF_PR_TITLE")]
+        title: Option<String>,
+        /// Branch to merge into instead of the configured base branch
+        #[arg(long, env = "WF_BASE_BRANCH")]
+        base: Option<String>,
+    },
+    /// Rebase or merge the updated base branch into the current work branch
+    Sync {
+        /// How to bring the base branch changes instead of the configured strategy
+        #[arg(long, value_enum, conflicts_with_all = ["resume", "abort"])]
+        strategy: Option<SyncStrategy>,
+        /// Resume the sync once the conflicts are resolved
+        #[arg(long = "continue", conflicts_with = "abort")]
+        resume: bool,
+        /// Abort the sync stopped on conflicts
+        #[arg(long)]
+        abort: bool,
+    },
+    /// Merge the current work branch into the base branch
+    Finish {
+        /// How to merge the branch instead of the configured strategy
+        #[arg(long, value_enum)]
+        strategy: Option<MergeStrategy>,
+        /// Delete the local and remote branch once merged, the base branch is pushed first
+        #[arg(long)]
+        delete_branch: bool,
+        /// Message of the merge commit
+        #[arg(long, short, env = "WF_COMMIT_MESSAGE")]
+        message: Option<String>,
+    },
+    /// Show the issue and the state of the current work branch
+    Status,
+    /// Check the commit messages of a range follow Conventional Commits
+    LintCommits {
+        #[arg(
+            help = "Range of commits to check, e.g. v1.0.0..HEAD, the commits missing from the remote base branch if omitted"
+        )]
+        range: Option<String>,
+    },
+    /// Print the changes since the last tag in Markdown, grouped by commit type
+    Changelog {
+        /// Tag or commit to start from instead of the last tag
+        #[arg(long)]
+        from: Option<String>,
+        /// Title of the changelog, "Unreleased" when omitted
+        #[arg(long)]
+        title: Option<String>,
+    },
+    /// Start and finish releases: release branch, version bump, merge into main and tag
+    #[command(subcommand)]
+    Release(WfReleaseCommands),
+    /// Start and finish hotfixes: branch from main, merge into main and the base branch
+    #[command(subcommand)]
+    Hotfix(WfHotfixCommands),
+    /// Cherry-pick the commits of an issue onto release branches in pushed backport branches
+    Backport {
+        #[arg(help = "Ticket id of the commits to backport")]
+        ticket_id: String,
+        /// Branches to backport to, separated by commas
+        #[arg(long, required = true, value_delimiter = ',')]
+        to: Vec<String>,
+        /// Comment the backport branches on the issue
+        #[arg(long)]
+        comment: bool,
+    },
+    /// List and remove the worktrees created for issues
+    #[command(subcommand)]
+    Worktree(WfWorktreeCommands),
+    /// Manage the git hooks adding the issue key to commit messages and checking them
+    #[command(subcommand)]
+    Hooks(WfHooksCommands),
     /// Do nothing, just to test
     Noop,
 }
 
+#[derive(Debug, Subcommand, PartialEq, Eq)]
+pub enum WfReleaseCommands {
+    /// Create the release branch of the next version from the base branch and bump the version
+    Start {
+        #[arg(
+            help = "Version to release, computed from the last tag and the commit types if omitted"
+        )]
+        version: Option<String>,
+        /// Part of the version to increment instead of the one required by the commit types
+        #[arg(long, value_enum, conflicts_with = "version")]
+        bump: Option<Bump>,
+    },
+    /// Merge the current release branch into main, tag it and merge it back into the base branch
+    Finish {
+        /// Push the main branch, the base branch and the tag once done
+        #[arg(long)]
+        push: bool,
+    },
+}
+
+#[derive(Debug, Subcommand, PartialEq, Eq)]
+pub enum WfHotfixCommands {
+    /// Create a hotfix branch for an issue from the main branch
+    Start {
+        #[arg(help = "Ticket id to create the branch from, pick one of your issues if omitted")]
+        ticket_id: Option<String>,
+        /// Name of the new branch instead of the one built from the issue
+        #[arg(long, env = "WF_BRANCH_NAME")]
+        branch_name: Option<String>,
+        /// How to update the main branch from the remote instead of the configured one
+        #[arg(long, value_enum)]
+        from: Option<StartFrom>,
+        /// What to do with uncommitted changes, asked when omitted
+        #[arg(long, value_enum)]
+        dirty: Option<DirtyTreeAction>,
+        /// Create a worktree for the new branch instead of checking it out in place
+        #[arg(long)]
+        worktree: bool,
+    },
+    /// Merge the current hotfix branch into the main branch and the base branch
+    Finish {
+        /// Push the main branch and the base branch once done
+        #[arg(long)]
+        push: bool,
+    },
+}
+
+#[derive(Debug, Subcommand, PartialEq, Eq)]
+pub enum WfWorktreeCommands {
+    /// List the worktrees with the issue of their branch
+    List,
+    /// Remove the worktree of an issue or a branch, refused when it has changes
+    Remove {
+        #[arg(help = "Ticket id or branch name of the worktree")]
+        name: String,
+    },
+}
+
+#[derive(Debug, Subcommand, PartialEq, Eq)]
+pub enum WfHooksCommands {
+    /// Install the prepare-commit-msg and commit-msg hooks, existing hooks are kept and run first
+    Install,
+    /// Run a hook, called by the installed hooks
+    #[command(hide = true)]
+    Run {
+        #[arg(value_enum)]
+        hook: Hook,
+        /// Arguments given by git to the hook
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+}
+
+#[derive(Debug, Args, PartialEq, Eq)]
+pub struct WfInitArgs {
+    /// Url of the Jira instance
+    #[arg(long, env = "WF_JIRA_HOST")]
+    pub jira_host: Option<String>,
+    /// Jira username
+    #[arg(long, env = "WF_JIRA_USER")]
+    pub jira_user: Option<String>,
+    /// Read the Jira token from the first line of the standard input
+    #[arg(long)]
+    pub jira_token_stdin: bool,
+    /// Base branch for features in the current repository
+    #[arg(long, env = "WF_BASE_BRANCH")]
+    pub base: Option<String>,
+}
+
 #[derive(Debug, Args, PartialEq, Eq)]
 pub struct WfTestArgs {
     #[command(subcommand)]
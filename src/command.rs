@@ -1,40 +1,128 @@
 use crate::{
-    config::{Config, RepoConfig},
+    backport::{self, BackportError},
+    branch_name::{
+        current_issue_key, current_user, issue_key_for_branch, issue_key_from_template,
+        render_branch_name, today, BranchNameContext,
+    },
+    changelog,
+    config::{
+        Config, IssueTrackerKind, JiraConfig, MergeStrategy, RepoConfig, StartFrom, SyncStrategy,
+        WorkflowEvent,
+    },
+    conventional::{self, ConventionalError},
     errors::WfError,
-    git::{to_branch_name, GitRepository},
-    init::{self, init_repo_config},
-    jira::JiraServer,
+    git::{validate_branch_name, CommitInfo, GitError, GitRepository, Worktree},
+    hooks::{self, Hook, Installed},
+    hosting::{HostingProvider, NewPullRequest, RemoteRepository},
+    init::{self, init_repo_config, BranchNameValidator, ConfigAnswers},
+    prompt::{PromptError, Prompter},
+    release::{self, Bump, ReleaseError, Version},
+    tracker::{Issue, IssueTracker},
 };
 
-use inquire::{Confirm, Text};
+use std::fmt::{self, Display};
+use std::path::{Path, PathBuf};
 
-pub fn command_init_config(config: Config) -> Result<Config, WfError> {
-    if config.is_set() &&
-        Confirm::new("Warning, your configuration is already defined, do you want to continue and overwrite it?")
-            .with_default(false)
-            .prompt()? {
+use clap::ValueEnum;
+use inquire::{Confirm, Select, Text};
+use url::Url;
+
+/// Answers to the questions of the start command given on the command line
+#[derive(Debug, Default)]
+pub struct StartAnswers<'a> {
+    pub ticket_id: Option<&'a str>,
+    pub branch_name: Option<&'a str>,
+    pub base: Option<&'a str>,
+    pub from: Option<StartFrom>,
+    pub dirty: Option<DirtyTreeAction>,
+    /// Create a worktree for the new branch even if it is not configured
+    pub worktree: bool,
+}
+
+/// What `start` does with uncommitted changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DirtyTreeAction {
+    /// Keep the changes in the working tree, it fails if they conflict with the new branch
+    Carry,
+    /// Stash the changes and restore them on the new branch
+    Stash,
+    /// Stop without creating the branch
+    Abort,
+}
+
+impl Display for DirtyTreeAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DirtyTreeAction::Carry => write!(f, "Carry the changes over to the new branch"),
+            DirtyTreeAction::Stash => {
+                write!(f, "Stash the changes and restore them on the new branch")
+            }
+            DirtyTreeAction::Abort => write!(f, "Abort"),
+        }
+    }
+}
+
+/// Answers to the questions of the pr command given on the command line
+#[derive(Debug, Default)]
+pub struct PrAnswers<'a> {
+    pub title: Option<&'a str>,
+    pub base: Option<&'a str>,
+}
+
+/// Answers to the questions of the finish command given on the command line
+#[derive(Debug, Default)]
+pub struct FinishAnswers<'a> {
+    pub strategy: Option<MergeStrategy>,
+    pub delete_branch: bool,
+    pub message: Option<&'a str>,
+}
+
+/// Ask before overwriting an existing configuration, `--yes` accepts it
+fn confirm_overwrite(prompter: &impl Prompter, message: &str) -> Result<bool, WfError> {
+    if !prompter.is_interactive() {
+        return Ok(true);
+    }
+
+    Ok(prompter.confirm(Confirm::new(message).with_default(false))?)
+}
+
+pub fn command_init_config(
+    config: Config,
+    prompter: &impl Prompter,
+    answers: &ConfigAnswers,
+) -> Result<Config, WfError> {
+    if config.is_set()
+        && !confirm_overwrite(
+            prompter,
+            "Warning, your configuration is already defined, do you want to continue and overwrite it?",
+        )?
+    {
         return Ok(config);
     }
 
-    let new_config = init::init_config(Some(&config))?;
-    config.save()?;
+    let new_config = init::init_config(Some(&config), prompter, answers)?;
+    new_config.save()?;
     Ok(new_config)
 }
 
 pub fn command_init_repo(
     repo_config: RepoConfig,
     repo: &impl GitRepository,
+    prompter: &impl Prompter,
+    base: Option<&str>,
 ) -> Result<RepoConfig, WfError> {
-    if repo_config.is_set() &&
-        Confirm::new("Warning, your repository configuration is already defined, do you want to continue and overwrite it?")
-            .with_default(false)
-            .prompt()? {
+    if repo_config.is_set()
+        && !confirm_overwrite(
+            prompter,
+            "Warning, your repository configuration is already defined, do you want to continue and overwrite it?",
+        )?
+    {
         return Ok(repo_config);
     }
 
     let branches = repo.branches()?;
 
-    let new_config = init_repo_config(Some(&repo_config), &branches)?;
+    let new_config = init_repo_config(Some(&repo_config), &branches, prompter, base)?;
     match repo.workdir() {
         Some(path) => {
             new_config.save(path)?;
@@ -45,38 +133,3137 @@ pub fn command_init_repo(
 }
 
 pub async fn command_start(
-    config: &Config,
     repo_config: &RepoConfig,
     repo: &impl GitRepository,
-    ticket_id: &str,
+    tracker: &dyn IssueTracker,
+    prompter: &impl Prompter,
+    answers: &StartAnswers<'_>,
+) -> Result<(), WfError> {
+    let dev_branch_name = match answers.base {
+        Some(base) => base,
+        None => repo_config
+            .branches
+            .as_ref()
+            .ok_or(WfError::ConfigurationNotSet)?
+            .dev
+            .as_ref(),
+    };
+
+    start_branch(
+        repo_config,
+        repo,
+        tracker,
+        prompter,
+        answers,
+        dev_branch_name,
+        "",
+    )
+    .await
+}
+
+/// Start a hotfix: like `start` but the branch is created from the main branch and its name
+/// gets the hotfix prefix
+pub async fn command_hotfix_start(
+    repo_config: &RepoConfig,
+    repo: &impl GitRepository,
+    tracker: &dyn IssueTracker,
+    prompter: &impl Prompter,
+    answers: &StartAnswers<'_>,
 ) -> Result<(), WfError> {
-    let jira = config
-        .jira
+    let branches = repo_config
+        .branches
         .as_ref()
-        .ok_or(WfError::ConfigurationNotSet)
-        .and_then(|c| JiraServer::try_from(c).map_err(WfError::from))?;
+        .ok_or(WfError::ConfigurationNotSet)?;
+    let main_branch_name = match answers.base {
+        Some(base) => base,
+        None => branches
+            .main
+            .as_deref()
+            .ok_or(ReleaseError::MainBranchNotSet)?,
+    };
+
+    start_branch(
+        repo_config,
+        repo,
+        tracker,
+        prompter,
+        answers,
+        main_branch_name,
+        branches.hotfix_prefix(),
+    )
+    .await
+}
+
+/// Create a branch for an issue from `base_branch_name`, the default name starts with `prefix`
+async fn start_branch(
+    repo_config: &RepoConfig,
+    repo: &impl GitRepository,
+    tracker: &dyn IssueTracker,
+    prompter: &impl Prompter,
+    answers: &StartAnswers<'_>,
+    base_branch_name: &str,
+    prefix: &str,
+) -> Result<(), WfError> {
+    let start_config = repo_config.start.clone().unwrap_or_default();
+    // Uncommitted changes stay where they are when the branch gets its own worktree
+    let worktree_dir = match answers.worktree || start_config.worktree {
+        true => Some(worktrees_dir(repo, start_config.worktrees_dir.as_deref())?),
+        false => None,
+    };
+    let dirty_action = match worktree_dir {
+        Some(_) => None,
+        None => dirty_tree_action(repo, prompter, answers.dirty)?,
+    };
+
+    let issue = match answers.ticket_id {
+        Some(ticket_id) => tracker.get_issue(ticket_id).await?,
+        None => {
+            let query = repo_config
+                .issues_query
+                .as_deref()
+                .unwrap_or(tracker.default_query());
+            pick_issue(tracker, query, prompter).await?
+        }
+    };
+    let user = current_user();
+    let date = today();
+    let default_branch_name = render_branch_name(
+        repo_config.branch_name.as_ref(),
+        &BranchNameContext {
+            issue: &issue,
+            user: user.as_deref(),
+            date: &date,
+        },
+    );
+    let default_branch_name = match default_branch_name.starts_with(prefix) {
+        true => default_branch_name,
+        false => format!("{}{}", prefix, default_branch_name),
+    };
+
+    println!("Found issue {}: {}", issue.key, issue.summary);
+    let new_branch_name = prompter.text(
+        Text::new("Branch name:")
+            .with_help_message("You can change the default branch name here.")
+            .with_initial_value(&default_branch_name)
+            .with_validator(BranchNameValidator::default()),
+        answers.branch_name,
+        "--branch-name or WF_BRANCH_NAME",
+    )?;
+
+    validate_branch_name(&new_branch_name)?;
+
+    let start_from = answers.from.unwrap_or(start_config.from);
+
+    let worktree_path = worktree_dir.map(|dir| dir.join(new_branch_name.replace('/', "-")));
+    if let Some(path) = &worktree_path {
+        if path.exists() {
+            return Err(GitError::WorktreeExists(path.display().to_string()).into());
+        }
+    }
+
+    let stashed = dirty_action == Some(DirtyTreeAction::Stash);
+    if stashed {
+        repo.stash(&format!("wf start {}", new_branch_name))?;
+    }
+
+    let result = update_base_branch(repo, base_branch_name, start_from).and_then(|from_branch| {
+        match &worktree_path {
+            Some(path) => repo.add_worktree(path, &new_branch_name, &from_branch)?,
+            None => repo.create_and_checkout_branch(&new_branch_name, &from_branch)?,
+        }
+        Ok(from_branch)
+    });
+
+    // Restore the changes on the new branch, or where they were if it could not be created
+    if stashed {
+        if let Err(e) = repo.stash_pop() {
+            eprintln!("Warning, {}", e);
+        }
+    }
+
+    let from_branch = result?;
+    println!(
+        "Branch {} created from {} with issue {}",
+        new_branch_name, from_branch, issue.key
+    );
+    if let Some(path) = &worktree_path {
+        println!("Worktree created in {}", path.display());
+    }
+
+    move_issue(
+        tracker,
+        &issue,
+        repo_config.transition_for(WorkflowEvent::Start),
+    )
+    .await;
+
+    Ok(())
+}
+
+/// Directory of the worktrees, relative paths are resolved from the repository
+fn worktrees_dir(repo: &impl GitRepository, configured: Option<&Path>) -> Result<PathBuf, WfError> {
+    let workdir = repo.workdir().ok_or(WfError::NoGitWorkingDirectory)?;
+    Ok(match configured {
+        Some(dir) => workdir.join(dir),
+        None => {
+            let name = workdir.file_name().unwrap_or_default().to_string_lossy();
+            workdir.with_file_name(format!("{}.worktrees", name))
+        }
+    })
+}
+
+/// Ask what to do with uncommitted changes, `None` when the working tree is clean
+fn dirty_tree_action(
+    repo: &impl GitRepository,
+    prompter: &impl Prompter,
+    answer: Option<DirtyTreeAction>,
+) -> Result<Option<DirtyTreeAction>, WfError> {
+    if repo.is_clean()? {
+        return Ok(None);
+    }
+
+    let message = "You have uncommitted changes, what do you want to do with them?";
+    let action = match answer {
+        Some(action) => action,
+        None if prompter.is_interactive() => prompter.select(
+            Select::new(
+                message,
+                vec![
+                    DirtyTreeAction::Carry,
+                    DirtyTreeAction::Stash,
+                    DirtyTreeAction::Abort,
+                ],
+            ),
+            None,
+        )?,
+        None => {
+            return Err(
+                PromptError::InputRequired(message.to_string(), "--dirty".to_string()).into(),
+            )
+        }
+    };
+
+    match action {
+        DirtyTreeAction::Abort => Err(GitError::DirtyWorkingTree.into()),
+        action => Ok(Some(action)),
+    }
+}
+
+/// Fetch the base branch from the remote and return the branch to start from.
+///
+/// When the remote cannot be reached or the local base branch has diverged, a warning is printed and
+/// the local base branch is used as it is.
+fn update_base_branch(
+    repo: &impl GitRepository,
+    base: &str,
+    start_from: StartFrom,
+) -> Result<String, WfError> {
+    println!("Fetching {} from {}", base, repo.remote());
+    if let Err(e) = repo.fetch(base) {
+        eprintln!(
+            "Warning, cannot fetch {} from {}, starting from the local branch: {}",
+            base,
+            repo.remote(),
+            e
+        );
+        return Ok(base.to_string());
+    }
+
+    let remote_base = repo.remote_branch(base);
+    if let Some((ahead, behind)) = repo.ahead_behind(base, &remote_base)? {
+        if ahead > 0 && behind > 0 {
+            eprintln!(
+                "Warning, {} has diverged from {}: {} commit(s) ahead and {} behind",
+                base, remote_base, ahead, behind
+            );
+        }
+    }
+
+    match start_from {
+        StartFrom::FastForward => match repo.fast_forward(base) {
+            Ok(()) | Err(GitError::Diverged(..)) => Ok(base.to_string()),
+            Err(e) => Err(e.into()),
+        },
+        StartFrom::Remote => Ok(remote_base),
+        StartFrom::Local => Ok(base.to_string()),
+    }
+}
+
+pub async fn command_push(
+    repo_config: &RepoConfig,
+    repo: &impl GitRepository,
+    tracker: Option<&dyn IssueTracker>,
+    force_with_lease: bool,
+) -> Result<(), WfError> {
+    repo.push(force_with_lease)?;
+
+    if let Some(status) = repo_config.transition_for(WorkflowEvent::Push) {
+        if let Some((tracker, issue)) = current_issue_or_warn(repo_config, tracker, repo).await {
+            move_issue(tracker, &issue, Some(status)).await;
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn command_pr(
+    repo_config: &RepoConfig,
+    repo: &impl GitRepository,
+    hosting: &impl HostingProvider,
+    tracker: Option<&dyn IssueTracker>,
+    prompter: &impl Prompter,
+    answers: &PrAnswers<'_>,
+) -> Result<(), WfError> {
+    let dev_branch_name = match answers.base {
+        Some(base) => base,
+        None => repo_config
+            .branches
+            .as_ref()
+            .ok_or(WfError::ConfigurationNotSet)?
+            .dev
+            .as_str(),
+    };
+
+    let branch_name = repo.current_branch()?;
+
+    if repo.needs_push()? {
+        println!("Pushing branch {} to {}", branch_name, repo.remote());
+        repo.push(false)?;
+    }
+
+    let remote = RemoteRepository::from_url(&repo.remote_url()?)?;
+
+    let current_issue = current_issue(repo_config, tracker, repo).await?;
 
+    let (default_title, body) = match &current_issue {
+        Some((_, issue)) => (
+            format!("{} {}", issue.key, issue.summary),
+            format!("Issue: {}", issue.url),
+        ),
+        None => (branch_name.clone(), String::new()),
+    };
+
+    let title = prompter.text(
+        Text::new("Pull request title:")
+            .with_help_message("You can change the default title here.")
+            .with_initial_value(&default_title),
+        answers.title,
+        "--title or WF_PR_TITLE",
+    )?;
+
+    let pull_request = hosting
+        .create_pull_request(
+            &remote,
+            &NewPullRequest {
+                title,
+                body,
+                head: branch_name.clone(),
+                base: dev_branch_name.to_string(),
+            },
+        )
+        .await?;
+
+    println!(
+        "Pull request #{} opened from {} to {}: {}",
+        pull_request.number, branch_name, dev_branch_name, pull_request.url
+    );
+
+    if let Some((tracker, issue)) = &current_issue {
+        move_issue(
+            *tracker,
+            issue,
+            repo_config.transition_for(WorkflowEvent::Pr),
+        )
+        .await;
+    }
+
+    Ok(())
+}
+
+pub async fn command_finish(
+    repo_config: &RepoConfig,
+    repo: &impl GitRepository,
+    tracker: Option<&dyn IssueTracker>,
+    prompter: &impl Prompter,
+    answers: &FinishAnswers<'_>,
+) -> Result<(), WfError> {
     let dev_branch_name = repo_config
         .branches
         .as_ref()
         .ok_or(WfError::ConfigurationNotSet)?
         .dev
-        .as_ref();
+        .as_str();
 
-    let issue = jira.get_issue(ticket_id).await?;
-    let default_branch_name = format!("{}-{}", issue.key, to_branch_name(&issue.summary));
+    let branch_name = repo.current_branch()?;
+    if branch_name == dev_branch_name {
+        return Err(WfError::OnBaseBranch(branch_name));
+    }
 
-    println!("Found issue {}: {}", issue.key, issue.summary);
-    let new_branch_name = Text::new("Branch name:")
-        .with_help_message("You can change the default branch name here.")
-        .with_initial_value(&default_branch_name)
-        .prompt()?;
+    if !repo.is_clean()? {
+        return Err(GitError::DirtyWorkingTree.into());
+    }
+
+    // Finishing from the worktree created for the branch, the merge is done from the main checkout
+    // as the base branch cannot be checked out in two worktrees
+    let worktree = repo
+        .worktrees()?
+        .into_iter()
+        .find(|w| w.branch.as_deref() == Some(branch_name.as_str()));
+    let main_worktree;
+    let repo = match &worktree {
+        Some(_) => {
+            main_worktree = repo.main_worktree()?;
+            if !main_worktree.is_clean()? {
+                let path = main_worktree.workdir().unwrap_or(Path::new("."));
+                return Err(GitError::DirtyWorktree(path.display().to_string()).into());
+            }
+            &main_worktree
+        }
+        None => repo,
+    };
+
+    let finish_config = repo_config.finish.clone().unwrap_or_default();
+    let strategy = answers.strategy.unwrap_or(finish_config.strategy);
+    let delete_branch = answers.delete_branch || finish_config.delete_branch;
+
+    println!("Updating {} from {}", dev_branch_name, repo.remote());
+    repo.fetch(dev_branch_name)?;
+    repo.fast_forward(dev_branch_name)?;
+
+    let current_issue = current_issue(repo_config, tracker, repo).await?;
+
+    let default_message = match (&current_issue, strategy) {
+        (Some((_, issue)), MergeStrategy::Squash) => format!("{} {}", issue.key, issue.summary),
+        _ => format!("Merge branch '{}' into {}", branch_name, dev_branch_name),
+    };
+
+    let message = prompter.text(
+        Text::new("Commit message:")
+            .with_help_message("You can change the default message here.")
+            .with_initial_value(&default_message),
+        answers.message,
+        "--message or WF_COMMIT_MESSAGE",
+    )?;
+
+    repo.merge_into(&branch_name, dev_branch_name, strategy, &message)?;
+    println!("Branch {} merged into {}", branch_name, dev_branch_name);
+
+    // Only once merged, on conflicts the branch is fixed in its worktree
+    if let Some(worktree) = &worktree {
+        repo.remove_worktree(&worktree.path)?;
+        println!("Worktree {} removed", worktree.path.display());
+    }
+
+    if delete_branch {
+        repo.delete_branch(&branch_name)?;
+        // The remote branch is the only published copy of the work until the merge is pushed
+        repo.push_refs(&[format!("refs/heads/{}", dev_branch_name)])?;
+        println!("Branch {} pushed to {}", dev_branch_name, repo.remote());
+        repo.delete_remote_branch(&branch_name)?;
+        println!("Branch {} deleted", branch_name);
+    }
+
+    if let Some((tracker, issue)) = &current_issue {
+        move_issue(
+            *tracker,
+            issue,
+            repo_config.transition_for(WorkflowEvent::Finish),
+        )
+        .await;
+    }
+
+    Ok(())
+}
+
+/// Bring the changes of the base branch, updated from the remote, into the current work branch
+pub fn command_sync(
+    repo_config: &RepoConfig,
+    repo: &impl GitRepository,
+    strategy: Option<SyncStrategy>,
+) -> Result<(), WfError> {
+    let dev_branch_name = repo_config
+        .branches
+        .as_ref()
+        .ok_or(WfError::ConfigurationNotSet)?
+        .dev
+        .as_str();
+
+    if let Some(operation) = repo.operation_in_progress()? {
+        return Err(GitError::OperationInProgress(operation).into());
+    }
+
+    let branch_name = repo.current_branch()?;
+    if branch_name == dev_branch_name {
+        return Err(WfError::OnBaseBranch(branch_name));
+    }
+
+    if !repo.is_clean()? {
+        return Err(GitError::DirtyWorkingTree.into());
+    }
+
+    let strategy = strategy.unwrap_or(repo_config.sync.clone().unwrap_or_default().strategy);
+
+    println!("Fetching {} from {}", dev_branch_name, repo.remote());
+    repo.fetch(dev_branch_name)?;
+    let upstream = repo.remote_branch(dev_branch_name);
+
+    let result = match strategy {
+        SyncStrategy::Merge => {
+            println!("Merging {} into {}", upstream, branch_name);
+            let message = format!("Merge branch '{}' into {}", dev_branch_name, branch_name);
+            repo.merge(&upstream, &message)
+        }
+        SyncStrategy::Rebase => {
+            println!("Rebasing {} onto {}", branch_name, upstream);
+            repo.rebase(&upstream)
+        }
+    };
+    report_conflicts(result)?;
+    println!("Branch {} is up to date with {}", branch_name, upstream);
+
+    let remote_branch = repo.remote_branch(&branch_name);
+    if let Some((_, behind)) = repo.ahead_behind(&branch_name, &remote_branch)? {
+        if behind > 0 {
+            println!(
+                "The history was rewritten, use push --force-with-lease to update {}",
+                remote_branch
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Resume the sync stopped on conflicts once they are resolved
+pub fn command_sync_continue(repo: &impl GitRepository) -> Result<(), WfError> {
+    let operation = repo
+        .operation_in_progress()?
+        .ok_or(GitError::NothingInProgress)?;
+
+    report_conflicts(repo.continue_operation())?;
+    println!("The {} is complete", operation);
+    Ok(())
+}
+
+/// Abort the sync stopped on conflicts and go back to the branch as it was before
+pub fn command_sync_abort(repo: &impl GitRepository) -> Result<(), WfError> {
+    let operation = repo
+        .operation_in_progress()?
+        .ok_or(GitError::NothingInProgress)?;
+
+    repo.abort_operation()?;
+    println!("The {} was aborted", operation);
+    Ok(())
+}
+
+/// List the conflicting files one per line with how to go on when the sync stopped on them
+fn report_conflicts(result: Result<(), GitError>) -> Result<(), WfError> {
+    if let Err(
+        GitError::RebaseConflict(_, files)
+        | GitError::MergeConflict(_, _, files)
+        | GitError::UnresolvedConflicts(files),
+    ) = &result
+    {
+        eprintln!("Conflicting files:");
+        for file in files {
+            eprintln!("    {}", file);
+        }
+        eprintln!(
+            "Resolve the conflicts, add the files with git add and run sync --continue, \
+            or run sync --abort to go back"
+        );
+    }
+
+    Ok(result?)
+}
+
+/// Linked worktree with the issue of its branch, listed by the worktree command
+#[derive(Debug)]
+pub struct IssueWorktree {
+    pub issue_key: Option<String>,
+    pub worktree: Worktree,
+}
+
+impl Display for IssueWorktree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:<12} {:<40} {}",
+            self.issue_key.as_deref().unwrap_or("-"),
+            self.worktree.branch.as_deref().unwrap_or("(detached)"),
+            self.worktree.path.display()
+        )
+    }
+}
+
+/// Linked worktrees of the repository with the issue of their branch
+pub fn command_worktree_list(
+    repo_config: &RepoConfig,
+    repo: &impl GitRepository,
+    tracker: Option<&dyn IssueTracker>,
+) -> Result<Vec<IssueWorktree>, WfError> {
+    Ok(repo
+        .worktrees()?
+        .into_iter()
+        .map(|worktree| IssueWorktree {
+            issue_key: worktree.branch.as_deref().and_then(|branch| {
+                issue_key_for_branch(repo_config.branch_name.as_ref(), tracker, branch)
+            }),
+            worktree,
+        })
+        .collect())
+}
+
+/// Remove the worktree of an issue or a branch
+pub fn command_worktree_remove(
+    repo_config: &RepoConfig,
+    repo: &impl GitRepository,
+    tracker: Option<&dyn IssueTracker>,
+    name: &str,
+) -> Result<(), WfError> {
+    let worktree = command_worktree_list(repo_config, repo, tracker)?
+        .into_iter()
+        .find(|w| {
+            w.issue_key.as_deref() == Some(name) || w.worktree.branch.as_deref() == Some(name)
+        })
+        .ok_or_else(|| GitError::WorktreeNotFound(name.to_string()))?
+        .worktree;
+
+    repo.remove_worktree(&worktree.path)?;
+    println!("Worktree {} removed", worktree.path.display());
+    Ok(())
+}
+
+/// Install the git hooks adding the issue key to commit messages, they call `executable`
+pub fn command_install_hooks(repo: &impl GitRepository, executable: &Path) -> Result<(), WfError> {
+    let hooks_dir = repo.hooks_dir()?;
+
+    for hook in Hook::ALL {
+        match hooks::install_hook(&hooks_dir, hook, executable)? {
+            Installed::Created => {
+                println!("Hook {} installed in {}", hook.name(), hooks_dir.display())
+            }
+            Installed::Updated => {
+                println!("Hook {} updated in {}", hook.name(), hooks_dir.display())
+            }
+            Installed::Chained(previous) => println!(
+                "Hook {} installed in {}, the existing one was moved to {} and runs first",
+                hook.name(),
+                hooks_dir.display(),
+                previous.display()
+            ),
+        }
+    }
+
+    Ok(())
+}
 
-    repo.create_and_checkout_branch(&new_branch_name, dev_branch_name)?;
+/// Run an installed hook with the arguments git gave it.
+///
+/// Messages are checked against Conventional Commits when configured. The issue key is only
+/// handled when the current branch has one, e.g. not during a rebase. The tracker is never
+/// contacted, its kind is enough to find the key.
+pub fn command_run_hook(
+    repo_config: &RepoConfig,
+    repo: &impl GitRepository,
+    hook: Hook,
+    args: &[String],
+) -> Result<(), WfError> {
+    let file = match args.first() {
+        Some(file) => Path::new(file),
+        None => return Ok(()),
+    };
+    let conventional_commits = repo_config.conventional_commits.as_ref();
+    if let (Hook::CommitMsg, Some(conventional_commits)) = (hook, conventional_commits) {
+        hooks::lint_commit_msg(file, conventional_commits)?;
+    }
+
+    let key = match repo.current_branch().ok().and_then(|branch| {
+        issue_key_from_template(repo_config.branch_name.as_ref(), &branch)
+            .or_else(|| repo_config.tracker.find_issue_key(&branch))
+    }) {
+        Some(key) => key,
+        None => return Ok(()),
+    };
+
+    let mut config = repo_config.commit_message.clone().unwrap_or_default();
+    if config.template.is_none() && conventional_commits.is_some() {
+        config.template = Some(hooks::CONVENTIONAL_TEMPLATE.to_string());
+    }
+    match hook {
+        Hook::PrepareCommitMsg => {
+            let source = args.get(1).map(String::as_str);
+            hooks::prepare_commit_msg(file, source, &key, &config)?
+        }
+        Hook::CommitMsg => hooks::check_commit_msg(file, &key, &config)?,
+    }
+    Ok(())
+}
+
+/// Check the messages of a range of commits follow Conventional Commits, invalid ones are printed.
+///
+/// Without a range the commits missing from the remote base branch are checked.
+pub fn command_lint_commits(
+    repo_config: &RepoConfig,
+    repo: &impl GitRepository,
+    range: Option<&str>,
+) -> Result<(), WfError> {
+    let range = match range {
+        Some(range) => range.to_string(),
+        None => {
+            let dev_branch_name = repo_config
+                .branches
+                .as_ref()
+                .ok_or(WfError::ConfigurationNotSet)?
+                .dev
+                .as_str();
+            format!("{}..HEAD", repo.remote_branch(dev_branch_name))
+        }
+    };
+    let config = repo_config.conventional_commits.clone().unwrap_or_default();
+
+    let commits = repo.commits(&range)?;
+    let checked: Vec<&CommitInfo> = commits
+        .iter()
+        .filter(|commit| !hooks::is_generated(&commit.message))
+        .collect();
+    let mut invalid = 0;
+    for commit in &checked {
+        if let Err(e) = conventional::lint(&commit.message, &config) {
+            println!("{} {}\n    {}", commit.short_id(), commit.subject(), e);
+            invalid += 1;
+        }
+    }
+
+    if invalid > 0 {
+        return Err(ConventionalError::InvalidCommits(invalid, checked.len(), range).into());
+    }
     println!(
-        "Branch {} created from {} with issue {}",
-        new_branch_name, dev_branch_name, ticket_id
+        "{} commits in {} follow Conventional Commits",
+        checked.len(),
+        range
+    );
+    Ok(())
+}
+
+/// Markdown changelog of the commits since `from`, the last tag when not given.
+///
+/// Issue keys are linked to Jira when it is the issue tracker of the repository.
+pub fn command_changelog(
+    repo_config: &RepoConfig,
+    repo: &impl GitRepository,
+    jira: Option<&JiraConfig>,
+    from: Option<&str>,
+    title: Option<&str>,
+) -> Result<String, WfError> {
+    let from = match from {
+        Some(from) => Some(from.to_string()),
+        None => {
+            let release_config = repo_config.release.clone().unwrap_or_default();
+            repo.last_tag("HEAD", release_config.tag_prefix())?
+        }
+    };
+    let range = match from {
+        Some(from) => format!("{}..HEAD", from),
+        None => "HEAD".to_string(),
+    };
+    let config = repo_config.conventional_commits.clone().unwrap_or_default();
+    let jira_host = jira
+        .filter(|_| repo_config.tracker == IssueTrackerKind::Jira)
+        .and_then(|jira| Url::parse(&jira.host).ok());
+
+    let commits = repo.commits(&range)?;
+    Ok(changelog::render(
+        title.unwrap_or("Unreleased"),
+        &commits,
+        &config.types,
+        jira_host.as_ref(),
+    ))
+}
+
+/// Create the release branch of the next version from the updated base branch and bump the
+/// version in the version files.
+///
+/// Unless given, the version is computed from the last tag and the types of the commits since.
+pub fn command_release_start(
+    repo_config: &RepoConfig,
+    repo: &impl GitRepository,
+    version: Option<&str>,
+    bump: Option<Bump>,
+) -> Result<(), WfError> {
+    let branches = repo_config
+        .branches
+        .as_ref()
+        .ok_or(WfError::ConfigurationNotSet)?;
+    let release_config = repo_config.release.clone().unwrap_or_default();
+    let tag_prefix = release_config.tag_prefix();
+
+    if !repo.is_clean()? {
+        return Err(GitError::DirtyWorkingTree.into());
+    }
+
+    let base = update_base_branch(repo, &branches.dev, StartFrom::FastForward)?;
+
+    let workdir = repo.workdir().ok_or(WfError::NoGitWorkingDirectory)?;
+    let files: Vec<PathBuf> = match release_config.version_files.is_empty() {
+        false => release_config.version_files.clone(),
+        true => release::DEFAULT_VERSION_FILES
+            .iter()
+            .map(PathBuf::from)
+            .filter(|file| workdir.join(file).exists())
+            .collect(),
+    };
+
+    // Without a release yet, the version files tell where the project is
+    let last_tag = repo.last_tag(&base, tag_prefix)?;
+    let released = match &last_tag {
+        Some(tag) => Some(Version::parse(tag, tag_prefix)?),
+        None => files
+            .iter()
+            .map(|file| release::read_version(workdir, file))
+            .find_map(Result::transpose)
+            .transpose()?,
+    };
+    let previous = released.unwrap_or_default();
+    let version = match version {
+        Some(version) => Version::parse(version, tag_prefix)?,
+        None => {
+            let range = match &last_tag {
+                Some(tag) => format!("{}..{}", tag, base),
+                None => base.clone(),
+            };
+            let bump = match bump {
+                Some(bump) => bump,
+                None => release::bump_for(&repo.commits(&range)?),
+            };
+            previous.bump(bump)
+        }
+    };
+    if released.is_some() && version <= previous {
+        return Err(
+            ReleaseError::VersionNotIncreased(version.to_string(), previous.to_string()).into(),
+        );
+    }
+
+    let branch_name = format!("{}{}", branches.release_prefix(), version);
+    validate_branch_name(&branch_name)?;
+    repo.create_and_checkout_branch(&branch_name, &base)?;
+    println!(
+        "Release {} started on {} from {}",
+        version, branch_name, base
     );
 
+    if files.is_empty() {
+        eprintln!(
+            "Warning, no version file found, set version_files in the release section of .workflow to bump one"
+        );
+        return Ok(());
+    }
+
+    let mut changed = Vec::new();
+    for file in &files {
+        changed.extend(release::update_version_file(
+            workdir, file, &previous, &version,
+        )?);
+    }
+    let changed: Vec<&Path> = changed.iter().map(PathBuf::as_path).collect();
+    repo.commit_files(&changed, &format!("chore(release): {}", version))?;
+    for file in changed {
+        println!("Version {} written in {}", version, file.display());
+    }
+
     Ok(())
 }
+
+/// Merge the current release branch into the main branch, tag it, merge it back into the base
+/// branch and delete the release branch
+pub fn command_release_finish(
+    repo_config: &RepoConfig,
+    repo: &impl GitRepository,
+    push: bool,
+) -> Result<(), WfError> {
+    let branches = repo_config
+        .branches
+        .as_ref()
+        .ok_or(WfError::ConfigurationNotSet)?;
+    let main_branch_name = branches
+        .main
+        .as_deref()
+        .ok_or(ReleaseError::MainBranchNotSet)?;
+    let dev_branch_name = branches.dev.as_str();
+    let release_config = repo_config.release.clone().unwrap_or_default();
+
+    let branch_name = repo.current_branch()?;
+    let version = branch_name
+        .strip_prefix(branches.release_prefix())
+        .ok_or_else(|| {
+            ReleaseError::NotAReleaseBranch(
+                branch_name.clone(),
+                branches.release_prefix().to_string(),
+            )
+        })
+        .and_then(|version| Version::parse(version, ""))?;
+
+    if !repo.is_clean()? {
+        return Err(GitError::DirtyWorkingTree.into());
+    }
+
+    for branch in [main_branch_name, dev_branch_name] {
+        println!("Updating {} from {}", branch, repo.remote());
+        repo.fetch(branch)?;
+        repo.fast_forward(branch)?;
+    }
+
+    let tag = format!("{}{}", release_config.tag_prefix(), version);
+    merge_tag_and_merge_back(
+        repo,
+        &branch_name,
+        main_branch_name,
+        dev_branch_name,
+        &tag,
+        &format!("Release {}", version),
+    )?;
+
+    repo.delete_branch(&branch_name)?;
+    println!("Branch {} deleted", branch_name);
+
+    let refs = [
+        format!("refs/heads/{}", main_branch_name),
+        format!("refs/heads/{}", dev_branch_name),
+        format!("refs/tags/{}", tag),
+    ];
+    if push {
+        repo.push_refs(&refs)?;
+        // Only once the release is published, the remote branch is all that is left of it otherwise
+        repo.delete_remote_branch(&branch_name)?;
+    } else {
+        println!(
+            "Release {} is ready, run git push {} {} {} {} to publish it",
+            version,
+            repo.remote(),
+            main_branch_name,
+            dev_branch_name,
+            tag
+        );
+    }
+
+    Ok(())
+}
+
+/// Merge `branch` into the main branch, tag the result and merge the main branch back into the
+/// base branch, the base branch is checked out at the end
+fn merge_tag_and_merge_back(
+    repo: &impl GitRepository,
+    branch: &str,
+    main: &str,
+    dev: &str,
+    tag: &str,
+    tag_message: &str,
+) -> Result<(), WfError> {
+    let message = format!("Merge branch '{}' into {}", branch, main);
+    repo.merge_into(branch, main, MergeStrategy::Merge, &message)?;
+    println!("Branch {} merged into {}", branch, main);
+
+    repo.create_tag(tag, tag_message)?;
+    println!("Tag {} created on {}", tag, main);
+
+    let message = format!("Merge branch '{}' into {}", main, dev);
+    match repo.merge_into(main, dev, MergeStrategy::Merge, &message) {
+        Ok(()) => {
+            println!("Branch {} merged back into {}", main, dev);
+            Ok(())
+        }
+        Err(GitError::MergeConflict(_, _, files)) => Err(ReleaseError::MergeBackConflict(
+            branch.to_string(),
+            main.to_string(),
+            dev.to_string(),
+            files,
+        )
+        .into()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Merge the current hotfix branch into the main and base branches.
+///
+/// Each branch is merged on its own so that a conflict on one does not prevent the other, merging
+/// again into a branch that already has the hotfix changes nothing, so finish can be run again
+/// once the conflicts are resolved.
+pub async fn command_hotfix_finish(
+    repo_config: &RepoConfig,
+    repo: &impl GitRepository,
+    tracker: Option<&dyn IssueTracker>,
+    push: bool,
+) -> Result<(), WfError> {
+    let branches = repo_config
+        .branches
+        .as_ref()
+        .ok_or(WfError::ConfigurationNotSet)?;
+    let main_branch_name = branches
+        .main
+        .as_deref()
+        .ok_or(ReleaseError::MainBranchNotSet)?;
+    let dev_branch_name = branches.dev.as_str();
+
+    let branch_name = repo.current_branch()?;
+    if !branch_name.starts_with(branches.hotfix_prefix()) {
+        return Err(ReleaseError::NotAHotfixBranch(
+            branch_name,
+            branches.hotfix_prefix().to_string(),
+        )
+        .into());
+    }
+
+    if !repo.is_clean()? {
+        return Err(GitError::DirtyWorkingTree.into());
+    }
+
+    for branch in [main_branch_name, dev_branch_name] {
+        println!("Updating {} from {}", branch, repo.remote());
+        repo.fetch(branch)?;
+        repo.fast_forward(branch)?;
+    }
+
+    let current_issue = current_issue_or_warn(repo_config, tracker, repo).await;
+
+    let mut conflicts = Vec::new();
+    for target in [main_branch_name, dev_branch_name] {
+        let message = format!("Merge branch '{}' into {}", branch_name, target);
+        match repo.merge_into(&branch_name, target, MergeStrategy::Merge, &message) {
+            Ok(()) => println!("Branch {} merged into {}", branch_name, target),
+            Err(GitError::MergeConflict(_, _, files)) => {
+                conflicts.push((target.to_string(), files))
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+    if !conflicts.is_empty() {
+        return Err(ReleaseError::HotfixConflicts(branch_name, conflicts).into());
+    }
+
+    repo.delete_branch(&branch_name)?;
+    println!("Branch {} deleted", branch_name);
+
+    if push {
+        repo.push_refs(&[
+            format!("refs/heads/{}", main_branch_name),
+            format!("refs/heads/{}", dev_branch_name),
+        ])?;
+        // Only once the hotfix is published, the remote branch is all that is left of it otherwise
+        repo.delete_remote_branch(&branch_name)?;
+    } else {
+        println!(
+            "Hotfix {} is ready, run git push {} {} {} to publish it",
+            branch_name,
+            repo.remote(),
+            main_branch_name,
+            dev_branch_name
+        );
+    }
+
+    if let Some((tracker, issue)) = &current_issue {
+        move_issue(
+            *tracker,
+            issue,
+            repo_config.transition_for(WorkflowEvent::Finish),
+        )
+        .await;
+    }
+
+    Ok(())
+}
+
+/// Cherry-pick the commits of an issue onto each target branch in a backport branch and push it.
+///
+/// The commits are the ones of the base branch mentioning the issue key, or the commits of the
+/// issue branch when there are none. A conflict or a failure on one target does not prevent the
+/// others, the branch checked out before is checked out again once done.
+pub async fn command_backport(
+    repo_config: &RepoConfig,
+    repo: &impl GitRepository,
+    tracker: &dyn IssueTracker,
+    ticket_id: &str,
+    targets: &[String],
+    comment: bool,
+) -> Result<(), WfError> {
+    let dev_branch_name = repo_config
+        .branches
+        .as_ref()
+        .ok_or(WfError::ConfigurationNotSet)?
+        .dev
+        .as_str();
+
+    if !repo.is_clean()? {
+        return Err(GitError::DirtyWorkingTree.into());
+    }
+
+    let issue = tracker.get_issue(ticket_id).await?;
+    let base = update_base_branch(repo, dev_branch_name, StartFrom::Remote)?;
+    let (source, commits) = backport_commits(repo_config, repo, tracker, &base, &issue.key)?;
+    println!("Backporting {} commit(s) of {}:", commits.len(), issue.key);
+    for commit in &commits {
+        println!("  {} {}", commit.short_id(), commit.subject());
+    }
+
+    let original_branch = repo.current_branch().ok();
+    let existing_branches = repo.branches()?;
+    let mut backported = Vec::new();
+    let mut conflicts = Vec::new();
+    let mut failures = Vec::new();
+    for target in targets {
+        let branch_name = backport::branch_name(&issue.key, target);
+        if existing_branches.contains(&branch_name) {
+            println!(
+                "Branch {} already exists, delete it to backport to {} again",
+                branch_name, target
+            );
+            continue;
+        }
+
+        match backport_to(repo, &branch_name, target, &source, &commits) {
+            Ok(true) => {
+                println!("Branch {} created on {} and pushed", branch_name, target);
+                backported.push(format!("- {} in {}", target, branch_name));
+            }
+            Ok(false) => println!("{} already has the commits of {}", target, issue.key),
+            Err(WfError::GitError(GitError::CherryPickConflict(_, _, files))) => {
+                println!(
+                    "Branch {} kept on {} without the commits, cherry-pick them by hand or delete it to backport again",
+                    branch_name, target
+                );
+                conflicts.push((target.to_string(), files));
+            }
+            Err(e) => {
+                eprintln!("Cannot backport {} to {}: {}", issue.key, target, e);
+                failures.push(target.to_string());
+            }
+        }
+    }
+
+    if let Some(branch) = &original_branch {
+        if let Err(e) = repo.checkout_branch(branch) {
+            eprintln!("Warning, cannot checkout {} again: {}", branch, e);
+        }
+    }
+
+    if comment && !backported.is_empty() {
+        let body = format!("Backported to:\n{}", backported.join("\n"));
+        if let Err(e) = tracker.add_comment(&issue, &body).await {
+            eprintln!("Warning, cannot comment on {}: {}", issue.key, e);
+        }
+    }
+
+    if !conflicts.is_empty() {
+        return Err(BackportError::Conflicts(issue.key, conflicts).into());
+    }
+    if !failures.is_empty() {
+        return Err(BackportError::Failed(issue.key, failures).into());
+    }
+    Ok(())
+}
+
+/// Cherry-pick the commits `target` misses onto a new backport branch and push it, `false` when
+/// the target already has them all
+fn backport_to(
+    repo: &impl GitRepository,
+    branch_name: &str,
+    target: &str,
+    source: &str,
+    commits: &[CommitInfo],
+) -> Result<bool, WfError> {
+    // Commits the target already has, e.g. when it was branched after them, are left out
+    let from_branch = update_base_branch(repo, target, StartFrom::Remote)?;
+    let missing = repo.commits(&format!("{}..{}", from_branch, source))?;
+    let ids: Vec<String> = commits
+        .iter()
+        .filter(|commit| missing.iter().any(|m| m.id == commit.id))
+        .map(|commit| commit.id.clone())
+        .collect();
+    if ids.is_empty() {
+        return Ok(false);
+    }
+
+    repo.create_and_checkout_branch(branch_name, &from_branch)?;
+    repo.cherry_pick(&ids)?;
+    repo.push(false)?;
+    Ok(true)
+}
+
+/// Commits of `base` mentioning the issue key, or of the issue branch when none does, oldest
+/// first, with the branch they were found on
+fn backport_commits(
+    repo_config: &RepoConfig,
+    repo: &impl GitRepository,
+    tracker: &dyn IssueTracker,
+    base: &str,
+    key: &str,
+) -> Result<(String, Vec<CommitInfo>), WfError> {
+    let commits = backport::issue_commits(repo.commits(base)?, key);
+    if !commits.is_empty() {
+        return Ok((base.to_string(), commits));
+    }
+
+    // Not merged yet, or merged with messages not mentioning the key
+    let issue_branch = repo.branches()?.into_iter().find(|branch| {
+        !branch.starts_with(backport::PREFIX)
+            && issue_key_for_branch(repo_config.branch_name.as_ref(), Some(tracker), branch)
+                .as_deref()
+                == Some(key)
+    });
+    let (branch, mut commits) = match issue_branch {
+        Some(branch) => {
+            let commits = repo.commits(&format!("{}..{}", base, branch))?;
+            (branch, commits)
+        }
+        None => (base.to_string(), Vec::new()),
+    };
+    if commits.is_empty() {
+        return Err(BackportError::NoCommits(key.to_string(), base.to_string()).into());
+    }
+    commits.reverse();
+    Ok((branch, commits))
+}
+
+/// State of the current work branch shown by the status command
+#[derive(Debug)]
+pub struct StatusReport {
+    pub branch: String,
+    pub base: String,
+    pub remote: String,
+    pub issue_key: Option<String>,
+    /// Issue fetched from the tracker, `None` when there is no tracker or it cannot be reached
+    pub issue: Option<Issue>,
+    /// Commits ahead and behind the base branch
+    pub base_ahead_behind: Option<(usize, usize)>,
+    /// Commits ahead and behind the remote branch, `None` when it was never pushed
+    pub remote_ahead_behind: Option<(usize, usize)>,
+    pub clean: bool,
+}
+
+impl Display for StatusReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "On branch {} (base {})", self.branch, self.base)?;
+
+        match (&self.issue, &self.issue_key) {
+            (Some(issue), _) => {
+                writeln!(
+                    f,
+                    "Issue {} {} [{}]",
+                    issue.key, issue.summary, issue.status
+                )?;
+                writeln!(f, "  {}", issue.url)?;
+            }
+            (None, Some(key)) => writeln!(f, "Issue {}", key)?,
+            (None, None) => writeln!(f, "No issue found for this branch")?,
+        }
+
+        match self.base_ahead_behind {
+            Some(counts) => writeln!(f, "{} {}", ahead_behind(counts), self.base)?,
+            None => writeln!(f, "Base branch {} not found", self.base)?,
+        }
+
+        match self.remote_ahead_behind {
+            Some(counts) => writeln!(
+                f,
+                "{} {}/{}",
+                ahead_behind(counts),
+                self.remote,
+                self.branch
+            )?,
+            None => writeln!(f, "Not pushed to {}", self.remote)?,
+        }
+
+        if self.clean {
+            write!(f, "Working tree clean")
+        } else {
+            write!(f, "Uncommitted changes in the working tree")
+        }
+    }
+}
+
+fn ahead_behind((ahead, behind): (usize, usize)) -> String {
+    match (ahead, behind) {
+        (0, 0) => "Up to date with".to_string(),
+        (ahead, 0) => format!("{} commit(s) ahead of", ahead),
+        (0, behind) => format!("{} commit(s) behind", behind),
+        (ahead, behind) => format!("{} commit(s) ahead and {} behind", ahead, behind),
+    }
+}
+
+pub async fn command_status(
+    repo_config: &RepoConfig,
+    repo: &impl GitRepository,
+    tracker: Option<&dyn IssueTracker>,
+) -> Result<StatusReport, WfError> {
+    let base = repo_config
+        .branches
+        .as_ref()
+        .ok_or(WfError::ConfigurationNotSet)?
+        .dev
+        .clone();
+
+    let branch = repo.current_branch()?;
+    let issue_key = current_issue_key(repo, repo_config.branch_name.as_ref(), tracker)?;
+
+    let issue = match (tracker, &issue_key) {
+        (Some(tracker), Some(key)) => match tracker.get_issue(key).await {
+            Ok(issue) => Some(issue),
+            Err(e) => {
+                eprintln!("Warning, cannot fetch issue {}: {}", key, e);
+                None
+            }
+        },
+        _ => None,
+    };
+
+    Ok(StatusReport {
+        base_ahead_behind: repo.ahead_behind(&branch, &base)?,
+        remote_ahead_behind: repo.ahead_behind(&branch, &repo.remote_branch(&branch))?,
+        remote: repo.remote().to_string(),
+        clean: repo.is_clean()?,
+        branch,
+        base,
+        issue_key,
+        issue,
+    })
+}
+
+/// Let the user choose among the issues returned by the query
+async fn pick_issue(
+    tracker: &dyn IssueTracker,
+    query: &str,
+    prompter: &impl Prompter,
+) -> Result<Issue, WfError> {
+    let message = "Which issue do you want to start?";
+    if !prompter.is_interactive() {
+        return Err(PromptError::InputRequired(
+            message.to_string(),
+            "a ticket id argument".to_string(),
+        )
+        .into());
+    }
+
+    let issues = tracker.search_issues(query).await?;
+    if issues.is_empty() {
+        return Err(WfError::NoIssueFound(query.to_string()));
+    }
+
+    Ok(prompter.select(Select::new(message, issues), None)?)
+}
+
+/// Fetch the issue matching the current branch, if any and if a tracker is available
+async fn current_issue<'a>(
+    repo_config: &RepoConfig,
+    tracker: Option<&'a dyn IssueTracker>,
+    repo: &impl GitRepository,
+) -> Result<Option<(&'a dyn IssueTracker, Issue)>, WfError> {
+    let tracker = match tracker {
+        Some(tracker) => tracker,
+        None => return Ok(None),
+    };
+
+    match current_issue_key(repo, repo_config.branch_name.as_ref(), Some(tracker))? {
+        Some(key) => Ok(Some((tracker, tracker.get_issue(&key).await?))),
+        None => Ok(None),
+    }
+}
+
+/// Like `current_issue` once the git part of the workflow is done, failing to get the issue only
+/// prints a warning.
+async fn current_issue_or_warn<'a>(
+    repo_config: &RepoConfig,
+    tracker: Option<&'a dyn IssueTracker>,
+    repo: &impl GitRepository,
+) -> Option<(&'a dyn IssueTracker, Issue)> {
+    match current_issue(repo_config, tracker, repo).await {
+        Ok(issue) => issue,
+        Err(e) => {
+            eprintln!("Warning, cannot get the issue of the current branch: {}", e);
+            None
+        }
+    }
+}
+
+/// Move the issue to the given status if any.
+///
+/// Failing to do so only prints a warning as the git part of the workflow is already done.
+async fn move_issue(tracker: &dyn IssueTracker, issue: &Issue, status: Option<&str>) {
+    let status = match status {
+        Some(status) => status,
+        None => return,
+    };
+
+    match tracker.transition_to_status(issue, status).await {
+        Ok(true) => println!("Issue {} moved to {}", issue.key, status),
+        Ok(false) => {}
+        Err(e) => eprintln!(
+            "Warning, cannot move issue {} to {}: {}",
+            issue.key, status, e
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{
+        BranchNameConfig, BranchsName, CommitMessageConfig, ConventionalCommitsConfig,
+        GitHubConfig, IssueTransitions, StartConfig, SyncConfig,
+    };
+    use crate::git::{LocalGitRepository, Operation};
+    use crate::git_cli::CliGitRepository;
+    use crate::hooks::HookError;
+    use crate::prompt::InquirePrompter;
+    use crate::secret::SecretRef;
+    use crate::test_support::{
+        issue, Answer, FakeGitRepository, FakeIssueTracker, Merge, MockJira, ScriptedPrompter,
+        TestRepository,
+    };
+
+    use super::*;
+
+    fn repo_config() -> RepoConfig {
+        RepoConfig {
+            branches: Some(BranchsName {
+                dev: "develop".to_string(),
+                main: None,
+                release: None,
+                hotfix: None,
+            }),
+            transitions: Some(IssueTransitions {
+                start: Some("In Progress".to_string()),
+                push: None,
+                pr: None,
+                finish: Some("Done".to_string()),
+            }),
+            ..RepoConfig::default()
+        }
+    }
+
+    fn tracker() -> FakeIssueTracker {
+        FakeIssueTracker::new(vec![
+            issue("PROJ-1", "Fix the login"),
+            issue("PROJ-2", "Add a logout button"),
+        ])
+    }
+
+    #[tokio::test]
+    async fn test_start_with_ticket_id() {
+        let repo = FakeGitRepository::new(None, &["main", "develop"], "main");
+        let tracker = tracker();
+        let prompter = ScriptedPrompter::new([Answer::Default]);
+        let answers = StartAnswers {
+            ticket_id: Some("PROJ-1"),
+            ..StartAnswers::default()
+        };
+
+        command_start(&repo_config(), &repo, &tracker, &prompter, &answers)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            repo.created_branches(),
+            vec![("PROJ-1-fix-the-login".to_string(), "develop".to_string())]
+        );
+        assert_eq!(repo.checkouts(), vec!["PROJ-1-fix-the-login"]);
+        assert_eq!(
+            tracker.transitions(),
+            vec![("PROJ-1".to_string(), "In Progress".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_start_fast_forwards_base_branch() {
+        let repo = FakeGitRepository::new(None, &["main", "develop"], "main");
+        let prompter = ScriptedPrompter::new([Answer::Default]);
+        let answers = StartAnswers {
+            ticket_id: Some("PROJ-1"),
+            ..StartAnswers::default()
+        };
+
+        command_start(&repo_config(), &repo, &tracker(), &prompter, &answers)
+            .await
+            .unwrap();
+
+        assert_eq!(repo.fetches(), vec!["develop"]);
+        assert_eq!(repo.fast_forwards(), vec!["develop"]);
+    }
+
+    #[tokio::test]
+    async fn test_start_from_remote_base_branch() {
+        let repo = FakeGitRepository::new(None, &["main", "develop", "origin/develop"], "main");
+        let prompter = ScriptedPrompter::new([Answer::Default]);
+        let answers = StartAnswers {
+            ticket_id: Some("PROJ-1"),
+            from: Some(StartFrom::Remote),
+            ..StartAnswers::default()
+        };
+
+        command_start(&repo_config(), &repo, &tracker(), &prompter, &answers)
+            .await
+            .unwrap();
+
+        assert!(repo.fast_forwards().is_empty());
+        assert_eq!(
+            repo.created_branches(),
+            vec![(
+                "PROJ-1-fix-the-login".to_string(),
+                "origin/develop".to_string()
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_start_from_diverged_base_branch() {
+        let repo = FakeGitRepository::new(None, &["main", "develop"], "main");
+        repo.set_ahead_behind("develop", "origin/develop", 1, 2);
+        let prompter = ScriptedPrompter::new([Answer::Default]);
+        let answers = StartAnswers {
+            ticket_id: Some("PROJ-1"),
+            ..StartAnswers::default()
+        };
+
+        command_start(&repo_config(), &repo, &tracker(), &prompter, &answers)
+            .await
+            .unwrap();
+
+        assert!(repo.fast_forwards().is_empty());
+        assert_eq!(
+            repo.created_branches(),
+            vec![("PROJ-1-fix-the-login".to_string(), "develop".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_start_stash_dirty_tree() {
+        let repo = FakeGitRepository::new(None, &["main", "develop"], "main");
+        repo.set_dirty(true);
+        let prompter = ScriptedPrompter::new([
+            Answer::Choice(DirtyTreeAction::Stash.to_string()),
+            Answer::Default,
+        ]);
+        let answers = StartAnswers {
+            ticket_id: Some("PROJ-1"),
+            ..StartAnswers::default()
+        };
+
+        command_start(&repo_config(), &repo, &tracker(), &prompter, &answers)
+            .await
+            .unwrap();
+
+        assert_eq!(repo.current(), "PROJ-1-fix-the-login");
+        assert!(repo.stashes().is_empty());
+        assert!(repo.is_dirty());
+    }
+
+    #[tokio::test]
+    async fn test_start_restore_stash_when_branch_creation_fails() {
+        let repo =
+            FakeGitRepository::new(None, &["main", "develop", "PROJ-1-fix-the-login"], "main");
+        repo.set_dirty(true);
+        let prompter = ScriptedPrompter::new([Answer::Default]);
+        let answers = StartAnswers {
+            ticket_id: Some("PROJ-1"),
+            dirty: Some(DirtyTreeAction::Stash),
+            ..StartAnswers::default()
+        };
+
+        let result = command_start(&repo_config(), &repo, &tracker(), &prompter, &answers).await;
+
+        assert!(matches!(
+            result,
+            Err(WfError::GitError(GitError::BranchExists(_)))
+        ));
+        assert_eq!(repo.current(), "main");
+        assert!(repo.stashes().is_empty());
+        assert!(repo.is_dirty());
+    }
+
+    #[tokio::test]
+    async fn test_start_carry_conflicting_changes() {
+        let repo = FakeGitRepository::new(None, &["main", "develop"], "main");
+        repo.set_dirty(true);
+        repo.set_checkout_conflict(true);
+        let prompter = ScriptedPrompter::new([Answer::Default]);
+        let answers = StartAnswers {
+            ticket_id: Some("PROJ-1"),
+            dirty: Some(DirtyTreeAction::Carry),
+            ..StartAnswers::default()
+        };
+
+        let result = command_start(&repo_config(), &repo, &tracker(), &prompter, &answers).await;
+
+        assert!(matches!(
+            result,
+            Err(WfError::GitError(GitError::CommandFailed(_, _)))
+        ));
+        assert_eq!(repo.current(), "main");
+        assert!(repo.created_branches().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_start_abort_on_dirty_tree() {
+        let repo = FakeGitRepository::new(None, &["main", "develop"], "main");
+        repo.set_dirty(true);
+        let tracker = tracker();
+        let prompter = ScriptedPrompter::new([Answer::Choice("Abort".to_string())]);
+        let answers = StartAnswers {
+            ticket_id: Some("PROJ-1"),
+            ..StartAnswers::default()
+        };
+
+        let result = command_start(&repo_config(), &repo, &tracker, &prompter, &answers).await;
+
+        assert!(matches!(
+            result,
+            Err(WfError::GitError(GitError::DirtyWorkingTree))
+        ));
+        assert!(repo.created_branches().is_empty());
+        assert!(tracker.transitions().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_start_dirty_tree_non_interactive() {
+        let repo = FakeGitRepository::new(None, &["main", "develop"], "main");
+        repo.set_dirty(true);
+        let answers = StartAnswers {
+            ticket_id: Some("PROJ-1"),
+            ..StartAnswers::default()
+        };
+
+        let result = command_start(
+            &repo_config(),
+            &repo,
+            &tracker(),
+            &InquirePrompter::new(false),
+            &answers,
+        )
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(WfError::PromptError(PromptError::InputRequired(_, hint))) if hint == "--dirty"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_start_pick_issue_and_rename_branch() {
+        let repo = FakeGitRepository::new(None, &["develop"], "develop");
+        let tracker = tracker();
+        let prompter = ScriptedPrompter::new([
+            Answer::Choice("PROJ-2 Add a logout button [To Do]".to_string()),
+            Answer::Text("feature/logout".to_string()),
+        ]);
+
+        command_start(
+            &repo_config(),
+            &repo,
+            &tracker,
+            &prompter,
+            &StartAnswers::default(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(repo.current(), "feature/logout");
+        assert_eq!(
+            prompter.questions(),
+            vec!["Which issue do you want to start?", "Branch name:"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_start_reject_invalid_branch_name() {
+        let repo = FakeGitRepository::new(None, &["develop"], "develop");
+        let prompter = ScriptedPrompter::new([Answer::Text("bad..name".to_string())]);
+        let answers = StartAnswers {
+            ticket_id: Some("PROJ-1"),
+            ..StartAnswers::default()
+        };
+
+        let result = command_start(&repo_config(), &repo, &tracker(), &prompter, &answers).await;
+
+        assert!(matches!(
+            result,
+            Err(WfError::PromptError(PromptError::InvalidAnswer(_, _)))
+        ));
+        assert_eq!(repo.current(), "develop");
+    }
+
+    #[tokio::test]
+    async fn test_start_non_interactive_requires_ticket_id() {
+        let repo = FakeGitRepository::new(None, &["develop"], "develop");
+        let prompter = InquirePrompter::new(false);
+
+        let result = command_start(
+            &repo_config(),
+            &repo,
+            &tracker(),
+            &prompter,
+            &StartAnswers::default(),
+        )
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(WfError::PromptError(PromptError::InputRequired(_, _)))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_start_with_jira() {
+        let mut mock_jira = MockJira::new().await;
+        mock_jira.issue(("PROJ-7", "Export as CSV", "To Do")).await;
+        mock_jira
+            .transitions("PROJ-7", &[("11", "In Progress"), ("31", "Done")])
+            .await;
+        let transition = mock_jira.expect_transition("PROJ-7", "11").await;
+        let repo = FakeGitRepository::new(None, &["develop"], "develop");
+        let prompter = ScriptedPrompter::new([Answer::Default]);
+        let answers = StartAnswers {
+            ticket_id: Some("PROJ-7"),
+            ..StartAnswers::default()
+        };
+
+        command_start(
+            &repo_config(),
+            &repo,
+            &mock_jira.jira(),
+            &prompter,
+            &answers,
+        )
+        .await
+        .unwrap();
+
+        transition.assert_async().await;
+        assert_eq!(repo.current(), "PROJ-7-export-as-csv");
+    }
+
+    #[tokio::test]
+    async fn test_start_with_missing_jira_issue() {
+        let mut mock_jira = MockJira::new().await;
+        mock_jira
+            .error(
+                "GET",
+                "/rest/api/2/issue/PROJ-404",
+                404,
+                "Issue does not exist",
+            )
+            .await;
+        let repo = FakeGitRepository::new(None, &["develop"], "develop");
+        let prompter = ScriptedPrompter::new([]);
+        let answers = StartAnswers {
+            ticket_id: Some("PROJ-404"),
+            ..StartAnswers::default()
+        };
+
+        let result = command_start(
+            &repo_config(),
+            &repo,
+            &mock_jira.jira(),
+            &prompter,
+            &answers,
+        )
+        .await;
+
+        assert!(matches!(result, Err(WfError::TrackerError(_))));
+        assert!(repo.created_branches().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_push_moves_issue() {
+        let repo = FakeGitRepository::new(None, &["develop", "PROJ-1-fix"], "PROJ-1-fix");
+        let tracker = tracker();
+        let mut repo_config = repo_config();
+        repo_config.transitions = Some(IssueTransitions {
+            start: None,
+            push: Some("In Review".to_string()),
+            pr: None,
+            finish: None,
+        });
+
+        command_push(&repo_config, &repo, Some(&tracker), false)
+            .await
+            .unwrap();
+
+        assert_eq!(repo.pushes(), vec!["PROJ-1-fix"]);
+        assert!(repo.forced_pushes().is_empty());
+        assert_eq!(
+            tracker.transitions(),
+            vec![("PROJ-1".to_string(), "In Review".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_push_with_unknown_issue() {
+        let repo = FakeGitRepository::new(None, &["develop", "PROJ-9-fix"], "PROJ-9-fix");
+        let tracker = tracker();
+        let mut repo_config = repo_config();
+        repo_config.transitions = Some(IssueTransitions {
+            start: None,
+            push: Some("In Review".to_string()),
+            pr: None,
+            finish: None,
+        });
+
+        command_push(&repo_config, &repo, Some(&tracker), false)
+            .await
+            .unwrap();
+
+        assert_eq!(repo.pushes(), vec!["PROJ-9-fix"]);
+        assert!(tracker.transitions().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_push_force_with_lease() {
+        let repo = FakeGitRepository::new(None, &["develop", "PROJ-1-fix"], "PROJ-1-fix");
+
+        command_push(&repo_config(), &repo, None, true)
+            .await
+            .unwrap();
+
+        assert_eq!(repo.forced_pushes(), vec!["PROJ-1-fix"]);
+    }
+
+    #[tokio::test]
+    async fn test_rejected_push_does_not_move_issue() {
+        let repo = FakeGitRepository::new(None, &["develop", "PROJ-1-fix"], "PROJ-1-fix");
+        repo.set_push_rejection("non-fast-forward");
+        let tracker = tracker();
+        let mut repo_config = repo_config();
+        repo_config.transitions = Some(IssueTransitions {
+            start: None,
+            push: Some("In Review".to_string()),
+            pr: None,
+            finish: None,
+        });
+
+        let result = command_push(&repo_config, &repo, Some(&tracker), false).await;
+
+        assert!(matches!(
+            result,
+            Err(WfError::GitError(GitError::NonFastForward(branch))) if branch == "PROJ-1-fix"
+        ));
+        assert!(repo.pushes().is_empty());
+        assert!(tracker.transitions().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_start_in_worktree() {
+        let repo = FakeGitRepository::new(Some(Path::new("/work/repo")), &["develop"], "main");
+        repo.set_dirty(true);
+        let answers = StartAnswers {
+            ticket_id: Some("PROJ-1"),
+            worktree: true,
+            ..StartAnswers::default()
+        };
+
+        command_start(
+            &repo_config(),
+            &repo,
+            &tracker(),
+            &InquirePrompter::new(false),
+            &answers,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            repo.worktrees().unwrap(),
+            vec![Worktree {
+                path: PathBuf::from("/work/repo.worktrees/PROJ-1-fix-the-login"),
+                branch: Some("PROJ-1-fix-the-login".to_string()),
+            }]
+        );
+        assert_eq!(repo.current(), "main");
+        assert!(repo.checkouts().is_empty());
+        assert!(repo.stashes().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_start_in_configured_worktrees_dir() {
+        let repo = FakeGitRepository::new(Some(Path::new("/work/repo")), &["develop"], "main");
+        let mut repo_config = repo_config();
+        repo_config.start = Some(StartConfig {
+            worktree: true,
+            worktrees_dir: Some(PathBuf::from("../trees")),
+            ..StartConfig::default()
+        });
+        let answers = StartAnswers {
+            ticket_id: Some("PROJ-1"),
+            branch_name: Some("feature/PROJ-1"),
+            ..StartAnswers::default()
+        };
+
+        command_start(
+            &repo_config,
+            &repo,
+            &tracker(),
+            &InquirePrompter::new(false),
+            &answers,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            repo.worktrees().unwrap()[0].path,
+            PathBuf::from("/work/repo/../trees/feature-PROJ-1")
+        );
+    }
+
+    #[test]
+    fn test_worktree_list_and_remove() {
+        let repo = FakeGitRepository::new(None, &["develop"], "develop");
+        repo.add_worktree(Path::new("/trees/PROJ-1-fix"), "PROJ-1-fix", "develop")
+            .unwrap();
+        repo.add_worktree(Path::new("/trees/spike"), "spike", "develop")
+            .unwrap();
+
+        let worktrees = command_worktree_list(&repo_config(), &repo, None).unwrap();
+        let keys: Vec<_> = worktrees.iter().map(|w| w.issue_key.as_deref()).collect();
+        assert_eq!(keys, vec![Some("PROJ-1"), None]);
+
+        command_worktree_remove(&repo_config(), &repo, None, "PROJ-1").unwrap();
+        command_worktree_remove(&repo_config(), &repo, None, "spike").unwrap();
+        assert_eq!(
+            repo.removed_worktrees(),
+            vec![
+                PathBuf::from("/trees/PROJ-1-fix"),
+                PathBuf::from("/trees/spike")
+            ]
+        );
+        assert!(matches!(
+            command_worktree_remove(&repo_config(), &repo, None, "PROJ-2"),
+            Err(WfError::GitError(GitError::WorktreeNotFound(_)))
+        ));
+    }
+
+    #[test]
+    fn test_install_hooks() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = FakeGitRepository::new(Some(dir.path()), &["develop"], "develop");
+
+        command_install_hooks(&repo, Path::new("/usr/local/bin/wf")).unwrap();
+
+        let hooks_dir = dir.path().join(".git").join("hooks");
+        assert!(hooks_dir.join("prepare-commit-msg").exists());
+        assert!(hooks_dir.join("commit-msg").exists());
+    }
+
+    #[test]
+    fn test_run_hooks_with_branch_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("COMMIT_EDITMSG");
+        let args = vec![file.display().to_string(), "message".to_string()];
+        let repo = FakeGitRepository::new(None, &["develop"], "PROJ-1-fix");
+        let mut repo_config = repo_config();
+        repo_config.commit_message = Some(CommitMessageConfig {
+            template: Some("{key}: {message}".to_string()),
+            require_key: true,
+        });
+
+        std::fs::write(&file, "Fix the login\n").unwrap();
+        command_run_hook(&repo_config, &repo, Hook::PrepareCommitMsg, &args).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&file).unwrap(),
+            "PROJ-1: Fix the login\n"
+        );
+        command_run_hook(&repo_config, &repo, Hook::CommitMsg, &args).unwrap();
+
+        std::fs::write(&file, "Fix the login\n").unwrap();
+        assert!(matches!(
+            command_run_hook(&repo_config, &repo, Hook::CommitMsg, &args),
+            Err(WfError::HookError(HookError::MissingIssueKey(_)))
+        ));
+    }
+
+    #[test]
+    fn test_run_hooks_with_key_format_of_tracker_kind() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("COMMIT_EDITMSG");
+        let args = vec![file.display().to_string(), "message".to_string()];
+        let repo = FakeGitRepository::new(None, &["develop"], "feature/12-fix");
+        let mut repo_config = repo_config();
+        repo_config.tracker = IssueTrackerKind::GitHub;
+        repo_config.commit_message = Some(CommitMessageConfig {
+            template: Some("{key}: {message}".to_string()),
+            require_key: true,
+        });
+
+        std::fs::write(&file, "Fix the login\n").unwrap();
+        command_run_hook(&repo_config, &repo, Hook::PrepareCommitMsg, &args).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(&file).unwrap(),
+            "12: Fix the login\n"
+        );
+    }
+
+    #[test]
+    fn test_run_hooks_without_branch_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("COMMIT_EDITMSG");
+        let args = vec![file.display().to_string()];
+        let repo = FakeGitRepository::new(None, &["develop"], "develop");
+        let mut repo_config = repo_config();
+        repo_config.commit_message = Some(CommitMessageConfig {
+            template: None,
+            require_key: true,
+        });
+
+        std::fs::write(&file, "Fix the build\n").unwrap();
+        command_run_hook(&repo_config, &repo, Hook::PrepareCommitMsg, &args).unwrap();
+        command_run_hook(&repo_config, &repo, Hook::CommitMsg, &args).unwrap();
+
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "Fix the build\n");
+    }
+
+    #[test]
+    fn test_commit_msg_hook_lints_conventional_commits() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("COMMIT_EDITMSG");
+        let args = vec![file.display().to_string()];
+        let repo = FakeGitRepository::new(None, &["develop"], "develop");
+        let mut repo_config = repo_config();
+        repo_config.conventional_commits = Some(ConventionalCommitsConfig::default());
+
+        std::fs::write(&file, "feat: add SSO\n").unwrap();
+        command_run_hook(&repo_config, &repo, Hook::CommitMsg, &args).unwrap();
+
+        std::fs::write(&file, "Add SSO\n").unwrap();
+        assert!(matches!(
+            command_run_hook(&repo_config, &repo, Hook::CommitMsg, &args),
+            Err(WfError::HookError(HookError::NotConventional(_)))
+        ));
+    }
+
+    #[test]
+    fn test_prepare_commit_msg_keeps_conventional_type_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("COMMIT_EDITMSG");
+        let args = vec![file.display().to_string(), "message".to_string()];
+        let repo = FakeGitRepository::new(None, &["develop"], "PROJ-1-fix");
+        let mut repo_config = repo_config();
+        repo_config.conventional_commits = Some(ConventionalCommitsConfig::default());
+
+        std::fs::write(&file, "fix: keep the session\n").unwrap();
+        command_run_hook(&repo_config, &repo, Hook::PrepareCommitMsg, &args).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(&file).unwrap(),
+            "fix: keep the session (PROJ-1)\n"
+        );
+    }
+
+    #[test]
+    fn test_lint_commits_missing_from_remote_base() {
+        let repo = FakeGitRepository::new(None, &["develop"], "PROJ-1-fix");
+        repo.set_commits(&[
+            ("b2c3d4e5f6", "fix(login): keep the session"),
+            ("a1b2c3d4e5", "Merge branch 'develop' into PROJ-1-fix"),
+        ]);
+
+        command_lint_commits(&repo_config(), &repo, None).unwrap();
+
+        assert_eq!(repo.commit_ranges(), vec!["origin/develop..HEAD"]);
+    }
+
+    #[test]
+    fn test_lint_commits_reports_invalid_commits() {
+        let repo = FakeGitRepository::new(None, &["develop"], "develop");
+        repo.set_commits(&[
+            ("c3d4e5f6a7", "Fix the build"),
+            ("b2c3d4e5f6", "feature: add SSO"),
+            ("a1b2c3d4e5", "fix: keep the session"),
+        ]);
+
+        let result = command_lint_commits(&repo_config(), &repo, Some("v1.0.0..HEAD"));
+
+        assert!(matches!(
+            result,
+            Err(WfError::ConventionalError(ConventionalError::InvalidCommits(2, 3, range)))
+                if range == "v1.0.0..HEAD"
+        ));
+    }
+
+    #[test]
+    fn test_changelog_since_last_tag() {
+        let repo = FakeGitRepository::new(None, &["develop"], "develop");
+        repo.set_last_tag("v1.0.0");
+        repo.set_commits(&[("a1b2c3d4e5", "feat(login): add SSO (PROJ-1)")]);
+        let jira = JiraConfig {
+            host: "https://jira.example.com".to_string(),
+            user: "jdoe".to_string(),
+            token: SecretRef::Plain("token".to_string()),
+        };
+
+        let changelog =
+            command_changelog(&repo_config(), &repo, Some(&jira), None, Some("v1.1.0")).unwrap();
+
+        assert_eq!(repo.commit_ranges(), vec!["v1.0.0..HEAD"]);
+        assert_eq!(
+            changelog,
+            "## v1.1.0\n\n### Features\n\n\
+             - **login:** add SSO ([PROJ-1](https://jira.example.com/browse/PROJ-1)) (a1b2c3d)\n"
+        );
+    }
+
+    #[test]
+    fn test_changelog_without_tag_or_jira() {
+        let repo = FakeGitRepository::new(None, &["develop"], "develop");
+        repo.set_commits(&[("a1b2c3d4e5", "fix: keep the session (PROJ-2)")]);
+        let repo_config = RepoConfig {
+            tracker: IssueTrackerKind::GitHub,
+            ..repo_config()
+        };
+        let jira = JiraConfig {
+            host: "https://jira.example.com".to_string(),
+            user: "jdoe".to_string(),
+            token: SecretRef::Plain("token".to_string()),
+        };
+
+        let changelog = command_changelog(&repo_config, &repo, Some(&jira), None, None).unwrap();
+
+        assert_eq!(repo.commit_ranges(), vec!["HEAD"]);
+        assert_eq!(
+            changelog,
+            "## Unreleased\n\n### Bug Fixes\n\n- keep the session (PROJ-2) (a1b2c3d)\n"
+        );
+    }
+
+    fn release_repo_config() -> RepoConfig {
+        RepoConfig {
+            branches: Some(BranchsName {
+                dev: "develop".to_string(),
+                main: Some("main".to_string()),
+                release: None,
+                hotfix: None,
+            }),
+            ..RepoConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_release_start_bumps_version_from_commits() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("Cargo.toml"),
+            "[package]\nname = \"app\"\nversion = \"1.2.3\"\n",
+        )
+        .unwrap();
+        let repo = FakeGitRepository::new(Some(dir.path()), &["develop", "main"], "develop");
+        repo.set_last_tag("v1.2.3");
+        repo.set_commits(&[("b2c3d4e5f6", "fix: a"), ("a1b2c3d4e5", "feat: b")]);
+
+        command_release_start(&release_repo_config(), &repo, None, None).unwrap();
+
+        assert_eq!(repo.fetches(), vec!["develop"]);
+        assert_eq!(repo.commit_ranges(), vec!["v1.2.3..develop"]);
+        assert_eq!(
+            repo.created_branches(),
+            vec![("release/1.3.0".to_string(), "develop".to_string())]
+        );
+        assert_eq!(
+            repo.committed_files(),
+            vec![(
+                vec![PathBuf::from("Cargo.toml")],
+                "chore(release): 1.3.0".to_string()
+            )]
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("Cargo.toml")).unwrap(),
+            "[package]\nname = \"app\"\nversion = \"1.3.0\"\n"
+        );
+    }
+
+    #[test]
+    fn test_release_start_without_tag_from_version_file() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("package.json"),
+            "{\n  \"name\": \"app\",\n  \"version\": \"1.2.3\"\n}\n",
+        )
+        .unwrap();
+        let repo = FakeGitRepository::new(Some(dir.path()), &["develop", "main"], "develop");
+        repo.set_last_tag("deploy-42");
+        repo.set_commits(&[("a1b2c3d4e5", "fix: a")]);
+
+        command_release_start(&release_repo_config(), &repo, None, None).unwrap();
+
+        assert_eq!(repo.commit_ranges(), vec!["develop"]);
+        assert_eq!(
+            repo.created_branches(),
+            vec![("release/1.2.4".to_string(), "develop".to_string())]
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("package.json")).unwrap(),
+            "{\n  \"name\": \"app\",\n  \"version\": \"1.2.4\"\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_release_start_with_given_version() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = FakeGitRepository::new(Some(dir.path()), &["develop", "main"], "develop");
+        repo.set_last_tag("v1.2.3");
+
+        command_release_start(&release_repo_config(), &repo, Some("v2.0.0"), None).unwrap();
+
+        assert!(repo.commit_ranges().is_empty());
+        assert_eq!(
+            repo.created_branches(),
+            vec![("release/2.0.0".to_string(), "develop".to_string())]
+        );
+        assert!(repo.committed_files().is_empty());
+
+        let result = command_release_start(&release_repo_config(), &repo, Some("1.2.0"), None);
+        assert!(matches!(
+            result,
+            Err(WfError::ReleaseError(ReleaseError::VersionNotIncreased(version, previous)))
+                if version == "1.2.0" && previous == "1.2.3"
+        ));
+    }
+
+    #[test]
+    fn test_release_finish_merges_tags_and_merges_back() {
+        let repo =
+            FakeGitRepository::new(None, &["develop", "main", "release/1.3.0"], "release/1.3.0");
+
+        command_release_finish(&release_repo_config(), &repo, true).unwrap();
+
+        assert_eq!(repo.fetches(), vec!["main", "develop"]);
+        let merges: Vec<(String, String)> = repo
+            .merges()
+            .into_iter()
+            .map(|m| (m.branch, m.target))
+            .collect();
+        assert_eq!(
+            merges,
+            vec![
+                ("release/1.3.0".to_string(), "main".to_string()),
+                ("main".to_string(), "develop".to_string()),
+            ]
+        );
+        assert_eq!(repo.tags(), vec!["v1.3.0"]);
+        assert_eq!(repo.deleted_branches(), vec!["release/1.3.0"]);
+        assert_eq!(repo.current(), "develop");
+        assert_eq!(
+            repo.pushed_refs(),
+            vec!["refs/heads/main", "refs/heads/develop", "refs/tags/v1.3.0"]
+        );
+        assert_eq!(repo.deleted_remote_branches(), vec!["release/1.3.0"]);
+    }
+
+    #[test]
+    fn test_release_finish_keeps_remote_branch_until_pushed() {
+        let repo =
+            FakeGitRepository::new(None, &["develop", "main", "release/1.3.0"], "release/1.3.0");
+        command_release_finish(&release_repo_config(), &repo, false).unwrap();
+        assert!(repo.deleted_remote_branches().is_empty());
+
+        let repo =
+            FakeGitRepository::new(None, &["develop", "main", "release/1.3.0"], "release/1.3.0");
+        repo.set_push_rejection("non-fast-forward");
+        assert!(command_release_finish(&release_repo_config(), &repo, true).is_err());
+        assert!(repo.deleted_remote_branches().is_empty());
+    }
+
+    #[test]
+    fn test_release_finish_reports_merge_back_conflicts() {
+        let repo =
+            FakeGitRepository::new(None, &["develop", "main", "release/1.3.0"], "release/1.3.0");
+        repo.set_conflicts_into("develop", &["CHANGELOG.md"]);
+
+        let result = command_release_finish(&release_repo_config(), &repo, true);
+
+        assert!(matches!(
+            result,
+            Err(WfError::ReleaseError(ReleaseError::MergeBackConflict(_, main, dev, files)))
+                if main == "main" && dev == "develop" && files == vec!["CHANGELOG.md"]
+        ));
+        assert_eq!(repo.tags(), vec!["v1.3.0"]);
+        assert!(repo.deleted_branches().is_empty());
+        assert!(repo.pushed_refs().is_empty());
+    }
+
+    #[test]
+    fn test_release_finish_needs_release_branch_and_main() {
+        let repo = FakeGitRepository::new(None, &["develop", "main"], "PROJ-1-fix");
+
+        assert!(matches!(
+            command_release_finish(&release_repo_config(), &repo, false),
+            Err(WfError::ReleaseError(ReleaseError::NotAReleaseBranch(branch, prefix)))
+                if branch == "PROJ-1-fix" && prefix == "release/"
+        ));
+        assert!(matches!(
+            command_release_finish(&repo_config(), &repo, false),
+            Err(WfError::ReleaseError(ReleaseError::MainBranchNotSet))
+        ));
+    }
+
+    fn hotfix_repo_config() -> RepoConfig {
+        RepoConfig {
+            branches: release_repo_config().branches,
+            ..repo_config()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hotfix_start_from_main() {
+        let repo = FakeGitRepository::new(None, &["main", "develop"], "develop");
+        let tracker = tracker();
+        let prompter = ScriptedPrompter::new([Answer::Default]);
+        let answers = StartAnswers {
+            ticket_id: Some("PROJ-1"),
+            ..StartAnswers::default()
+        };
+
+        command_hotfix_start(&hotfix_repo_config(), &repo, &tracker, &prompter, &answers)
+            .await
+            .unwrap();
+
+        assert_eq!(repo.fast_forwards(), vec!["main"]);
+        assert_eq!(
+            repo.created_branches(),
+            vec![(
+                "hotfix/PROJ-1-fix-the-login".to_string(),
+                "main".to_string()
+            )]
+        );
+        assert_eq!(
+            tracker.transitions(),
+            vec![("PROJ-1".to_string(), "In Progress".to_string())]
+        );
+
+        assert!(matches!(
+            command_hotfix_start(&repo_config(), &repo, &tracker, &prompter, &answers).await,
+            Err(WfError::ReleaseError(ReleaseError::MainBranchNotSet))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_hotfix_finish_merges_into_main_and_base() {
+        let repo = FakeGitRepository::new(
+            None,
+            &["develop", "main", "hotfix/PROJ-1-fix"],
+            "hotfix/PROJ-1-fix",
+        );
+        let tracker = tracker();
+
+        command_hotfix_finish(&hotfix_repo_config(), &repo, Some(&tracker), true)
+            .await
+            .unwrap();
+
+        assert_eq!(repo.fetches(), vec!["main", "develop"]);
+        let merges: Vec<(String, String)> = repo
+            .merges()
+            .into_iter()
+            .map(|m| (m.branch, m.target))
+            .collect();
+        assert_eq!(
+            merges,
+            vec![
+                ("hotfix/PROJ-1-fix".to_string(), "main".to_string()),
+                ("hotfix/PROJ-1-fix".to_string(), "develop".to_string()),
+            ]
+        );
+        assert!(repo.tags().is_empty());
+        assert_eq!(repo.deleted_branches(), vec!["hotfix/PROJ-1-fix"]);
+        assert_eq!(
+            repo.pushed_refs(),
+            vec!["refs/heads/main", "refs/heads/develop"]
+        );
+        assert_eq!(repo.deleted_remote_branches(), vec!["hotfix/PROJ-1-fix"]);
+        assert_eq!(
+            tracker.transitions(),
+            vec![("PROJ-1".to_string(), "Done".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_hotfix_finish_keeps_remote_branch_until_pushed() {
+        let branches = ["develop", "main", "hotfix/PROJ-1-fix"];
+        let repo = FakeGitRepository::new(None, &branches, "hotfix/PROJ-1-fix");
+        command_hotfix_finish(&hotfix_repo_config(), &repo, None, false)
+            .await
+            .unwrap();
+        assert!(repo.pushed_refs().is_empty());
+        assert!(repo.deleted_remote_branches().is_empty());
+
+        let repo = FakeGitRepository::new(None, &branches, "hotfix/PROJ-1-fix");
+        repo.set_push_rejection("non-fast-forward");
+        assert!(
+            command_hotfix_finish(&hotfix_repo_config(), &repo, None, true)
+                .await
+                .is_err()
+        );
+        assert!(repo.deleted_remote_branches().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_hotfix_finish_with_unknown_issue() {
+        let repo = FakeGitRepository::new(
+            None,
+            &["develop", "main", "hotfix/PROJ-9-fix"],
+            "hotfix/PROJ-9-fix",
+        );
+        let tracker = tracker();
+
+        command_hotfix_finish(&hotfix_repo_config(), &repo, Some(&tracker), true)
+            .await
+            .unwrap();
+
+        assert_eq!(repo.deleted_branches(), vec!["hotfix/PROJ-9-fix"]);
+        assert!(tracker.transitions().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_hotfix_finish_reports_conflicts_by_branch() {
+        let repo = FakeGitRepository::new(
+            None,
+            &["develop", "main", "hotfix/PROJ-1-fix"],
+            "hotfix/PROJ-1-fix",
+        );
+        repo.set_conflicts_into("develop", &["src/login.rs"]);
+        let tracker = tracker();
+
+        let result =
+            command_hotfix_finish(&hotfix_repo_config(), &repo, Some(&tracker), true).await;
+
+        match result {
+            Err(WfError::ReleaseError(ReleaseError::HotfixConflicts(branch, conflicts))) => {
+                assert_eq!(branch, "hotfix/PROJ-1-fix");
+                assert_eq!(
+                    conflicts,
+                    vec![("develop".to_string(), vec!["src/login.rs".to_string()])]
+                );
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert_eq!(repo.merges().len(), 1);
+        assert!(repo.deleted_branches().is_empty());
+        assert!(repo.pushed_refs().is_empty());
+        assert!(tracker.transitions().is_empty());
+    }
+
+    /// Finish a hotfix conflicting with the base branch, then again once the base branch has the fix
+    async fn hotfix_finish_again_after_conflict<R: GitRepository>(open: impl Fn(&Path) -> R) {
+        let test_repo = TestRepository::new();
+        let repo = open(&test_repo.workdir);
+        test_repo.checkout("develop");
+        test_repo.commit("README.md", "Develop readme");
+        repo.create_and_checkout_branch("hotfix/login", "main")
+            .unwrap();
+        test_repo.commit("README.md", "Hotfix readme");
+
+        let result = command_hotfix_finish(&hotfix_repo_config(), &repo, None, false).await;
+
+        assert!(matches!(
+            result,
+            Err(WfError::ReleaseError(ReleaseError::HotfixConflicts(_, conflicts)))
+                if conflicts == vec![("develop".to_string(), vec!["README.md".to_string()])]
+        ));
+        assert_eq!(test_repo.head_branch(), "hotfix/login");
+
+        test_repo.checkout("develop");
+        test_repo.commit("README.md", "Hotfix readme");
+        test_repo.checkout("hotfix/login");
+        command_hotfix_finish(&hotfix_repo_config(), &repo, None, false)
+            .await
+            .unwrap();
+
+        assert_eq!(test_repo.head_branch(), "develop");
+        assert_eq!(
+            test_repo.last_commit("develop"),
+            ("Merge branch 'hotfix/login' into develop".to_string(), 2)
+        );
+        assert_eq!(
+            test_repo.last_commit("main"),
+            ("Merge branch 'hotfix/login' into main".to_string(), 2)
+        );
+        assert!(!repo
+            .branches()
+            .unwrap()
+            .contains(&"hotfix/login".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_hotfix_finish_again_after_conflict() {
+        hotfix_finish_again_after_conflict(|path| LocalGitRepository::open(path).unwrap()).await;
+        hotfix_finish_again_after_conflict(|path| CliGitRepository::open(path).unwrap()).await;
+    }
+
+    #[tokio::test]
+    async fn test_hotfix_finish_needs_hotfix_branch() {
+        let repo = FakeGitRepository::new(None, &["develop", "main"], "PROJ-1-fix");
+
+        assert!(matches!(
+            command_hotfix_finish(&hotfix_repo_config(), &repo, None, false).await,
+            Err(WfError::ReleaseError(ReleaseError::NotAHotfixBranch(branch, prefix)))
+                if branch == "PROJ-1-fix" && prefix == "hotfix/"
+        ));
+    }
+
+    fn backport_repo(branches: &[&str]) -> FakeGitRepository {
+        let mut all = vec![
+            "develop",
+            "origin/develop",
+            "origin/release/1.2",
+            "origin/release/1.3",
+        ];
+        all.extend(branches);
+        FakeGitRepository::new(None, &all, "develop")
+    }
+
+    fn targets() -> Vec<String> {
+        vec!["release/1.2".to_string(), "release/1.3".to_string()]
+    }
+
+    #[tokio::test]
+    async fn test_backport_cherry_picks_onto_each_target() {
+        let repo = backport_repo(&[]);
+        repo.set_commits(&[
+            ("c3", "fix: keep the session (PROJ-1)"),
+            ("c2", "PROJ-2 Add a logout button"),
+            ("c1", "PROJ-1 Fix the login"),
+        ]);
+        let tracker = tracker();
+
+        command_backport(&repo_config(), &repo, &tracker, "PROJ-1", &targets(), true)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            repo.commit_ranges(),
+            vec![
+                "origin/develop",
+                "origin/release/1.2..origin/develop",
+                "origin/release/1.3..origin/develop"
+            ]
+        );
+        let branches = [
+            "backport/PROJ-1-release-1.2".to_string(),
+            "backport/PROJ-1-release-1.3".to_string(),
+        ];
+        assert_eq!(
+            repo.created_branches(),
+            vec![
+                (branches[0].clone(), "origin/release/1.2".to_string()),
+                (branches[1].clone(), "origin/release/1.3".to_string()),
+            ]
+        );
+        let ids = vec!["c1".to_string(), "c3".to_string()];
+        assert_eq!(
+            repo.cherry_picks(),
+            vec![
+                (branches[0].clone(), ids.clone()),
+                (branches[1].clone(), ids)
+            ]
+        );
+        assert_eq!(repo.pushes(), branches);
+        assert_eq!(
+            tracker.comments(),
+            vec![(
+                "PROJ-1".to_string(),
+                "Backported to:\n\
+                 - release/1.2 in backport/PROJ-1-release-1.2\n\
+                 - release/1.3 in backport/PROJ-1-release-1.3"
+                    .to_string()
+            )]
+        );
+    }
+
+    async fn backport_missing_commits<R: GitRepository>(open: impl Fn(&Path) -> R) {
+        let test_repo = TestRepository::new();
+        let repo = open(&test_repo.workdir);
+        // The session fix was already copied by hand on release/1.1
+        repo.create_and_checkout_branch("release/1.1", "main")
+            .unwrap();
+        test_repo.commit("session.txt", "fix: keep the session (PROJ-1)");
+        repo.push(false).unwrap();
+        repo.create_and_checkout_branch("release/1.2", "main")
+            .unwrap();
+        repo.push(false).unwrap();
+        test_repo.checkout("develop");
+        test_repo.commit("session.txt", "fix: keep the session (PROJ-1)");
+        test_repo.commit("login.txt", "feat: remember the login (PROJ-1)");
+        repo.push(false).unwrap();
+        repo.create_and_checkout_branch("release/1.3", "develop")
+            .unwrap();
+        repo.push(false).unwrap();
+        test_repo.checkout("develop");
+        let targets = ["release/1.1", "release/1.2", "release/1.3"].map(String::from);
+
+        command_backport(&repo_config(), &repo, &tracker(), "PROJ-1", &targets, false)
+            .await
+            .unwrap();
+
+        let picked = |target: &str| {
+            repo.commits(&format!(
+                "{}..backport/PROJ-1-{}",
+                target,
+                target.replace('/', "-")
+            ))
+            .unwrap()
+            .len()
+        };
+        assert_eq!(picked("release/1.1"), 1);
+        assert_eq!(picked("release/1.2"), 2);
+        assert_eq!(test_repo.head_branch(), "develop");
+        assert!(!repo
+            .branches()
+            .unwrap()
+            .contains(&"backport/PROJ-1-release-1.3".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_backport_missing_commits() {
+        backport_missing_commits(|path| LocalGitRepository::open(path).unwrap()).await;
+        backport_missing_commits(|path| CliGitRepository::open(path).unwrap()).await;
+    }
+
+    #[tokio::test]
+    async fn test_backport_reports_conflicts_by_target() {
+        let repo = backport_repo(&[]);
+        repo.set_commits(&[("c1", "PROJ-1 Fix the login")]);
+        repo.set_conflicts_into("backport/PROJ-1-release-1.2", &["src/login.rs"]);
+        let tracker = tracker();
+
+        let result =
+            command_backport(&repo_config(), &repo, &tracker, "PROJ-1", &targets(), false).await;
+
+        match result {
+            Err(WfError::BackportError(BackportError::Conflicts(key, conflicts))) => {
+                assert_eq!(key, "PROJ-1");
+                assert_eq!(
+                    conflicts,
+                    vec![("release/1.2".to_string(), vec!["src/login.rs".to_string()])]
+                );
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert_eq!(repo.created_branches().len(), 2);
+        assert_eq!(repo.pushes(), vec!["backport/PROJ-1-release-1.3"]);
+        assert!(tracker.comments().is_empty());
+        assert_eq!(repo.current(), "develop");
+    }
+
+    #[tokio::test]
+    async fn test_backport_goes_on_after_a_failed_target() {
+        let repo = backport_repo(&[]);
+        repo.set_commits(&[("c1", "PROJ-1 Fix the login")]);
+        let targets = ["release/1.1", "release/1.2", "release/1.3"].map(String::from);
+
+        let result =
+            command_backport(&repo_config(), &repo, &tracker(), "PROJ-1", &targets, false).await;
+
+        assert!(matches!(
+            result,
+            Err(WfError::BackportError(BackportError::Failed(key, failed)))
+                if key == "PROJ-1" && failed == ["release/1.1"]
+        ));
+        assert_eq!(
+            repo.pushes(),
+            vec!["backport/PROJ-1-release-1.2", "backport/PROJ-1-release-1.3"]
+        );
+        assert_eq!(repo.current(), "develop");
+    }
+
+    #[tokio::test]
+    async fn test_backport_commits_of_issue_branch() {
+        let repo = backport_repo(&["PROJ-1-fix-the-login", "backport/PROJ-1-release-1.2"]);
+        repo.set_commits(&[("c2", "Add a test"), ("c1", "Fix the login")]);
+
+        command_backport(
+            &repo_config(),
+            &repo,
+            &tracker(),
+            "PROJ-1",
+            &targets(),
+            false,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            repo.commit_ranges(),
+            vec![
+                "origin/develop",
+                "origin/develop..PROJ-1-fix-the-login",
+                "origin/release/1.3..PROJ-1-fix-the-login"
+            ]
+        );
+        assert_eq!(
+            repo.cherry_picks(),
+            vec![(
+                "backport/PROJ-1-release-1.3".to_string(),
+                vec!["c1".to_string(), "c2".to_string()]
+            )]
+        );
+
+        let repo = backport_repo(&[]);
+        assert!(matches!(
+            command_backport(&repo_config(), &repo, &tracker(), "PROJ-1", &targets(), false).await,
+            Err(WfError::BackportError(BackportError::NoCommits(key, base)))
+                if key == "PROJ-1" && base == "origin/develop"
+        ));
+    }
+
+    #[test]
+    fn test_sync_merges_remote_base() {
+        let repo = FakeGitRepository::new(None, &["develop", "PROJ-1-fix"], "PROJ-1-fix");
+
+        command_sync(&repo_config(), &repo, None).unwrap();
+
+        assert_eq!(repo.fetches(), vec!["develop"]);
+        assert_eq!(
+            repo.merges(),
+            vec![Merge {
+                branch: "origin/develop".to_string(),
+                target: "PROJ-1-fix".to_string(),
+                strategy: MergeStrategy::Merge,
+                message: "Merge branch 'develop' into PROJ-1-fix".to_string(),
+            }]
+        );
+        assert!(repo.rebases().is_empty());
+    }
+
+    #[test]
+    fn test_sync_rebase_from_config() {
+        let repo = FakeGitRepository::new(None, &["develop", "PROJ-1-fix"], "PROJ-1-fix");
+        let mut repo_config = repo_config();
+        repo_config.sync = Some(SyncConfig {
+            strategy: SyncStrategy::Rebase,
+        });
+
+        command_sync(&repo_config, &repo, None).unwrap();
+        assert_eq!(repo.rebases(), vec!["origin/develop"]);
+
+        command_sync(&repo_config, &repo, Some(SyncStrategy::Merge)).unwrap();
+        assert_eq!(repo.rebases().len(), 1);
+        assert_eq!(repo.merges().len(), 1);
+    }
+
+    #[test]
+    fn test_sync_refuses_base_branch_and_dirty_tree() {
+        let repo = FakeGitRepository::new(None, &["develop", "PROJ-1-fix"], "develop");
+        assert!(matches!(
+            command_sync(&repo_config(), &repo, None),
+            Err(WfError::OnBaseBranch(_))
+        ));
+
+        let repo = FakeGitRepository::new(None, &["develop", "PROJ-1-fix"], "PROJ-1-fix");
+        repo.set_dirty(true);
+        assert!(matches!(
+            command_sync(&repo_config(), &repo, None),
+            Err(WfError::GitError(GitError::DirtyWorkingTree))
+        ));
+        assert!(repo.fetches().is_empty());
+    }
+
+    #[test]
+    fn test_sync_conflicts_then_continue() {
+        let repo = FakeGitRepository::new(None, &["develop", "PROJ-1-fix"], "PROJ-1-fix");
+        repo.set_conflicts(&["src/main.rs"]);
+
+        let result = command_sync(&repo_config(), &repo, Some(SyncStrategy::Rebase));
+        assert!(matches!(
+            result,
+            Err(WfError::GitError(GitError::RebaseConflict(_, files))) if files == ["src/main.rs"]
+        ));
+        assert!(matches!(
+            command_sync(&repo_config(), &repo, None),
+            Err(WfError::GitError(GitError::OperationInProgress(
+                Operation::Rebase
+            )))
+        ));
+        assert!(matches!(
+            command_sync_continue(&repo),
+            Err(WfError::GitError(GitError::UnresolvedConflicts(_)))
+        ));
+
+        repo.set_conflicts(&[]);
+        command_sync_continue(&repo).unwrap();
+
+        assert_eq!(repo.operation_in_progress().unwrap(), None);
+    }
+
+    #[test]
+    fn test_sync_abort() {
+        let repo = FakeGitRepository::new(None, &["develop", "PROJ-1-fix"], "PROJ-1-fix");
+        assert!(matches!(
+            command_sync_abort(&repo),
+            Err(WfError::GitError(GitError::NothingInProgress))
+        ));
+
+        repo.set_conflicts(&["src/main.rs"]);
+        assert!(command_sync(&repo_config(), &repo, None).is_err());
+        command_sync_abort(&repo).unwrap();
+
+        assert_eq!(repo.operation_in_progress().unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_finish_squash_and_delete_branch() {
+        let repo = FakeGitRepository::new(None, &["develop", "PROJ-1-fix"], "PROJ-1-fix");
+        let tracker = tracker();
+        let prompter = ScriptedPrompter::new([Answer::Default]);
+        let answers = FinishAnswers {
+            strategy: Some(MergeStrategy::Squash),
+            delete_branch: true,
+            message: None,
+        };
+
+        command_finish(&repo_config(), &repo, Some(&tracker), &prompter, &answers)
+            .await
+            .unwrap();
+
+        assert_eq!(repo.fetches(), vec!["develop"]);
+        assert_eq!(repo.merges()[0].message, "PROJ-1 Fix the login".to_string());
+        assert_eq!(repo.current(), "develop");
+        assert_eq!(repo.deleted_branches(), vec!["PROJ-1-fix"]);
+        assert_eq!(repo.pushed_refs(), vec!["refs/heads/develop"]);
+        assert_eq!(repo.deleted_remote_branches(), vec!["PROJ-1-fix"]);
+        assert_eq!(
+            tracker.transitions(),
+            vec![("PROJ-1".to_string(), "Done".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_finish_keeps_remote_branch_until_base_is_pushed() {
+        let answers = FinishAnswers {
+            message: Some("Merge PROJ-1"),
+            ..FinishAnswers::default()
+        };
+        let repo = FakeGitRepository::new(None, &["develop", "PROJ-1-fix"], "PROJ-1-fix");
+        command_finish(
+            &repo_config(),
+            &repo,
+            None,
+            &ScriptedPrompter::new([]),
+            &answers,
+        )
+        .await
+        .unwrap();
+        assert!(repo.pushed_refs().is_empty());
+        assert!(repo.deleted_remote_branches().is_empty());
+
+        let answers = FinishAnswers {
+            delete_branch: true,
+            ..answers
+        };
+        let repo = FakeGitRepository::new(None, &["develop", "PROJ-1-fix"], "PROJ-1-fix");
+        repo.set_push_rejection("non-fast-forward");
+        let result = command_finish(
+            &repo_config(),
+            &repo,
+            None,
+            &ScriptedPrompter::new([]),
+            &answers,
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(WfError::GitError(GitError::NonFastForward(_)))
+        ));
+        assert_eq!(repo.merges().len(), 1);
+        assert!(repo.deleted_remote_branches().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_finish_removes_worktree() {
+        let repo = FakeGitRepository::new(None, &["develop"], "PROJ-1-fix");
+        let path = Path::new("/work/repo.worktrees/PROJ-1-fix");
+        repo.add_worktree(path, "PROJ-1-fix", "develop").unwrap();
+        let answers = FinishAnswers {
+            delete_branch: true,
+            message: Some("Merge PROJ-1"),
+            ..FinishAnswers::default()
+        };
+
+        command_finish(
+            &repo_config(),
+            &repo,
+            None,
+            &InquirePrompter::new(false),
+            &answers,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(repo.deleted_branches(), vec!["PROJ-1-fix"]);
+        assert_eq!(repo.removed_worktrees(), vec![path]);
+    }
+
+    /// Finish from the worktree of the branch, in a real repository where the base branch is
+    /// checked out in the main checkout
+    async fn finish_from_worktree<R: GitRepository>(open: impl Fn(&Path) -> R) {
+        let test_repo = TestRepository::new();
+        test_repo.checkout("develop");
+        let path = test_repo
+            .workdir
+            .with_file_name("work.worktrees")
+            .join("PROJ-1-fix");
+        open(&test_repo.workdir)
+            .add_worktree(&path, "PROJ-1-fix", "develop")
+            .unwrap();
+        let repo = open(&path);
+        std::fs::write(path.join("fix.txt"), "Fix the login").unwrap();
+        repo.commit_files(&[Path::new("fix.txt")], "Fix the login")
+            .unwrap();
+        let answers = FinishAnswers {
+            strategy: Some(MergeStrategy::Merge),
+            delete_branch: true,
+            message: Some("Merge PROJ-1"),
+        };
+
+        command_finish(
+            &repo_config(),
+            &repo,
+            None,
+            &InquirePrompter::new(false),
+            &answers,
+        )
+        .await
+        .unwrap();
+
+        assert!(!path.exists());
+        assert_eq!(test_repo.head_branch(), "develop");
+        assert_eq!(
+            test_repo.last_commit("develop"),
+            ("Merge PROJ-1".to_string(), 2)
+        );
+        assert!(test_repo.file_content("fix.txt").is_some());
+        assert!(test_repo
+            .repository()
+            .find_branch("PROJ-1-fix", git2::BranchType::Local)
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_finish_from_worktree() {
+        finish_from_worktree(|path| LocalGitRepository::open(path).unwrap()).await;
+        finish_from_worktree(|path| CliGitRepository::open(path).unwrap()).await;
+    }
+
+    async fn finish_from_worktree_with_conflicts<R: GitRepository>(open: impl Fn(&Path) -> R) {
+        let test_repo = TestRepository::new();
+        test_repo.checkout("develop");
+        let path = test_repo
+            .workdir
+            .with_file_name("work.worktrees")
+            .join("PROJ-1-fix");
+        open(&test_repo.workdir)
+            .add_worktree(&path, "PROJ-1-fix", "develop")
+            .unwrap();
+        test_repo.commit("fix.txt", "Fix the logout");
+        let repo = open(&path);
+        std::fs::write(path.join("fix.txt"), "Fix the login").unwrap();
+        repo.commit_files(&[Path::new("fix.txt")], "Fix the login")
+            .unwrap();
+        let answers = FinishAnswers {
+            strategy: Some(MergeStrategy::Merge),
+            delete_branch: true,
+            message: Some("Merge PROJ-1"),
+        };
+
+        let result = command_finish(
+            &repo_config(),
+            &repo,
+            None,
+            &InquirePrompter::new(false),
+            &answers,
+        )
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(WfError::GitError(GitError::MergeConflict(_, _, _)))
+        ));
+        assert_eq!(
+            std::fs::read_to_string(path.join("fix.txt")).unwrap(),
+            "Fix the login"
+        );
+        assert_eq!(repo.current_branch().unwrap(), "PROJ-1-fix");
+        assert_eq!(test_repo.head_branch(), "develop");
+        assert_eq!(
+            test_repo.last_commit("develop"),
+            ("Fix the logout".to_string(), 1)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_finish_from_worktree_with_conflicts() {
+        finish_from_worktree_with_conflicts(|path| LocalGitRepository::open(path).unwrap()).await;
+        finish_from_worktree_with_conflicts(|path| CliGitRepository::open(path).unwrap()).await;
+    }
+
+    #[tokio::test]
+    async fn test_finish_refuse_dirty_tree() {
+        let repo = FakeGitRepository::new(None, &["develop", "PROJ-1-fix"], "PROJ-1-fix");
+        repo.set_dirty(true);
+
+        let result = command_finish(
+            &repo_config(),
+            &repo,
+            None,
+            &ScriptedPrompter::new([]),
+            &FinishAnswers::default(),
+        )
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(WfError::GitError(GitError::DirtyWorkingTree))
+        ));
+        assert!(repo.merges().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_finish_refuse_on_conflicts() {
+        let repo = FakeGitRepository::new(None, &["develop", "PROJ-1-fix"], "PROJ-1-fix");
+        repo.set_conflicts(&["src/main.rs"]);
+        let tracker = tracker();
+        let prompter = ScriptedPrompter::new([Answer::Default]);
+        let answers = FinishAnswers {
+            delete_branch: true,
+            ..FinishAnswers::default()
+        };
+
+        let result =
+            command_finish(&repo_config(), &repo, Some(&tracker), &prompter, &answers).await;
+
+        assert!(matches!(
+            result,
+            Err(WfError::GitError(GitError::MergeConflict(_, _, files))) if files == vec!["src/main.rs"]
+        ));
+        assert_eq!(repo.current(), "PROJ-1-fix");
+        assert!(repo.deleted_branches().is_empty());
+        assert!(tracker.transitions().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_finish_refuse_on_base_branch() {
+        let repo = FakeGitRepository::new(None, &["develop"], "develop");
+
+        let result = command_finish(
+            &repo_config(),
+            &repo,
+            None,
+            &ScriptedPrompter::new([]),
+            &FinishAnswers::default(),
+        )
+        .await;
+
+        assert!(matches!(result, Err(WfError::OnBaseBranch(_))));
+    }
+
+    #[tokio::test]
+    async fn test_status_with_issue_from_template() {
+        let repo = FakeGitRepository::new(
+            None,
+            &["develop", "jdoe/PROJ-2-logout"],
+            "jdoe/PROJ-2-logout",
+        );
+        repo.set_ahead_behind("jdoe/PROJ-2-logout", "develop", 2, 1);
+        repo.set_ahead_behind("jdoe/PROJ-2-logout", "origin/jdoe/PROJ-2-logout", 0, 0);
+        let mut repo_config = repo_config();
+        repo_config.branch_name = Some(BranchNameConfig {
+            template: Some("{user}/{key}-{slug}".to_string()),
+            ..BranchNameConfig::default()
+        });
+
+        let report = command_status(&repo_config, &repo, Some(&tracker()))
+            .await
+            .unwrap();
+
+        assert_eq!(report.issue_key.as_deref(), Some("PROJ-2"));
+        assert_eq!(report.issue.unwrap().summary, "Add a logout button");
+        assert_eq!(report.base_ahead_behind, Some((2, 1)));
+        assert_eq!(report.remote_ahead_behind, Some((0, 0)));
+        assert!(report.clean);
+    }
+
+    #[tokio::test]
+    async fn test_status_with_configured_remote() {
+        let repo = FakeGitRepository::new(None, &["develop", "PROJ-1-fix"], "PROJ-1-fix")
+            .with_remote("upstream");
+        repo.set_ahead_behind("PROJ-1-fix", "upstream/PROJ-1-fix", 1, 0);
+
+        let report = command_status(&repo_config(), &repo, None).await.unwrap();
+
+        assert_eq!(report.remote_ahead_behind, Some((1, 0)));
+        assert!(report
+            .to_string()
+            .contains("1 commit(s) ahead of upstream/PROJ-1-fix"));
+    }
+
+    #[tokio::test]
+    async fn test_status_not_pushed_and_dirty() {
+        let repo = FakeGitRepository::new(None, &["develop", "PROJ-1-fix"], "PROJ-1-fix");
+        repo.set_ahead_behind("PROJ-1-fix", "develop", 1, 0);
+        repo.set_dirty(true);
+
+        let report = command_status(&repo_config(), &repo, None).await.unwrap();
+
+        assert_eq!(report.issue_key.as_deref(), Some("PROJ-1"));
+        assert!(report.issue.is_none());
+        assert_eq!(
+            report.to_string(),
+            "On branch PROJ-1-fix (base develop)\n\
+             Issue PROJ-1\n\
+             1 commit(s) ahead of develop\n\
+             Not pushed to origin\n\
+             Uncommitted changes in the working tree"
+        );
+    }
+
+    #[test]
+    fn test_init_repo_keeps_config_when_not_confirmed() {
+        let workdir = tempfile::tempdir().unwrap();
+        let repo = FakeGitRepository::new(Some(workdir.path()), &["main", "develop"], "main");
+        let prompter = ScriptedPrompter::new([Answer::No]);
+
+        let result = command_init_repo(repo_config(), &repo, &prompter, None).unwrap();
+
+        assert_eq!(result.branches.unwrap().dev, "develop");
+        assert!(!workdir.path().join(".workflow").exists());
+    }
+
+    #[test]
+    fn test_init_repo_overwrite() {
+        let workdir = tempfile::tempdir().unwrap();
+        let repo = FakeGitRepository::new(Some(workdir.path()), &["main", "develop"], "main");
+        let prompter = ScriptedPrompter::new([
+            Answer::Yes,
+            Answer::Choice("main".to_string()),
+            Answer::Default,
+        ]);
+
+        command_init_repo(repo_config(), &repo, &prompter, None).unwrap();
+
+        let saved = RepoConfig::load(workdir.path()).unwrap();
+        assert_eq!(saved.branches.as_ref().unwrap().dev, "main");
+        assert_eq!(
+            saved.transition_for(WorkflowEvent::Start),
+            Some("In Progress")
+        );
+    }
+
+    #[test]
+    fn test_init_config_keeps_config_when_not_confirmed() {
+        let config = Config {
+            github: Some(GitHubConfig {
+                api_url: "https://api.github.com".to_string(),
+                token: SecretRef::Plain("token".to_string()),
+            }),
+            ..Config::default()
+        };
+        let prompter = ScriptedPrompter::new([Answer::No]);
+
+        let result = command_init_config(config, &prompter, &ConfigAnswers::default()).unwrap();
+
+        assert!(result.github.is_some());
+        assert_eq!(prompter.questions().len(), 1);
+    }
+}
@@ -1,32 +1,278 @@
-use std::path::Path;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
 
+use clap::ValueEnum;
 use confy::ConfyError;
-use inquire::InquireError;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 use crate::adapt_err::Adapt;
+use crate::git::DEFAULT_REMOTE;
+use crate::prompt::PromptError;
+use crate::secret::{SecretError, SecretRef};
 
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct Config {
+    // Plain values must come before tables to be stored as TOML
+    #[serde(default)]
+    pub git_backend: GitBackend,
     pub jira: Option<JiraConfig>,
+    pub github: Option<GitHubConfig>,
+    pub gitlab: Option<GitLabConfig>,
+    pub git_credentials: Option<GitCredentialsConfig>,
+}
+
+/// Implementation used to operate on git repositories
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum GitBackend {
+    /// Built-in libgit2 implementation
+    #[default]
+    Libgit2,
+    /// Shell out to the installed `git` command
+    Cli,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct JiraConfig {
     pub host: String,
     pub user: String,
-    pub token: String,
+    pub token: SecretRef,
 }
 
-#[derive(Debug, Serialize, Deserialize, Default)]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GitHubConfig {
+    pub api_url: String,
+    pub token: SecretRef,
+}
+
+pub const GITHUB_API_URL: &str = "https://api.github.com";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GitLabConfig {
+    pub api_url: String,
+    pub token: SecretRef,
+}
+
+pub const GITLAB_API_URL: &str = "https://gitlab.com/api/v4";
+
+/// Credentials of the libgit2 backend, tried after the ssh agent
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct GitCredentialsConfig {
+    /// Private ssh keys, `~/.ssh/id_ed25519`, `id_ecdsa` and `id_rsa` when empty
+    #[serde(default)]
+    pub ssh_keys: Vec<PathBuf>,
+    /// User sent with the https token, most forges accept any
+    pub https_user: Option<String>,
+    /// Token for https remotes, used when no git credential helper knows the remote
+    pub https_token: Option<SecretRef>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
 pub struct RepoConfig {
+    // Plain values must come before tables to be stored as TOML
+    #[serde(default)]
+    pub tracker: IssueTrackerKind,
+    /// Query used to list issues when starting without a ticket id, in the tracker syntax
+    #[serde(alias = "issues_jql")]
+    pub issues_query: Option<String>,
+    /// Remote to fetch from and push to, `origin` when not set
+    pub remote: Option<String>,
     pub branches: Option<BranchsName>,
+    pub transitions: Option<IssueTransitions>,
+    pub branch_name: Option<BranchNameConfig>,
+    pub commit_message: Option<CommitMessageConfig>,
+    pub conventional_commits: Option<ConventionalCommitsConfig>,
+    pub start: Option<StartConfig>,
+    pub sync: Option<SyncConfig>,
+    pub finish: Option<FinishConfig>,
+    pub release: Option<ReleaseConfig>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Where `start` creates the new branch from
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum StartFrom {
+    /// Fast-forward the local base branch to its remote tracking branch and branch from it
+    #[default]
+    FastForward,
+    /// Branch from the remote tracking branch, e.g. `origin/develop`, the local one is left as it is
+    Remote,
+    /// Branch from the local base branch without updating it
+    Local,
+}
+
+/// Behaviour of the start command
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct StartConfig {
+    #[serde(default)]
+    pub from: StartFrom,
+    /// Create a linked worktree for the new branch instead of checking it out in place
+    #[serde(default)]
+    pub worktree: bool,
+    /// Directory of the worktrees relative to the repository, `../<repository>.worktrees` if not set
+    pub worktrees_dir: Option<PathBuf>,
+}
+
+/// How `sync` brings the changes of the base branch into the work branch
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncStrategy {
+    /// Merge the base branch, the pushed history of the work branch is kept
+    #[default]
+    Merge,
+    /// Replay the commits of the work branch on the base branch, it must then be force pushed
+    Rebase,
+}
+
+/// Behaviour of the sync command
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct SyncConfig {
+    #[serde(default)]
+    pub strategy: SyncStrategy,
+}
+
+/// How `finish` merges a branch into the base branch
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum MergeStrategy {
+    /// Keep the branch history with a merge commit
+    #[default]
+    Merge,
+    /// Add all the changes of the branch as a single commit
+    Squash,
+}
+
+/// Behaviour of the finish command
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct FinishConfig {
+    #[serde(default)]
+    pub strategy: MergeStrategy,
+    /// Delete the local and remote branch once merged
+    #[serde(default)]
+    pub delete_branch: bool,
+}
+
+/// Behaviour of the release commands
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct ReleaseConfig {
+    /// Prefix of the release tags, `v` when not set
+    pub tag_prefix: Option<String>,
+    /// Files holding the version, relative to the repository, `Cargo.toml` and `package.json`
+    /// are used when they exist if not set
+    #[serde(default)]
+    pub version_files: Vec<PathBuf>,
+}
+
+impl ReleaseConfig {
+    pub fn tag_prefix(&self) -> &str {
+        self.tag_prefix.as_deref().unwrap_or("v")
+    }
+}
+
+/// How branch names are built when starting an issue
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct BranchNameConfig {
+    /// Template with `{key}`, `{slug}`, `{type}`, `{prefix}`, `{user}` and `{date}` placeholders
+    pub template: Option<String>,
+    /// Maximum length of the branch name, the slug is shortened to fit
+    pub max_length: Option<usize>,
+    /// Value of `{prefix}` by issue type, e.g. `Bug = "bugfix"`
+    #[serde(default)]
+    pub prefixes: BTreeMap<String, String>,
+}
+
+/// How the git hooks installed by wf handle the issue key in commit messages
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct CommitMessageConfig {
+    /// Subject line with `{key}` and `{message}` placeholders, `{key} {message}` when not set
+    pub template: Option<String>,
+    /// Refuse commits whose message does not contain the issue key of the branch
+    #[serde(default)]
+    pub require_key: bool,
+}
+
+/// Conventional Commits rules checked by `lint-commits` and the commit-msg hook
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ConventionalCommitsConfig {
+    /// Allowed types, also the order of the changelog sections
+    #[serde(default = "default_commit_types")]
+    pub types: Vec<String>,
+    /// Allowed scopes, any scope is accepted when empty
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    /// Refuse commits without a scope
+    #[serde(default)]
+    pub require_scope: bool,
+}
+
+impl Default for ConventionalCommitsConfig {
+    fn default() -> Self {
+        Self {
+            types: default_commit_types(),
+            scopes: Vec::new(),
+            require_scope: false,
+        }
+    }
+}
+
+fn default_commit_types() -> Vec<String> {
+    [
+        "feat", "fix", "perf", "refactor", "docs", "test", "build", "ci", "chore", "style",
+        "revert",
+    ]
+    .iter()
+    .map(|t| t.to_string())
+    .collect()
+}
+
+/// Issue tracker used by a repository
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum IssueTrackerKind {
+    #[default]
+    Jira,
+    GitHub,
+    GitLab,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct BranchsName {
     pub dev: String,
+    /// Production branch releases are merged into and tagged on, hotfixes start from it
+    pub main: Option<String>,
+    /// Prefix of the release branches, `release/` when not set
+    pub release: Option<String>,
+    /// Prefix of the hotfix branches, `hotfix/` when not set
+    pub hotfix: Option<String>,
+}
+
+impl BranchsName {
+    pub fn release_prefix(&self) -> &str {
+        self.release.as_deref().unwrap_or("release/")
+    }
+
+    pub fn hotfix_prefix(&self) -> &str {
+        self.hotfix.as_deref().unwrap_or("hotfix/")
+    }
+}
+
+/// Status issues are moved to when a workflow event happens, e.g. `start = "In Progress"`
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct IssueTransitions {
+    pub start: Option<String>,
+    pub push: Option<String>,
+    pub pr: Option<String>,
+    pub finish: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorkflowEvent {
+    Start,
+    Push,
+    Pr,
+    Finish,
 }
 
 #[derive(Debug, Error)]
@@ -34,7 +280,28 @@ pub enum ConfigError {
     #[error("Configuration error {0}")]
     ConfyError(#[from] ConfyError),
     #[error("Input error {0}")]
-    InquireError(#[from] InquireError),
+    PromptError(#[from] PromptError),
+    #[error("Secret error {0}")]
+    SecretError(#[from] SecretError),
+}
+
+impl Display for GitBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GitBackend::Libgit2 => write!(f, "libgit2 (built-in)"),
+            GitBackend::Cli => write!(f, "git command line"),
+        }
+    }
+}
+
+impl Display for IssueTrackerKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IssueTrackerKind::Jira => write!(f, "Jira"),
+            IssueTrackerKind::GitHub => write!(f, "GitHub Issues"),
+            IssueTrackerKind::GitLab => write!(f, "GitLab Issues"),
+        }
+    }
 }
 
 impl Config {
@@ -47,7 +314,7 @@ impl Config {
     }
 
     pub fn is_set(&self) -> bool {
-        self.jira.is_some()
+        self.jira.is_some() || self.github.is_some() || self.gitlab.is_some()
     }
 
     pub fn is_not_set(&self) -> bool {
@@ -70,7 +337,141 @@ impl RepoConfig {
         self.branches.is_some()
     }
 
+    pub fn remote_name(&self) -> &str {
+        self.remote.as_deref().unwrap_or(DEFAULT_REMOTE)
+    }
+
+    /// Status the current issue should be moved to when the given event happens
+    pub fn transition_for(&self, event: WorkflowEvent) -> Option<&str> {
+        let transitions = self.transitions.as_ref()?;
+        match event {
+            WorkflowEvent::Start => transitions.start.as_deref(),
+            WorkflowEvent::Push => transitions.push.as_deref(),
+            WorkflowEvent::Pr => transitions.pr.as_deref(),
+            WorkflowEvent::Finish => transitions.finish.as_deref(),
+        }
+    }
+
     pub fn is_not_set(&self) -> bool {
         !self.is_set()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store_full_config() {
+        let config = Config {
+            git_backend: GitBackend::Cli,
+            jira: Some(JiraConfig {
+                host: "https://jira.example.com".to_string(),
+                user: "jdoe".to_string(),
+                token: SecretRef::Keyring {
+                    keyring: "jira".to_string(),
+                },
+            }),
+            github: None,
+            gitlab: None,
+            git_credentials: Some(GitCredentialsConfig {
+                https_user: None,
+                https_token: Some(SecretRef::Command {
+                    command: "pass show git".to_string(),
+                }),
+                ssh_keys: vec![PathBuf::from("/home/jdoe/.ssh/work_ed25519")],
+            }),
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+
+        confy::store_path(&path, &config).unwrap();
+        let loaded: Config = confy::load_path(&path).unwrap();
+
+        assert_eq!(loaded.git_backend, GitBackend::Cli);
+        assert_eq!(loaded.jira.unwrap().user, "jdoe");
+        assert_eq!(loaded.git_credentials.unwrap().ssh_keys.len(), 1);
+    }
+
+    #[test]
+    fn test_store_full_repo_config() {
+        let repo_config = RepoConfig {
+            tracker: IssueTrackerKind::GitHub,
+            issues_query: Some("is:open".to_string()),
+            remote: Some("upstream".to_string()),
+            branches: Some(BranchsName {
+                dev: "develop".to_string(),
+                main: Some("main".to_string()),
+                release: None,
+                hotfix: None,
+            }),
+            transitions: Some(IssueTransitions::default()),
+            start: Some(StartConfig {
+                from: StartFrom::Remote,
+                worktree: true,
+                worktrees_dir: Some(PathBuf::from("../worktrees")),
+            }),
+            sync: Some(SyncConfig {
+                strategy: SyncStrategy::Rebase,
+            }),
+            finish: Some(FinishConfig {
+                strategy: MergeStrategy::Squash,
+                delete_branch: true,
+            }),
+            release: Some(ReleaseConfig {
+                tag_prefix: Some("release-".to_string()),
+                version_files: vec![PathBuf::from("VERSION")],
+            }),
+            branch_name: Some(BranchNameConfig {
+                template: Some("{key}".to_string()),
+                max_length: Some(40),
+                prefixes: BTreeMap::from([("Bug".to_string(), "fix".to_string())]),
+            }),
+            commit_message: Some(CommitMessageConfig {
+                template: Some("[{key}] {message}".to_string()),
+                require_key: true,
+            }),
+            conventional_commits: Some(ConventionalCommitsConfig {
+                types: vec!["feat".to_string(), "fix".to_string()],
+                scopes: vec!["api".to_string()],
+                require_scope: true,
+            }),
+        };
+        let workdir = tempfile::tempdir().unwrap();
+
+        repo_config.save(workdir.path()).unwrap();
+        let loaded = RepoConfig::load(workdir.path()).unwrap();
+
+        assert_eq!(loaded.tracker, IssueTrackerKind::GitHub);
+        assert_eq!(loaded.remote_name(), "upstream");
+        assert_eq!(loaded.branch_name.unwrap().max_length, Some(40));
+        let start = loaded.start.unwrap();
+        assert_eq!(start.from, StartFrom::Remote);
+        assert!(start.worktree);
+        assert_eq!(loaded.sync.unwrap().strategy, SyncStrategy::Rebase);
+        assert!(loaded.conventional_commits.unwrap().require_scope);
+        let branches = loaded.branches.unwrap();
+        assert_eq!(branches.main.as_deref(), Some("main"));
+        assert_eq!(branches.release_prefix(), "release/");
+        assert_eq!(loaded.release.unwrap().tag_prefix(), "release-");
+    }
+
+    #[test]
+    fn test_conventional_commits_default_types() {
+        let workdir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            workdir.path().join(".workflow"),
+            "[conventional_commits]\nscopes = [\"api\"]\n",
+        )
+        .unwrap();
+
+        let loaded = RepoConfig::load(workdir.path()).unwrap();
+
+        let conventional_commits = loaded.conventional_commits.unwrap();
+        assert_eq!(conventional_commits.scopes, vec!["api"]);
+        assert_eq!(
+            conventional_commits.types,
+            ConventionalCommitsConfig::default().types
+        );
+    }
+}
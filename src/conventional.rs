@@ -0,0 +1,158 @@
+use std::sync::OnceLock;
+
+use regex::Regex;
+use thiserror::Error;
+
+use crate::config::ConventionalCommitsConfig;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ConventionalError {
+    #[error("\"{0}\" does not follow Conventional Commits, expected \"type(scope): description\"")]
+    InvalidSubject(String),
+    #[error("Unknown type {0}, expected one of: {}", .1.join(", "))]
+    UnknownType(String, Vec<String>),
+    #[error("Unknown scope {0}, expected one of: {}", .1.join(", "))]
+    UnknownScope(String, Vec<String>),
+    #[error("A scope is required, e.g. \"{0}(scope): description\"")]
+    MissingScope(String),
+    #[error("{0} of the {1} commits in {2} do not follow Conventional Commits")]
+    InvalidCommits(usize, usize, String),
+}
+
+/// Commit message following Conventional Commits, `type(scope)!: description`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConventionalCommit<'a> {
+    pub kind: &'a str,
+    pub scope: Option<&'a str>,
+    /// Marked with `!` after the type or with a `BREAKING CHANGE:` footer
+    pub breaking: bool,
+    pub description: &'a str,
+}
+
+/// Subject of a Conventional Commit, compiled once
+static SUBJECT: OnceLock<Option<Regex>> = OnceLock::new();
+
+/// Parse the subject of a message and look for a breaking change footer in its body
+pub fn parse(message: &str) -> Result<ConventionalCommit<'_>, ConventionalError> {
+    let subject = message.lines().next().unwrap_or_default();
+    let captures = SUBJECT
+        .get_or_init(|| {
+            Regex::new(
+                r"^(?P<kind>[A-Za-z]+)(?:\((?P<scope>[^()\s]+)\))?(?P<breaking>!)?: (?P<description>\S.*)$",
+            )
+            .ok()
+        })
+        .as_ref()
+        .and_then(|regex| regex.captures(subject))
+    .ok_or_else(|| ConventionalError::InvalidSubject(subject.to_string()))?;
+    let text = |name| captures.name(name).map(|m| m.as_str());
+
+    let breaking_footer = message
+        .lines()
+        .skip(1)
+        .any(|line| line.starts_with("BREAKING CHANGE:") || line.starts_with("BREAKING-CHANGE:"));
+
+    Ok(ConventionalCommit {
+        kind: text("kind").unwrap_or_default(),
+        scope: text("scope"),
+        breaking: text("breaking").is_some() || breaking_footer,
+        description: text("description").unwrap_or_default().trim_end(),
+    })
+}
+
+/// Parse a message and check its type and scope are allowed by the configuration
+pub fn lint<'a>(
+    message: &'a str,
+    config: &ConventionalCommitsConfig,
+) -> Result<ConventionalCommit<'a>, ConventionalError> {
+    let commit = parse(message)?;
+
+    if !config.types.iter().any(|t| t == commit.kind) {
+        return Err(ConventionalError::UnknownType(
+            commit.kind.to_string(),
+            config.types.clone(),
+        ));
+    }
+    match commit.scope {
+        None if config.require_scope => {
+            Err(ConventionalError::MissingScope(commit.kind.to_string()))
+        }
+        Some(scope) if !config.scopes.is_empty() && !config.scopes.iter().any(|s| s == scope) => {
+            Err(ConventionalError::UnknownScope(
+                scope.to_string(),
+                config.scopes.clone(),
+            ))
+        }
+        _ => Ok(commit),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse("feat(login): add SSO\n\nRefs PROJ-1").unwrap(),
+            ConventionalCommit {
+                kind: "feat",
+                scope: Some("login"),
+                breaking: false,
+                description: "add SSO",
+            }
+        );
+        assert_eq!(
+            parse("fix!: drop the v1 api").unwrap(),
+            ConventionalCommit {
+                kind: "fix",
+                scope: None,
+                breaking: true,
+                description: "drop the v1 api",
+            }
+        );
+        assert!(
+            parse("refactor: split\n\nBREAKING CHANGE: config moved")
+                .unwrap()
+                .breaking
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_subject() {
+        for subject in ["Fix the login", "feat:add SSO", "feat(): add SSO", "feat: "] {
+            assert_eq!(
+                parse(subject),
+                Err(ConventionalError::InvalidSubject(subject.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn test_lint_types_and_scopes() {
+        let config = ConventionalCommitsConfig {
+            scopes: vec!["api".to_string(), "ui".to_string()],
+            ..ConventionalCommitsConfig::default()
+        };
+
+        assert!(lint("feat(api): add users", &config).is_ok());
+        assert!(lint("feat: add users", &config).is_ok());
+        assert!(matches!(
+            lint("feature(api): add users", &config),
+            Err(ConventionalError::UnknownType(kind, _)) if kind == "feature"
+        ));
+        assert!(matches!(
+            lint("fix(db): lock", &config),
+            Err(ConventionalError::UnknownScope(scope, _)) if scope == "db"
+        ));
+
+        let config = ConventionalCommitsConfig {
+            require_scope: true,
+            ..config
+        };
+        assert_eq!(
+            lint("fix: lock", &config),
+            Err(ConventionalError::MissingScope("fix".to_string()))
+        );
+    }
+}
@@ -1,6 +1,9 @@
-use crate::{config::ConfigError, git::GitError, jira::JiraError};
+use crate::{
+    backport::BackportError, config::ConfigError, conventional::ConventionalError, git::GitError,
+    hooks::HookError, hosting::HostingError, jira::JiraError, prompt::PromptError,
+    release::ReleaseError, tracker::TrackerError,
+};
 use clap::Error;
-use inquire::InquireError;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -12,11 +15,31 @@ pub enum WfError {
     #[error("Git error: {0}")]
     GitError(#[from] GitError),
     #[error("Input error: {0}")]
-    InquireError(#[from] InquireError),
+    PromptError(#[from] PromptError),
     #[error("Configuration error: {0}")]
     ConfigError(#[from] ConfigError),
     #[error("{0}")]
     CliArgsError(#[from] Error),
     #[error("Current repository has no working directory !?!")]
     NoGitWorkingDirectory,
+    #[error("Hosting error: {0}")]
+    HostingError(#[from] HostingError),
+    #[error("GitHub is not configured, please run init command first")]
+    HostingNotConfigured,
+    #[error("Commit message error: {0}")]
+    ConventionalError(#[from] ConventionalError),
+    #[error("Hook error: {0}")]
+    HookError(#[from] HookError),
+    #[error("Release error: {0}")]
+    ReleaseError(#[from] ReleaseError),
+    #[error("Backport error: {0}")]
+    BackportError(#[from] BackportError),
+    #[error("Issue tracker error: {0}")]
+    TrackerError(#[from] TrackerError),
+    #[error("{0} is not configured, please run init command first")]
+    TrackerNotConfigured(String),
+    #[error("No issue found with query: {0}")]
+    NoIssueFound(String),
+    #[error("You are on the base branch {0}, please checkout a work branch first")]
+    OnBaseBranch(String),
 }
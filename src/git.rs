@@ -1,8 +1,20 @@
-use std::path::Path;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs;
+use std::path::{Path, PathBuf};
 
-use git2::{BranchType, Cred, Error, PushOptions, RemoteCallbacks, Repository};
+use git2::{
+    build::CheckoutBuilder, AnnotatedCommit, Branch, BranchType, Error, ErrorCode, FetchOptions,
+    Index, Oid, PushOptions, Rebase, Remote, Repository, RepositoryState, ResetType, RevparseMode,
+    Signature, Sort, StashApplyOptions, StatusOptions, WorktreeAddOptions, WorktreePruneOptions,
+};
 use thiserror::Error;
 
+use crate::config::MergeStrategy;
+use crate::credentials::{CredentialChain, GitCredentials};
+use crate::release::{self, Version};
+
 #[derive(Debug, Error)]
 pub enum GitError {
     #[error("Cannot open repository")]
@@ -13,49 +25,416 @@ pub enum GitError {
     CommitNotFound(String),
     #[error("Cannot create branch {0}")]
     CannotCreateBranch(String),
+    #[error("Branch {0} already exists")]
+    BranchExists(String),
     #[error("Cannot checkout branch {0}: {1}")]
     CannotCheckoutBranch(String, Error),
     #[error("Cannot get HEAD")]
     CannotGetHead,
     #[error("You are not in a branch, please checkout a branch first")]
     NotInABranch,
-    #[error("Repository origin not found")]
-    OriginNotFound,
-    #[error("Cannot push to origin: {0}")]
-    CannotPushToOrigin(Error),
+    #[error("Remote {0} not found")]
+    RemoteNotFound(String),
+    #[error("No credentials for {0}, please configure an ssh key or an https token")]
+    NoCredentials(String),
+    #[error("Authentication to {0} failed, tried: {1}")]
+    AuthenticationFailed(String, String),
+    #[error("Cannot get credentials: {0}")]
+    CredentialError(String),
+    #[error("Cannot push to remote: {0}")]
+    CannotPush(Error),
+    #[error("Push of {0} rejected, the remote branch has commits you do not have, please update your branch first")]
+    NonFastForward(String),
+    #[error("Push of {0} refused, the branch is protected on the remote: {1}")]
+    ProtectedBranch(String, String),
+    #[error("Push of {0} rejected: {1}")]
+    PushRejected(String, String),
+    #[error("Branch {0} changed on the remote since it was last fetched, please fetch it before forcing the push")]
+    StaleLease(String),
     #[error("Cannot list branches {0}")]
     CannotListBranches(Error),
+    #[error("Invalid branch name {0}: {1}")]
+    InvalidBranchName(String, String),
+    #[error("git {0} failed: {1}")]
+    CommandFailed(String, String),
+    #[error("You have uncommitted changes, please commit or stash them first")]
+    DirtyWorkingTree,
+    #[error("Branch {0} has diverged from {1}, please reconcile them first")]
+    Diverged(String, String),
+    #[error("Cannot merge {0} into {1}, conflicts in: {}", .2.join(", "))]
+    MergeConflict(String, String, Vec<String>),
+    #[error("Cannot cherry-pick {0} onto {1}, conflicts in: {}", .2.join(", "))]
+    CherryPickConflict(String, String, Vec<String>),
+    #[error("Cannot rebase {0}, conflicts in: {}", .1.join(", "))]
+    RebaseConflict(String, Vec<String>),
+    #[error("Conflicts remain in: {}, please resolve them and add them first", .0.join(", "))]
+    UnresolvedConflicts(Vec<String>),
+    #[error("A {0} is in progress, please continue or abort it first")]
+    OperationInProgress(Operation),
+    #[error("No rebase or merge in progress")]
+    NothingInProgress,
+    #[error("Directory {0} already exists, cannot create a worktree in it")]
+    WorktreeExists(String),
+    #[error("No worktree found for {0}")]
+    WorktreeNotFound(String),
+    #[error("Worktree {0} has changes, please commit or remove them first")]
+    DirtyWorktree(String),
+    #[error("Tag {0} already exists")]
+    TagExists(String),
+    #[error("Cannot restore the stashed changes on {0}, they are kept in the stash: {1}")]
+    CannotRestoreStash(String, String),
     #[error("Git Error")]
     GitErro(#[from] Error),
 }
 
+/// Remote used when none is configured
+pub const DEFAULT_REMOTE: &str = "origin";
+
+/// Linked worktree of the repository
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Worktree {
+    pub path: PathBuf,
+    /// Branch checked out in the worktree, `None` when its HEAD is detached
+    pub branch: Option<String>,
+}
+
+/// Commit listed by `commits`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitInfo {
+    pub id: String,
+    /// Full message, subject and body, without trailing newlines
+    pub message: String,
+}
+
+impl CommitInfo {
+    pub fn short_id(&self) -> &str {
+        &self.id[..self.id.len().min(7)]
+    }
+
+    /// First line of the message
+    pub fn subject(&self) -> &str {
+        self.message.lines().next().unwrap_or_default()
+    }
+}
+
+/// Operation stopped on conflicts, waiting to be continued or aborted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Rebase,
+    Merge,
+}
+
 pub trait GitRepository {
+    /// Use another remote than `origin` to fetch and push
+    fn with_remote(self, remote: &str) -> Self
+    where
+        Self: Sized;
+
+    /// Name of the remote used to fetch and push
+    fn remote(&self) -> &str;
+
+    /// Name of the remote tracking branch of a branch, e.g. `origin/develop`
+    fn remote_branch(&self, branch: &str) -> String {
+        format!("{}/{}", self.remote(), branch)
+    }
+
+    /// Create `new_branch` from a local branch or a remote tracking branch like `origin/develop`.
+    ///
+    /// Uncommitted changes are carried over to the new branch. When they conflict with it, the
+    /// checkout fails and the new branch is deleted.
     fn create_and_checkout_branch(
         &self,
         new_branch: &str,
         from_branch: &str,
     ) -> Result<(), GitError>;
 
-    fn push(&self) -> Result<(), GitError>;
+    /// Checkout an existing local branch, refused when local changes conflict with it
+    fn checkout_branch(&self, branch: &str) -> Result<(), GitError>;
+
+    /// Create `new_branch` from a local branch or a remote tracking branch and check it out in a
+    /// new linked worktree at `path`, the current checkout is left untouched
+    fn add_worktree(
+        &self,
+        path: &Path,
+        new_branch: &str,
+        from_branch: &str,
+    ) -> Result<(), GitError>;
+
+    /// Linked worktrees of the repository, the main checkout is not included
+    fn worktrees(&self) -> Result<Vec<Worktree>, GitError>;
+
+    /// The same repository opened on its main checkout, with the same remote
+    fn main_worktree(&self) -> Result<Self, GitError>
+    where
+        Self: Sized;
+
+    /// Remove a linked worktree and its directory, refused when it has changes or untracked files
+    fn remove_worktree(&self, path: &Path) -> Result<(), GitError>;
+
+    /// Push the current branch and make it track the remote branch, like `git push -u`
+    ///
+    /// With `force_with_lease` the remote branch is replaced, but only if it is still where it
+    /// was at the last fetch.
+    fn push(&self, force_with_lease: bool) -> Result<(), GitError>;
 
     fn workdir(&self) -> Option<&Path>;
 
     fn branches(&self) -> Result<Vec<String>, GitError>;
+
+    /// Name of the branch currently checked out
+    fn current_branch(&self) -> Result<String, GitError>;
+
+    /// Directory git runs the hooks from, `core.hooksPath` when it is set
+    fn hooks_dir(&self) -> Result<PathBuf, GitError>;
+
+    /// Commits of a range like `v1.0..HEAD`, or all the commits reachable from a revision, newest
+    /// first. Merge commits are left out.
+    fn commits(&self, range: &str) -> Result<Vec<CommitInfo>, GitError>;
+
+    /// Most recent release tag, `prefix` followed by a version, reachable from a revision, `None`
+    /// when there is none
+    fn last_tag(&self, revision: &str, prefix: &str) -> Result<Option<String>, GitError>;
+
+    /// Add files, relative to the working directory, to the index and commit them on the
+    /// current branch
+    fn commit_files(&self, files: &[&Path], message: &str) -> Result<(), GitError>;
+
+    /// Add an annotated tag on HEAD
+    fn create_tag(&self, tag: &str, message: &str) -> Result<(), GitError>;
+
+    /// Push local branches and tags without forcing, e.g. `refs/heads/main` or `refs/tags/v1.0.0`
+    fn push_refs(&self, refs: &[String]) -> Result<(), GitError>;
+
+    /// Url of the remote
+    fn remote_url(&self) -> Result<String, GitError>;
+
+    /// Returns true if the current branch is missing on the remote or points to another commit
+    fn needs_push(&self) -> Result<bool, GitError>;
+
+    /// Number of commits of `branch` missing from `other` and of `other` missing from `branch`.
+    ///
+    /// Both are names of local or remote tracking branches, e.g. `develop` or `origin/develop`.
+    /// Returns `None` when one of them does not exist.
+    fn ahead_behind(&self, branch: &str, other: &str) -> Result<Option<(usize, usize)>, GitError>;
+
+    /// Returns true when tracked files have no uncommitted changes
+    fn is_clean(&self) -> Result<bool, GitError>;
+
+    /// Stash the uncommitted changes of tracked files
+    fn stash(&self, message: &str) -> Result<(), GitError>;
+
+    /// Apply the last stash on the current branch and drop it.
+    ///
+    /// On conflicts the conflicting files are left with markers and the stash is kept.
+    fn stash_pop(&self) -> Result<(), GitError>;
+
+    /// Update the remote tracking branch of `branch` from the remote
+    fn fetch(&self, branch: &str) -> Result<(), GitError>;
+
+    /// Move a local branch to its remote tracking branch when it is behind.
+    ///
+    /// Nothing is done when the local branch is up to date or ahead, an error is returned when
+    /// both have diverged.
+    fn fast_forward(&self, branch: &str) -> Result<(), GitError>;
+
+    /// Merge `branch` into `target` and checkout `target`.
+    ///
+    /// On conflicts nothing is changed, `branch` stays checked out and `MergeConflict` lists the
+    /// conflicting files. When a linked worktree has `branch`, the checkout is left as it was.
+    fn merge_into(
+        &self,
+        branch: &str,
+        target: &str,
+        strategy: MergeStrategy,
+        message: &str,
+    ) -> Result<(), GitError>;
+
+    /// Apply commits, oldest first, on the current branch with a `(cherry picked from commit ...)`
+    /// line added to their message. Commits whose changes the branch already has are skipped.
+    ///
+    /// On conflicts nothing is changed and `CherryPickConflict` lists the conflicting files of the
+    /// first commit that cannot be applied.
+    fn cherry_pick(&self, commits: &[String]) -> Result<(), GitError>;
+
+    /// Rebase the current branch onto a local or remote tracking branch.
+    ///
+    /// On conflicts the rebase stops with the conflicting files marked, `RebaseConflict` lists
+    /// them and the rebase waits to be continued or aborted.
+    fn rebase(&self, onto: &str) -> Result<(), GitError>;
+
+    /// Merge a local or remote tracking branch into the current branch, fast-forwarding if possible.
+    ///
+    /// On conflicts the merge stops with the conflicting files marked, `MergeConflict` lists them
+    /// and the merge waits to be continued or aborted.
+    fn merge(&self, branch: &str, message: &str) -> Result<(), GitError>;
+
+    /// Rebase or merge stopped on conflicts, if any
+    fn operation_in_progress(&self) -> Result<Option<Operation>, GitError>;
+
+    /// Resume the stopped rebase or merge once the conflicts are resolved and added to the index
+    fn continue_operation(&self) -> Result<(), GitError>;
+
+    /// Abort the stopped rebase or merge and go back to the state before it
+    fn abort_operation(&self) -> Result<(), GitError>;
+
+    /// Delete a local branch, even if it was not merged
+    fn delete_branch(&self, branch: &str) -> Result<(), GitError>;
+
+    /// Delete a branch on the remote, nothing is done if the remote does not have it
+    fn delete_remote_branch(&self, branch: &str) -> Result<(), GitError>;
 }
 
 pub struct LocalGitRepository {
     inner: Repository,
+    remote: String,
+    credentials: GitCredentials,
 }
 
 impl LocalGitRepository {
     pub fn discover() -> Result<Self, GitError> {
-        let inner = Repository::discover(".").map_err(|_| GitError::CannotOpenRepository)?;
+        Self::open(Path::new("."))
+    }
+
+    pub fn open(path: &Path) -> Result<Self, GitError> {
+        let inner = Repository::discover(path).map_err(|_| GitError::CannotOpenRepository)?;
+
+        Ok(Self {
+            inner,
+            remote: DEFAULT_REMOTE.to_string(),
+            credentials: GitCredentials::default(),
+        })
+    }
+
+    /// Use these credentials when the remote asks for authentication
+    pub fn with_credentials(self, credentials: GitCredentials) -> Self {
+        Self {
+            credentials,
+            ..self
+        }
+    }
+}
+
+impl LocalGitRepository {
+    fn checkout_new_branch(&self, branch: &Branch, name: &str) -> Result<(), GitError> {
+        let tree = branch
+            .get()
+            .peel(git2::ObjectType::Tree)
+            .map_err(|e| GitError::CannotCheckoutBranch(name.to_string(), e))?;
+
+        // A safe checkout keeps local changes and fails without touching anything on conflicts
+        self.inner
+            .checkout_tree(&tree, Some(CheckoutBuilder::new().safe()))
+            .map_err(|e| GitError::CannotCheckoutBranch(name.to_string(), e))?;
+
+        let ref_name = branch
+            .get()
+            .name()
+            .ok_or_else(|| GitError::BranchNotFound(name.to_string()))?;
+
+        self.inner
+            .set_head(ref_name)
+            .map_err(|e| GitError::CannotCheckoutBranch(name.to_string(), e))
+    }
+
+    /// Credentials for one network operation, asked when the remote needs them
+    fn credential_chain(&self) -> CredentialChain<'_> {
+        self.credentials.chain(self.inner.config().ok())
+    }
+
+    fn find_remote(&self) -> Result<Remote<'_>, GitError> {
+        self.inner
+            .find_remote(&self.remote)
+            .map_err(|_| GitError::RemoteNotFound(self.remote.clone()))
+    }
+
+    /// Stashes need a mutable repository, a second handle on the same repository is used
+    fn reopen(&self) -> Result<Repository, GitError> {
+        Repository::open(self.inner.path()).map_err(|_| GitError::CannotOpenRepository)
+    }
+
+    fn find_worktree(&self, path: &Path) -> Result<git2::Worktree, GitError> {
+        for name in self.inner.worktrees()?.iter().flatten() {
+            let worktree = self.inner.find_worktree(name)?;
+            if same_path(worktree.path(), path) {
+                return Ok(worktree);
+            }
+        }
+        Err(GitError::WorktreeNotFound(path.display().to_string()))
+    }
+
+    fn annotated_branch(&self, name: &str) -> Result<AnnotatedCommit<'_>, GitError> {
+        let branch = self
+            .inner
+            .find_branch(name, BranchType::Local)
+            .or_else(|_| self.inner.find_branch(name, BranchType::Remote))
+            .map_err(|_| GitError::BranchNotFound(name.to_string()))?;
+        Ok(self.inner.reference_to_annotated_commit(branch.get())?)
+    }
+
+    /// Apply the remaining steps of a rebase, `resume` first commits the step that stopped
+    fn run_rebase(&self, rebase: &mut Rebase, branch: &str, resume: bool) -> Result<(), GitError> {
+        let signature = self.inner.signature()?;
+        if resume {
+            commit_rebase_step(rebase, &signature)?;
+        }
+
+        while let Some(operation) = rebase.next() {
+            operation?;
+
+            let files = conflicting_files(&self.inner.index()?)?;
+            if !files.is_empty() {
+                return Err(GitError::RebaseConflict(branch.to_string(), files));
+            }
+            commit_rebase_step(rebase, &signature)?;
+        }
+
+        rebase.finish(Some(&signature))?;
+        Ok(())
+    }
+
+    /// Commit the merge in progress with the message saved in `MERGE_MSG`
+    fn commit_merge(&self, index: &mut Index) -> Result<(), GitError> {
+        let mut ids = Vec::new();
+        self.reopen()?.mergehead_foreach(|id| {
+            ids.push(*id);
+            true
+        })?;
+
+        let mut parents = vec![self.inner.head()?.peel_to_commit()?];
+        for id in ids {
+            parents.push(self.inner.find_commit(id)?);
+        }
+        let parents: Vec<_> = parents.iter().collect();
 
-        Ok(Self { inner })
+        let tree = self.inner.find_tree(index.write_tree()?)?;
+        let signature = self.inner.signature()?;
+        let message = self.inner.message()?;
+        self.inner.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            &message,
+            &tree,
+            &parents,
+        )?;
+
+        self.inner.cleanup_state()?;
+        Ok(())
     }
 }
 
 impl GitRepository for LocalGitRepository {
+    fn with_remote(self, remote: &str) -> Self {
+        Self {
+            remote: remote.to_string(),
+            ..self
+        }
+    }
+
+    fn remote(&self) -> &str {
+        &self.remote
+    }
+
     fn create_and_checkout_branch(
         &self,
         new_branch: &str,
@@ -63,7 +442,8 @@ impl GitRepository for LocalGitRepository {
     ) -> Result<(), GitError> {
         let target_branch = self
             .inner
-            .find_branch(from_branch, git2::BranchType::Local)
+            .find_branch(from_branch, BranchType::Local)
+            .or_else(|_| self.inner.find_branch(from_branch, BranchType::Remote))
             .map_err(|_| GitError::BranchNotFound(from_branch.to_string()))?;
 
         let commit = target_branch
@@ -71,68 +451,211 @@ impl GitRepository for LocalGitRepository {
             .peel_to_commit()
             .map_err(|_| GitError::CommitNotFound(from_branch.to_string()))?;
 
+        if self
+            .inner
+            .find_branch(new_branch, BranchType::Local)
+            .is_ok()
+        {
+            return Err(GitError::BranchExists(new_branch.to_string()));
+        }
         let mut branch = self
             .inner
             .branch(new_branch, &commit, false)
             .map_err(|_| GitError::CannotCreateBranch(new_branch.to_string()))?;
 
-        branch
-            .set_upstream(Some(new_branch))
-            .map_err(|_| GitError::CannotCreateBranch(new_branch.to_string()))?;
+        let result = self.checkout_new_branch(&branch, new_branch);
+        if result.is_err() {
+            // Do not leave a branch behind that was never checked out
+            let _ = branch.delete();
+        }
+        result
+    }
 
-        let tree = branch
+    fn checkout_branch(&self, branch: &str) -> Result<(), GitError> {
+        let local = self
+            .inner
+            .find_branch(branch, BranchType::Local)
+            .map_err(|_| GitError::BranchNotFound(branch.to_string()))?;
+        self.checkout_new_branch(&local, branch)
+    }
+
+    fn add_worktree(
+        &self,
+        path: &Path,
+        new_branch: &str,
+        from_branch: &str,
+    ) -> Result<(), GitError> {
+        let commit = self
+            .inner
+            .find_branch(from_branch, BranchType::Local)
+            .or_else(|_| self.inner.find_branch(from_branch, BranchType::Remote))
+            .map_err(|_| GitError::BranchNotFound(from_branch.to_string()))?
             .get()
-            .peel(git2::ObjectType::Tree)
-            .map_err(|e| GitError::CannotCheckoutBranch(new_branch.to_string(), e))?;
+            .peel_to_commit()
+            .map_err(|_| GitError::CommitNotFound(from_branch.to_string()))?;
 
-        self.inner
-            .checkout_tree(&tree, None)
-            .map_err(|e| GitError::CannotCheckoutBranch(new_branch.to_string(), e))?;
+        if self
+            .inner
+            .find_branch(new_branch, BranchType::Local)
+            .is_ok()
+        {
+            return Err(GitError::BranchExists(new_branch.to_string()));
+        }
+        let mut branch = self
+            .inner
+            .branch(new_branch, &commit, false)
+            .map_err(|_| GitError::CannotCreateBranch(new_branch.to_string()))?;
+
+        let mut options = WorktreeAddOptions::new();
+        options.reference(Some(branch.get()));
+        let result = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .map_err(|e| GitError::CommandFailed("worktree add".to_string(), e.to_string()))
+            .and_then(|_| {
+                // Worktree names cannot contain slashes, unlike branch names
+                let name = new_branch.replace('/', "-");
+                Ok(self.inner.worktree(&name, path, Some(&options))?)
+            });
+
+        if result.is_err() {
+            // Do not leave a branch behind that was never checked out
+            let _ = branch.delete();
+        }
+        result.map(|_| ())
+    }
+
+    fn worktrees(&self) -> Result<Vec<Worktree>, GitError> {
+        let mut result = Vec::new();
 
-        let ref_name = match branch.get().name() {
-            Some(name) => name,
-            None => {
-                return Err(GitError::BranchNotFound(new_branch.to_string()));
+        for name in self.inner.worktrees()?.iter().flatten() {
+            let worktree = self.inner.find_worktree(name)?;
+            // Skip worktrees whose directory was deleted by hand
+            if worktree.validate().is_err() {
+                continue;
             }
+
+            let branch = Repository::open_from_worktree(&worktree)
+                .ok()
+                .and_then(|repo| {
+                    let head = repo.head().ok()?;
+                    match head.is_branch() {
+                        true => head.shorthand().map(String::from),
+                        false => None,
+                    }
+                });
+
+            result.push(Worktree {
+                path: worktree.path().to_path_buf(),
+                branch,
+            });
+        }
+
+        Ok(result)
+    }
+
+    fn main_worktree(&self) -> Result<Self, GitError> {
+        // The git directory of a linked worktree points to the one of the main checkout
+        let git_dir = self.inner.path();
+        let common_dir = match std::fs::read_to_string(git_dir.join("commondir")) {
+            Ok(common_dir) => git_dir.join(common_dir.trim()),
+            Err(_) => git_dir.to_path_buf(),
         };
+        let inner = Repository::open(common_dir).map_err(|_| GitError::CannotOpenRepository)?;
+        Ok(Self {
+            inner,
+            remote: self.remote.clone(),
+            credentials: self.credentials.clone(),
+        })
+    }
 
-        self.inner
-            .set_head(ref_name)
-            .map_err(|e| GitError::CannotCheckoutBranch(new_branch.to_string(), e))?;
+    fn remove_worktree(&self, path: &Path) -> Result<(), GitError> {
+        let worktree = self.find_worktree(path)?;
+
+        let mut options = StatusOptions::new();
+        options.include_untracked(true).include_ignored(false);
+        let repo = Repository::open_from_worktree(&worktree)?;
+        if !repo.statuses(Some(&mut options))?.is_empty() {
+            return Err(GitError::DirtyWorktree(path.display().to_string()));
+        }
 
+        worktree.prune(Some(
+            WorktreePruneOptions::new().valid(true).working_tree(true),
+        ))?;
         Ok(())
     }
 
-    fn push(&self) -> Result<(), GitError> {
+    fn push(&self, force_with_lease: bool) -> Result<(), GitError> {
         let reference = self.inner.head().map_err(|_| GitError::CannotGetHead)?;
         if !reference.is_branch() {
             return Err(GitError::NotInABranch);
         }
 
-        let mut remote = self
+        let mut remote = self.find_remote()?;
+
+        let (ref_name, branch_name) = match (reference.name(), reference.shorthand()) {
+            (Some(ref_name), Some(branch_name)) => (ref_name, branch_name),
+            _ => return Err(GitError::NotInABranch),
+        };
+
+        // Where the remote branch was at the last fetch, a forced push may only replace that
+        let remote_ref = format!("refs/remotes/{}", self.remote_branch(branch_name));
+        let expected = self
             .inner
-            .find_remote("origin")
-            .map_err(|_| GitError::OriginNotFound)?;
+            .refname_to_id(&remote_ref)
+            .unwrap_or_else(|_| Oid::zero());
 
-        let ref_name = match reference.name() {
-            Some(name) => name,
-            None => {
-                return Err(GitError::OriginNotFound);
+        let refspec = match force_with_lease {
+            true => format!("+{}:{}", ref_name, ref_name),
+            false => format!("{}:{}", ref_name, ref_name),
+        };
+
+        let statuses = RefCell::new(Vec::new());
+        let stale = Cell::new(false);
+        let mut chain = self.credential_chain();
+        let result = {
+            let mut callbacks = chain.callbacks();
+            callbacks.push_transfer_progress(print_push_progress);
+            callbacks.sideband_progress(|data| {
+                eprint!("remote: {}", String::from_utf8_lossy(data));
+                true
+            });
+            callbacks.push_update_reference(|reference, status| {
+                statuses
+                    .borrow_mut()
+                    .push((reference.to_string(), status.map(String::from)));
+                Ok(())
+            });
+            if force_with_lease {
+                callbacks.push_negotiation(|updates| {
+                    if updates.iter().any(|update| update.src() != expected) {
+                        stale.set(true);
+                        return Err(Error::from_str("stale info"));
+                    }
+                    Ok(())
+                });
             }
+
+            let mut push_options = PushOptions::new();
+            push_options.remote_callbacks(callbacks);
+            remote.push(&[refspec], Some(&mut push_options))
         };
 
-        let mut callbacks = RemoteCallbacks::new();
-        callbacks.credentials(|_url, username_from_url, _allowed_types| {
-            Cred::ssh_key_from_agent(username_from_url.unwrap())
-        });
+        result.map_err(|e| {
+            chain.failure(&e).unwrap_or_else(|| match e.code() {
+                _ if stale.get() => GitError::StaleLease(branch_name.to_string()),
+                ErrorCode::NotFastForward => GitError::NonFastForward(branch_name.to_string()),
+                _ => GitError::CannotPush(e),
+            })
+        })?;
 
-        let mut push_options = PushOptions::new();
-        push_options.remote_callbacks(callbacks);
+        report_push_statuses(statuses.into_inner())?;
 
-        let refspec = format!("{}:{}", ref_name, ref_name);
-        remote
-            .push(&[refspec], Some(&mut push_options))
-            .map_err(GitError::CannotPushToOrigin)
+        // Same tracking configuration as `git push -u`
+        let mut config = self.inner.config()?;
+        config.set_str(&format!("branch.{}.remote", branch_name), &self.remote)?;
+        config.set_str(&format!("branch.{}.merge", branch_name), ref_name)?;
+        Ok(())
     }
 
     fn workdir(&self) -> Option<&Path> {
@@ -145,7 +668,7 @@ impl GitRepository for LocalGitRepository {
         let branches = self
             .inner
             .branches(Some(BranchType::Local))
-            .map_err(GitError::CannotPushToOrigin)?;
+            .map_err(GitError::CannotListBranches)?;
 
         for branch in branches {
             if let Some(name) = branch?.0.name()? {
@@ -155,30 +678,1698 @@ impl GitRepository for LocalGitRepository {
 
         Ok(result)
     }
-}
 
-pub fn to_branch_name(str: &str) -> String {
-    str.chars()
-        .map(|c: char| -> char {
-            match c.to_ascii_lowercase() {
-                m if m.is_ascii_lowercase() || m.is_ascii_digit() => m,
-                _ => '-',
-            }
-        })
-        .fold(String::default(), |acc, c| -> String {
-            if c == '-' && acc.ends_with('-') {
-                acc
+    fn current_branch(&self) -> Result<String, GitError> {
+        let reference = self.inner.head().map_err(|_| GitError::CannotGetHead)?;
+        if !reference.is_branch() {
+            return Err(GitError::NotInABranch);
+        }
+
+        reference
+            .shorthand()
+            .map(String::from)
+            .ok_or(GitError::NotInABranch)
+    }
+
+    fn hooks_dir(&self) -> Result<PathBuf, GitError> {
+        let workdir = self.inner.workdir().ok_or(GitError::CannotOpenRepository)?;
+        if let Ok(hooks_path) = self.inner.config()?.get_path("core.hooksPath") {
+            return Ok(workdir.join(hooks_path));
+        }
+
+        // Linked worktrees share the hooks of the main repository
+        let git_dir = self.inner.path();
+        let common_dir = match fs::read_to_string(git_dir.join("commondir")) {
+            Ok(common_dir) => git_dir.join(common_dir.trim()),
+            Err(_) => git_dir.to_path_buf(),
+        };
+        Ok(common_dir.join("hooks"))
+    }
+
+    fn commits(&self, range: &str) -> Result<Vec<CommitInfo>, GitError> {
+        let not_found = |_| GitError::CommitNotFound(range.to_string());
+        let mut revwalk = self.inner.revwalk()?;
+        revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+        let spec = self.inner.revparse(range).map_err(not_found)?;
+        let commit_id = |object: Option<&git2::Object>| match object {
+            Some(object) => object.peel_to_commit().map(|c| c.id()).map_err(not_found),
+            None => Err(GitError::CommitNotFound(range.to_string())),
+        };
+        if spec.mode().contains(RevparseMode::SINGLE) {
+            revwalk.push(commit_id(spec.from())?)?;
+        } else {
+            let (from, to) = (commit_id(spec.from())?, commit_id(spec.to())?);
+            revwalk.push(to)?;
+            // `A...B` has the commits of both sides since they diverged
+            if spec.mode().contains(RevparseMode::MERGE_BASE) {
+                revwalk.push(from)?;
+                revwalk.hide(self.inner.merge_base(from, to)?)?;
             } else {
-                format!("{}{}", acc, c)
+                revwalk.hide(from)?;
+            }
+        }
+
+        let mut commits = Vec::new();
+        for oid in revwalk {
+            let commit = self.inner.find_commit(oid?)?;
+            if commit.parent_count() > 1 {
+                continue;
+            }
+            commits.push(CommitInfo {
+                id: commit.id().to_string(),
+                message: String::from_utf8_lossy(commit.message_bytes())
+                    .trim_end()
+                    .to_string(),
+            });
+        }
+        Ok(commits)
+    }
+
+    fn last_tag(&self, revision: &str, prefix: &str) -> Result<Option<String>, GitError> {
+        let commit = self
+            .inner
+            .revparse_single(revision)
+            .and_then(|object| object.peel_to_commit())
+            .map_err(|_| GitError::CommitNotFound(revision.to_string()))?;
+
+        // Describe matches a glob, other tags sharing the prefix like `v-old` are left out here
+        let mut release_tags: HashMap<Oid, Vec<String>> = HashMap::new();
+        let names = self.inner.tag_names(Some(&format!("{}*", prefix)))?;
+        for name in names.iter().flatten() {
+            if release::is_release_tag(name, prefix) {
+                let tagged = self
+                    .inner
+                    .revparse_single(&format!("refs/tags/{}", name))?
+                    .peel_to_commit()?;
+                release_tags
+                    .entry(tagged.id())
+                    .or_default()
+                    .push(name.to_string());
             }
+        }
+
+        let mut revwalk = self.inner.revwalk()?;
+        revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+        revwalk.push(commit.id())?;
+        for oid in revwalk {
+            if let Some(tags) = release_tags.get(&oid?) {
+                return Ok(tags
+                    .iter()
+                    .max_by_key(|tag| Version::parse(tag, prefix).ok())
+                    .cloned());
+            }
+        }
+        Ok(None)
+    }
+
+    fn commit_files(&self, files: &[&Path], message: &str) -> Result<(), GitError> {
+        let mut index = self.inner.index()?;
+        index.read(false)?;
+        for file in files {
+            index.add_path(file)?;
+        }
+        index.write()?;
+
+        let tree = self.inner.find_tree(index.write_tree()?)?;
+        let signature = self.inner.signature()?;
+        let parent = self.inner.head()?.peel_to_commit()?;
+        self.inner.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &[&parent],
+        )?;
+        Ok(())
+    }
+
+    fn create_tag(&self, tag: &str, message: &str) -> Result<(), GitError> {
+        if self
+            .inner
+            .find_reference(&format!("refs/tags/{}", tag))
+            .is_ok()
+        {
+            return Err(GitError::TagExists(tag.to_string()));
+        }
+
+        let head = self.inner.head()?.peel_to_commit()?;
+        let signature = self.inner.signature()?;
+        self.inner
+            .tag(tag, head.as_object(), &signature, message, false)?;
+        Ok(())
+    }
+
+    fn push_refs(&self, refs: &[String]) -> Result<(), GitError> {
+        let mut remote = self.find_remote()?;
+        let refspecs: Vec<String> = refs
+            .iter()
+            .map(|reference| format!("{}:{}", reference, reference))
+            .collect();
+
+        let statuses = RefCell::new(Vec::new());
+        let mut chain = self.credential_chain();
+        let result = {
+            let mut callbacks = chain.callbacks();
+            callbacks.push_transfer_progress(print_push_progress);
+            callbacks.push_update_reference(|reference, status| {
+                statuses
+                    .borrow_mut()
+                    .push((reference.to_string(), status.map(String::from)));
+                Ok(())
+            });
+
+            let mut push_options = PushOptions::new();
+            push_options.remote_callbacks(callbacks);
+            remote.push(&refspecs, Some(&mut push_options))
+        };
+
+        result.map_err(|e| {
+            chain.failure(&e).unwrap_or_else(|| match e.code() {
+                ErrorCode::NotFastForward => {
+                    let names: Vec<&str> = refs
+                        .iter()
+                        .map(|reference| reference.trim_start_matches("refs/heads/"))
+                        .collect();
+                    GitError::NonFastForward(names.join(", "))
+                }
+                _ => GitError::CannotPush(e),
+            })
+        })?;
+        report_push_statuses(statuses.into_inner())
+    }
+
+    fn remote_url(&self) -> Result<String, GitError> {
+        let remote = self.find_remote()?;
+
+        remote
+            .url()
+            .map(String::from)
+            .ok_or_else(|| GitError::RemoteNotFound(self.remote.clone()))
+    }
+
+    fn needs_push(&self) -> Result<bool, GitError> {
+        let branch_name = self.current_branch()?;
+        let local = self
+            .inner
+            .head()
+            .map_err(|_| GitError::CannotGetHead)?
+            .target()
+            .ok_or(GitError::CannotGetHead)?;
+
+        let remote_ref = format!("refs/remotes/{}", self.remote_branch(&branch_name));
+        match self.inner.find_reference(&remote_ref) {
+            Ok(reference) => Ok(reference.target() != Some(local)),
+            Err(_) => Ok(true),
+        }
+    }
+
+    fn ahead_behind(&self, branch: &str, other: &str) -> Result<Option<(usize, usize)>, GitError> {
+        let find_target = |name: &str| {
+            self.inner
+                .find_branch(name, BranchType::Local)
+                .or_else(|_| self.inner.find_branch(name, BranchType::Remote))
+                .ok()
+                .and_then(|b| b.get().target())
+        };
+
+        match (find_target(branch), find_target(other)) {
+            (Some(local), Some(upstream)) => {
+                Ok(Some(self.inner.graph_ahead_behind(local, upstream)?))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn is_clean(&self) -> Result<bool, GitError> {
+        let mut options = StatusOptions::new();
+        options.include_untracked(false).include_ignored(false);
+
+        Ok(self.inner.statuses(Some(&mut options))?.is_empty())
+    }
+
+    fn stash(&self, message: &str) -> Result<(), GitError> {
+        let mut repo = self.reopen()?;
+        let signature = repo.signature()?;
+        repo.stash_save(&signature, message, None)?;
+        Ok(())
+    }
+
+    fn stash_pop(&self) -> Result<(), GitError> {
+        let mut repo = self.reopen()?;
+        let mut checkout = CheckoutBuilder::new();
+        checkout.safe();
+        let mut options = StashApplyOptions::new();
+        options.checkout_options(checkout);
+
+        let cannot_restore = |reason: String| {
+            GitError::CannotRestoreStash(self.current_branch().unwrap_or_default(), reason)
+        };
+
+        repo.stash_apply(0, Some(&mut options))
+            .map_err(|e| cannot_restore(e.message().to_string()))?;
+
+        // libgit2 writes the conflicts without failing, keep the stash like git does
+        let conflicts = conflicting_files(&repo.index()?)?;
+        if !conflicts.is_empty() {
+            return Err(cannot_restore(format!(
+                "conflicts in {}",
+                conflicts.join(", ")
+            )));
+        }
+
+        repo.stash_drop(0)?;
+        Ok(())
+    }
+
+    fn fetch(&self, branch: &str) -> Result<(), GitError> {
+        let mut remote = self.find_remote()?;
+
+        let refspec = format!(
+            "refs/heads/{}:refs/remotes/{}/{}",
+            branch, self.remote, branch
+        );
+        let mut chain = self.credential_chain();
+        let result = {
+            let mut fetch_options = FetchOptions::new();
+            fetch_options.remote_callbacks(chain.callbacks());
+            remote.fetch(&[refspec], Some(&mut fetch_options), None)
+        };
+
+        result.map_err(|e| {
+            chain.failure(&e).unwrap_or_else(|| {
+                GitError::CommandFailed(format!("fetch {} {}", self.remote, branch), e.to_string())
+            })
         })
-        .trim_matches('-')
-        .to_string()
-}
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    fn fast_forward(&self, branch: &str) -> Result<(), GitError> {
+        let mut local = self
+            .inner
+            .find_branch(branch, BranchType::Local)
+            .map_err(|_| GitError::BranchNotFound(branch.to_string()))?;
+        let remote_branch = self.remote_branch(branch);
+        let remote = self
+            .inner
+            .find_branch(&remote_branch, BranchType::Remote)
+            .map_err(|_| GitError::BranchNotFound(remote_branch.clone()))?;
+
+        let local_id = local.get().target().ok_or(GitError::CannotGetHead)?;
+        let remote_id = remote.get().target().ok_or(GitError::CannotGetHead)?;
+
+        if local_id == remote_id || self.inner.graph_descendant_of(local_id, remote_id)? {
+            return Ok(());
+        }
+        if !self.inner.graph_descendant_of(remote_id, local_id)? {
+            return Err(GitError::Diverged(branch.to_string(), remote_branch));
+        }
+
+        if local.is_head() {
+            let commit = self.inner.find_commit(remote_id)?;
+            self.inner
+                .checkout_tree(commit.as_object(), Some(CheckoutBuilder::new().safe()))
+                .map_err(|e| GitError::CannotCheckoutBranch(branch.to_string(), e))?;
+        }
+
+        local.get_mut().set_target(
+            remote_id,
+            &format!("fast-forward {} to {}", branch, remote_branch),
+        )?;
+        Ok(())
+    }
+
+    fn merge_into(
+        &self,
+        branch: &str,
+        target: &str,
+        strategy: MergeStrategy,
+        message: &str,
+    ) -> Result<(), GitError> {
+        let find_commit = |name: &str| {
+            self.inner
+                .find_branch(name, BranchType::Local)
+                .and_then(|b| b.get().peel_to_commit())
+                .map_err(|_| GitError::BranchNotFound(name.to_string()))
+        };
+        let theirs = find_commit(branch)?;
+        let ours = find_commit(target)?;
+        let target_ref = format!("refs/heads/{}", target);
+
+        // Like git, only check out the target when it already has the changes of the branch
+        if ours.id() == theirs.id() || self.inner.graph_descendant_of(ours.id(), theirs.id())? {
+            self.inner
+                .checkout_tree(ours.as_object(), Some(CheckoutBuilder::new().safe()))
+                .map_err(|e| GitError::CannotCheckoutBranch(target.to_string(), e))?;
+            return self
+                .inner
+                .set_head(&target_ref)
+                .map_err(|e| GitError::CannotCheckoutBranch(target.to_string(), e));
+        }
+
+        // Merge in memory so a conflict leaves the working tree untouched
+        let mut index = self.inner.merge_commits(&ours, &theirs, None)?;
+        let files = conflicting_files(&index)?;
+        if !files.is_empty() {
+            // Like the git backend, leave `branch` checked out whatever was checked out before,
+            // unless its own worktree has it
+            let in_worktree = self
+                .worktrees()?
+                .iter()
+                .any(|w| w.branch.as_deref() == Some(branch));
+            if !in_worktree && self.current_branch().ok().as_deref() != Some(branch) {
+                let local = self
+                    .inner
+                    .find_branch(branch, BranchType::Local)
+                    .map_err(|_| GitError::BranchNotFound(branch.to_string()))?;
+                self.checkout_new_branch(&local, branch)?;
+            }
+            return Err(GitError::MergeConflict(
+                branch.to_string(),
+                target.to_string(),
+                files,
+            ));
+        }
+
+        let tree = self.inner.find_tree(index.write_tree_to(&self.inner)?)?;
+        let signature = self.inner.signature()?;
+        let parents = match strategy {
+            MergeStrategy::Merge => vec![&ours, &theirs],
+            MergeStrategy::Squash => vec![&ours],
+        };
+
+        let oid = self
+            .inner
+            .commit(None, &signature, &signature, message, &tree, &parents)?;
+
+        // Only move the target once the merge is checked out, a failed checkout changes nothing
+        self.inner
+            .checkout_tree(tree.as_object(), Some(CheckoutBuilder::new().safe()))
+            .map_err(|e| GitError::CannotCheckoutBranch(target.to_string(), e))?;
+        self.inner
+            .find_reference(&target_ref)?
+            .set_target(oid, &format!("merge {}: {}", branch, message))?;
+        self.inner
+            .set_head(&target_ref)
+            .map_err(|e| GitError::CannotCheckoutBranch(target.to_string(), e))?;
+
+        Ok(())
+    }
+
+    fn cherry_pick(&self, commits: &[String]) -> Result<(), GitError> {
+        let branch = self.current_branch()?;
+        let signature = self.inner.signature()?;
+        let mut parent = self.inner.head()?.peel_to_commit()?;
+
+        // Pick in memory so a conflict leaves the branch and the working tree untouched
+        for id in commits {
+            let commit = self
+                .inner
+                .revparse_single(id)
+                .and_then(|object| object.peel_to_commit())
+                .map_err(|_| GitError::CommitNotFound(id.to_string()))?;
+            let mut index = self.inner.cherrypick_commit(&commit, &parent, 0, None)?;
+            let files = conflicting_files(&index)?;
+            if !files.is_empty() {
+                return Err(GitError::CherryPickConflict(
+                    commit.id().to_string(),
+                    branch,
+                    files,
+                ));
+            }
+
+            let tree = self.inner.find_tree(index.write_tree_to(&self.inner)?)?;
+            // The branch already has the changes, e.g. picked before
+            if tree.id() == parent.tree_id() {
+                continue;
+            }
+            let message = format!(
+                "{}\n\n(cherry picked from commit {})\n",
+                String::from_utf8_lossy(commit.message_bytes()).trim_end(),
+                commit.id()
+            );
+            let oid = self.inner.commit(
+                None,
+                &commit.author(),
+                &signature,
+                &message,
+                &tree,
+                &[&parent],
+            )?;
+            parent = self.inner.find_commit(oid)?;
+        }
+
+        self.inner
+            .checkout_tree(parent.as_object(), Some(CheckoutBuilder::new().safe()))
+            .map_err(|e| GitError::CannotCheckoutBranch(branch.clone(), e))?;
+        self.inner
+            .head()?
+            .set_target(parent.id(), &format!("cherry-pick onto {}", branch))?;
+        Ok(())
+    }
+
+    fn rebase(&self, onto: &str) -> Result<(), GitError> {
+        let branch = self.current_branch()?;
+        let upstream = self.annotated_branch(onto)?;
+
+        let head = self.inner.head()?.target().ok_or(GitError::CannotGetHead)?;
+        if head == upstream.id() || self.inner.graph_descendant_of(head, upstream.id())? {
+            return Ok(());
+        }
+
+        // The index is cached, git may have changed it since it was loaded
+        self.inner.index()?.read(false)?;
+        let mut rebase = self.inner.rebase(None, Some(&upstream), None, None)?;
+        self.run_rebase(&mut rebase, &branch, false)
+    }
+
+    fn merge(&self, branch: &str, message: &str) -> Result<(), GitError> {
+        let current = self.current_branch()?;
+        let theirs = self.annotated_branch(branch)?;
+
+        let (analysis, _) = self.inner.merge_analysis(&[&theirs])?;
+        if analysis.is_up_to_date() {
+            return Ok(());
+        }
+        if analysis.is_fast_forward() {
+            let commit = self.inner.find_commit(theirs.id())?;
+            self.inner
+                .checkout_tree(commit.as_object(), Some(CheckoutBuilder::new().safe()))
+                .map_err(|e| GitError::CannotCheckoutBranch(current.clone(), e))?;
+            self.inner
+                .head()?
+                .set_target(theirs.id(), &format!("merge {}: fast-forward", branch))?;
+            return Ok(());
+        }
+
+        // Leaves MERGE_HEAD and the conflicts behind like git, so the merge can be resumed
+        self.inner.merge(&[&theirs], None, None)?;
+        fs::write(self.inner.path().join("MERGE_MSG"), message)
+            .map_err(|e| GitError::CommandFailed("merge".to_string(), e.to_string()))?;
+
+        let mut index = self.inner.index()?;
+        let files = conflicting_files(&index)?;
+        if !files.is_empty() {
+            return Err(GitError::MergeConflict(branch.to_string(), current, files));
+        }
+        self.commit_merge(&mut index)
+    }
+
+    fn operation_in_progress(&self) -> Result<Option<Operation>, GitError> {
+        Ok(match self.inner.state() {
+            RepositoryState::Merge => Some(Operation::Merge),
+            RepositoryState::Rebase
+            | RepositoryState::RebaseInteractive
+            | RepositoryState::RebaseMerge => Some(Operation::Rebase),
+            _ => None,
+        })
+    }
+
+    fn continue_operation(&self) -> Result<(), GitError> {
+        let operation = self
+            .operation_in_progress()?
+            .ok_or(GitError::NothingInProgress)?;
+
+        // The conflicts were resolved by other processes, reload the index from the disk
+        let mut index = self.inner.index()?;
+        index.read(false)?;
+        let files = conflicting_files(&index)?;
+        if !files.is_empty() {
+            return Err(GitError::UnresolvedConflicts(files));
+        }
+
+        match operation {
+            Operation::Rebase => {
+                let mut rebase = self.inner.open_rebase(None)?;
+                let branch = rebase
+                    .orig_head_name()
+                    .map(|name| name.trim_start_matches("refs/heads/").to_string())
+                    .unwrap_or_default();
+                self.run_rebase(&mut rebase, &branch, true)
+            }
+            Operation::Merge => self.commit_merge(&mut index),
+        }
+    }
+
+    fn abort_operation(&self) -> Result<(), GitError> {
+        match self.operation_in_progress()? {
+            Some(Operation::Rebase) => self.inner.open_rebase(None)?.abort()?,
+            Some(Operation::Merge) => {
+                let head = self.inner.head()?.peel_to_commit()?;
+                self.inner.reset(head.as_object(), ResetType::Hard, None)?;
+                self.inner.cleanup_state()?;
+            }
+            None => return Err(GitError::NothingInProgress),
+        }
+        Ok(())
+    }
+
+    fn delete_branch(&self, branch: &str) -> Result<(), GitError> {
+        self.inner
+            .find_branch(branch, BranchType::Local)
+            .map_err(|_| GitError::BranchNotFound(branch.to_string()))?
+            .delete()?;
+        Ok(())
+    }
+
+    fn delete_remote_branch(&self, branch: &str) -> Result<(), GitError> {
+        let mut remote_branch = match self
+            .inner
+            .find_branch(&self.remote_branch(branch), BranchType::Remote)
+        {
+            Ok(remote_branch) => remote_branch,
+            Err(_) => return Ok(()),
+        };
+
+        let mut remote = self.find_remote()?;
+
+        let mut chain = self.credential_chain();
+        let result = {
+            let mut push_options = PushOptions::new();
+            push_options.remote_callbacks(chain.callbacks());
+            remote.push(
+                &[format!(":refs/heads/{}", branch)],
+                Some(&mut push_options),
+            )
+        };
+        result.map_err(|e| chain.failure(&e).unwrap_or(GitError::CannotPush(e)))?;
+
+        // Usually already removed when the push updated the remote tracking branches
+        let _ = remote_branch.delete();
+        Ok(())
+    }
+}
+
+impl Display for Operation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Operation::Rebase => write!(f, "rebase"),
+            Operation::Merge => write!(f, "merge"),
+        }
+    }
+}
+
+fn print_push_progress(current: usize, total: usize, bytes: usize) {
+    if total == 0 {
+        return;
+    }
+    eprint!("\rWriting objects: {}/{}, {} bytes", current, total, bytes);
+    if current == total {
+        eprintln!();
+    }
+}
+
+/// Print the status of each pushed reference, the first rejection is returned
+fn report_push_statuses(statuses: Vec<(String, Option<String>)>) -> Result<(), GitError> {
+    for (reference, status) in statuses {
+        match status {
+            Some(reason) => {
+                eprintln!("{}: rejected ({})", reference, reason);
+                return Err(push_rejection(&reference, &reason));
+            }
+            None => eprintln!("{}: pushed", reference),
+        }
+    }
+    Ok(())
+}
+
+/// Error for a reference the remote refused to update, from the reason it gave
+pub(crate) fn push_rejection(reference: &str, reason: &str) -> GitError {
+    let branch = reference
+        .strip_prefix("refs/heads/")
+        .unwrap_or(reference)
+        .to_string();
+
+    if reason.contains("non-fast-forward") || reason.contains("fetch first") {
+        GitError::NonFastForward(branch)
+    } else if reason.contains("stale info") {
+        GitError::StaleLease(branch)
+    } else if reason.contains("protected") {
+        GitError::ProtectedBranch(branch, reason.to_string())
+    } else {
+        GitError::PushRejected(branch, reason.to_string())
+    }
+}
+
+/// Compare paths of existing files, ignoring symbolic links and trailing separators
+pub(crate) fn same_path(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a.components().eq(b.components()),
+    }
+}
+
+/// Commit the current step of a rebase with the author and message of the original commit
+fn commit_rebase_step(rebase: &mut Rebase, signature: &Signature) -> Result<(), GitError> {
+    match rebase.commit(None, signature, None) {
+        // The changes of the commit are already in the new base
+        Err(e) if e.code() == ErrorCode::Applied => Ok(()),
+        result => result.map(|_| ()).map_err(GitError::from),
+    }
+}
+
+/// Paths of the conflicting entries of an index
+fn conflicting_files(index: &Index) -> Result<Vec<String>, GitError> {
+    if !index.has_conflicts() {
+        return Ok(Vec::new());
+    }
+
+    let mut files: Vec<String> = index
+        .conflicts()?
+        .filter_map(|c| c.ok())
+        .filter_map(|c| c.our.or(c.their).or(c.ancestor))
+        .map(|entry| String::from_utf8_lossy(&entry.path).to_string())
+        .collect();
+    files.dedup();
+    Ok(files)
+}
+
+pub fn to_branch_name(str: &str) -> String {
+    str.chars()
+        .map(|c: char| -> char {
+            match c.to_ascii_lowercase() {
+                m if m.is_ascii_lowercase() || m.is_ascii_digit() => m,
+                _ => '-',
+            }
+        })
+        .fold(String::default(), |acc, c| -> String {
+            if c == '-' && acc.ends_with('-') {
+                acc
+            } else {
+                format!("{}{}", acc, c)
+            }
+        })
+        .trim_matches('-')
+        .to_string()
+}
+
+/// Check a branch name against git ref-name rules (see `git check-ref-format --branch`)
+pub fn validate_branch_name(name: &str) -> Result<(), GitError> {
+    let invalid = |reason: &str| {
+        Err(GitError::InvalidBranchName(
+            name.to_string(),
+            reason.to_string(),
+        ))
+    };
+
+    if name.is_empty() || name == "@" {
+        return invalid("name cannot be empty or @");
+    }
+    if name.starts_with('-') {
+        return invalid("name cannot start with a dash");
+    }
+    if name.starts_with('/') || name.ends_with('/') || name.contains("//") {
+        return invalid("name cannot start or end with a slash or contain empty components");
+    }
+    if name.ends_with('.') {
+        return invalid("name cannot end with a dot");
+    }
+    if name.contains("..") || name.contains("@{") {
+        return invalid("name cannot contain '..' or '@{'");
+    }
+    if let Some(c) = name
+        .chars()
+        .find(|c| c.is_ascii_control() || " ~^:?*[\\".contains(*c))
+    {
+        return invalid(&format!("name cannot contain {:?}", c));
+    }
+    if name
+        .split('/')
+        .any(|component| component.starts_with('.') || component.ends_with(".lock"))
+    {
+        return invalid("components cannot start with a dot or end with .lock");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestRepository;
+
+    #[test]
+    fn test_list_branches() {
+        let test_repo = TestRepository::new();
+        let repo = LocalGitRepository::open(&test_repo.workdir).unwrap();
+
+        let mut branches = repo.branches().unwrap();
+        branches.sort();
+
+        assert_eq!(branches, vec!["develop", "main"]);
+        assert_eq!(repo.current_branch().unwrap(), "main");
+    }
+
+    #[test]
+    fn test_create_and_checkout_branch() {
+        let test_repo = TestRepository::new();
+        let repo = LocalGitRepository::open(&test_repo.workdir).unwrap();
+
+        repo.create_and_checkout_branch("PROJ-1-feature", "develop")
+            .unwrap();
+
+        assert_eq!(test_repo.head_branch(), "PROJ-1-feature");
+        assert!(repo
+            .branches()
+            .unwrap()
+            .contains(&"PROJ-1-feature".to_string()));
+    }
+
+    #[test]
+    fn test_checkout_branch() {
+        let test_repo = TestRepository::new();
+        let repo = LocalGitRepository::open(&test_repo.workdir).unwrap();
+
+        repo.checkout_branch("develop").unwrap();
+
+        assert_eq!(test_repo.head_branch(), "develop");
+        assert!(matches!(
+            repo.checkout_branch("missing"),
+            Err(GitError::BranchNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_create_existing_branch_keeps_it() {
+        let test_repo = TestRepository::new();
+        let repo = LocalGitRepository::open(&test_repo.workdir).unwrap();
+        repo.create_and_checkout_branch("PROJ-1-feature", "develop")
+            .unwrap();
+        test_repo.commit("fix.txt", "Fix the login");
+        test_repo.checkout("main");
+
+        let result = repo.create_and_checkout_branch("PROJ-1-feature", "develop");
+
+        assert!(matches!(result, Err(GitError::BranchExists(_))));
+        assert_eq!(test_repo.head_branch(), "main");
+        assert_eq!(
+            test_repo.last_commit("PROJ-1-feature"),
+            ("Fix the login".to_string(), 1)
+        );
+    }
+
+    #[test]
+    fn test_create_branch_from_remote_branch() {
+        let test_repo = TestRepository::new();
+        test_repo.commit_on_origin("develop", "remote.txt", "Remote change");
+        let repo = LocalGitRepository::open(&test_repo.workdir).unwrap();
+        repo.fetch("develop").unwrap();
+
+        repo.create_and_checkout_branch("PROJ-1-feature", "origin/develop")
+            .unwrap();
+
+        assert_eq!(test_repo.head_branch(), "PROJ-1-feature");
+        assert_eq!(
+            test_repo.file_content("remote.txt").as_deref(),
+            Some("Remote change")
+        );
+        assert_eq!(test_repo.last_commit("develop").0, "Initial commit");
+    }
+
+    #[test]
+    fn test_create_branch_with_conflicting_changes() {
+        let test_repo = TestRepository::new();
+        test_repo.checkout("develop");
+        test_repo.commit("README.md", "Develop readme");
+        test_repo.checkout("main");
+        std::fs::write(test_repo.workdir.join("README.md"), "Local change").unwrap();
+        let repo = LocalGitRepository::open(&test_repo.workdir).unwrap();
+
+        let result = repo.create_and_checkout_branch("PROJ-1-feature", "develop");
+
+        assert!(result.is_err());
+        assert_eq!(test_repo.head_branch(), "main");
+        assert!(!repo
+            .branches()
+            .unwrap()
+            .contains(&"PROJ-1-feature".to_string()));
+        assert_eq!(
+            test_repo.file_content("README.md").as_deref(),
+            Some("Local change")
+        );
+    }
+
+    #[test]
+    fn test_stash_and_restore_on_new_branch() {
+        let test_repo = TestRepository::new();
+        std::fs::write(test_repo.workdir.join("README.md"), "Local change").unwrap();
+        let repo = LocalGitRepository::open(&test_repo.workdir).unwrap();
+
+        repo.stash("wf start").unwrap();
+        assert!(repo.is_clean().unwrap());
+        repo.create_and_checkout_branch("PROJ-1-feature", "develop")
+            .unwrap();
+        repo.stash_pop().unwrap();
+
+        assert!(!repo.is_clean().unwrap());
+        assert_eq!(
+            test_repo.file_content("README.md").as_deref(),
+            Some("Local change")
+        );
+        assert!(repo.stash_pop().is_err());
+    }
+
+    #[test]
+    fn test_stash_pop_conflict_keeps_stash() {
+        let test_repo = TestRepository::new();
+        std::fs::write(test_repo.workdir.join("README.md"), "Local change").unwrap();
+        let repo = LocalGitRepository::open(&test_repo.workdir).unwrap();
+        repo.stash("wf start").unwrap();
+        test_repo.commit("README.md", "Other change");
+
+        let result = repo.stash_pop();
+
+        assert!(matches!(result, Err(GitError::CannotRestoreStash(b, _)) if b == "main"));
+        let mut stashes = 0;
+        test_repo
+            .repository()
+            .stash_foreach(|_, _, _| {
+                stashes += 1;
+                true
+            })
+            .unwrap();
+        assert_eq!(stashes, 1);
+    }
+
+    #[test]
+    fn test_create_branch_from_missing_branch() {
+        let test_repo = TestRepository::new();
+        let repo = LocalGitRepository::open(&test_repo.workdir).unwrap();
+
+        let result = repo.create_and_checkout_branch("PROJ-1-feature", "missing");
+
+        assert!(matches!(result, Err(GitError::BranchNotFound(b)) if b == "missing"));
+        assert_eq!(test_repo.head_branch(), "main");
+    }
+
+    #[test]
+    fn test_push_rejection_reasons() {
+        assert!(matches!(
+            push_rejection("refs/heads/a", "non-fast-forward"),
+            GitError::NonFastForward(b) if b == "a"
+        ));
+        assert!(matches!(
+            push_rejection("refs/heads/a", "stale info"),
+            GitError::StaleLease(b) if b == "a"
+        ));
+        assert!(matches!(
+            push_rejection("refs/heads/main", "protected branch hook declined"),
+            GitError::ProtectedBranch(b, _) if b == "main"
+        ));
+        assert!(matches!(
+            push_rejection("refs/heads/a", "pre-receive hook declined"),
+            GitError::PushRejected(b, r) if b == "a" && r == "pre-receive hook declined"
+        ));
+    }
+
+    #[test]
+    fn test_push_new_branch() {
+        let test_repo = TestRepository::new();
+        let repo = LocalGitRepository::open(&test_repo.workdir).unwrap();
+        repo.create_and_checkout_branch("PROJ-1-feature", "develop")
+            .unwrap();
+        test_repo.commit("feature.txt", "Add feature");
+
+        assert!(repo.needs_push().unwrap());
+        repo.push(false).unwrap();
+
+        assert!(!repo.needs_push().unwrap());
+        assert_eq!(
+            test_repo.origin_branches(),
+            vec!["PROJ-1-feature", "develop", "main"]
+        );
+        assert_eq!(
+            test_repo.upstream("PROJ-1-feature").as_deref(),
+            Some("origin/PROJ-1-feature")
+        );
+    }
+
+    #[test]
+    fn test_push_to_configured_remote() {
+        let test_repo = TestRepository::new();
+        test_repo.rename_origin("upstream");
+        let repo = LocalGitRepository::open(&test_repo.workdir)
+            .unwrap()
+            .with_remote("upstream");
+        repo.create_and_checkout_branch("PROJ-1-feature", "develop")
+            .unwrap();
+        assert_eq!(test_repo.upstream("PROJ-1-feature"), None);
+
+        repo.push(false).unwrap();
+
+        assert!(!repo.needs_push().unwrap());
+        assert_eq!(
+            test_repo.upstream("PROJ-1-feature").as_deref(),
+            Some("upstream/PROJ-1-feature")
+        );
+        assert_eq!(
+            repo.ahead_behind("PROJ-1-feature", "upstream/develop")
+                .unwrap(),
+            Some((0, 0))
+        );
+    }
+
+    #[test]
+    fn test_push_to_missing_remote() {
+        let test_repo = TestRepository::new();
+        let repo = LocalGitRepository::open(&test_repo.workdir)
+            .unwrap()
+            .with_remote("missing");
+
+        let result = repo.push(false);
+
+        assert!(matches!(result, Err(GitError::RemoteNotFound(r)) if r == "missing"));
+    }
+
+    #[test]
+    fn test_push_rejected_when_remote_moved() {
+        let test_repo = TestRepository::new();
+        let repo = LocalGitRepository::open(&test_repo.workdir).unwrap();
+        repo.create_and_checkout_branch("PROJ-1-feature", "develop")
+            .unwrap();
+        repo.push(false).unwrap();
+        test_repo.commit_on_origin("PROJ-1-feature", "remote.txt", "Remote change");
+        test_repo.commit("feature.txt", "Add feature");
+
+        let result = repo.push(false);
+
+        assert!(matches!(result, Err(GitError::NonFastForward(b)) if b == "PROJ-1-feature"));
+        assert_eq!(test_repo.last_commit("PROJ-1-feature").0, "Add feature");
+    }
+
+    #[test]
+    fn test_force_with_lease_needs_fetch() {
+        let test_repo = TestRepository::new();
+        let repo = LocalGitRepository::open(&test_repo.workdir).unwrap();
+        repo.create_and_checkout_branch("PROJ-1-feature", "develop")
+            .unwrap();
+        repo.push(false).unwrap();
+        test_repo.commit_on_origin("PROJ-1-feature", "remote.txt", "Remote change");
+        test_repo.commit("feature.txt", "Add feature");
+
+        let result = repo.push(true);
+        assert!(matches!(result, Err(GitError::StaleLease(b)) if b == "PROJ-1-feature"));
+
+        repo.fetch("PROJ-1-feature").unwrap();
+        repo.push(true).unwrap();
+        assert!(!repo.needs_push().unwrap());
+        assert_eq!(
+            repo.ahead_behind("PROJ-1-feature", "origin/PROJ-1-feature")
+                .unwrap(),
+            Some((0, 0))
+        );
+    }
+
+    #[test]
+    fn test_rebase_onto_remote_base() {
+        let test_repo = TestRepository::new();
+        let repo = LocalGitRepository::open(&test_repo.workdir).unwrap();
+        repo.create_and_checkout_branch("PROJ-1-feature", "develop")
+            .unwrap();
+        test_repo.commit("feature.txt", "Add feature");
+        test_repo.commit_on_origin("develop", "remote.txt", "Remote change");
+        repo.fetch("develop").unwrap();
+
+        repo.rebase("origin/develop").unwrap();
+
+        assert_eq!(repo.operation_in_progress().unwrap(), None);
+        assert_eq!(test_repo.head_branch(), "PROJ-1-feature");
+        assert_eq!(
+            test_repo.last_commit("PROJ-1-feature"),
+            ("Add feature".to_string(), 1)
+        );
+        assert_eq!(
+            repo.ahead_behind("PROJ-1-feature", "origin/develop")
+                .unwrap(),
+            Some((1, 0))
+        );
+        assert_eq!(
+            test_repo.file_content("remote.txt").as_deref(),
+            Some("Remote change")
+        );
+    }
+
+    #[test]
+    fn test_rebase_conflict_then_continue() {
+        let test_repo = TestRepository::new();
+        let repo = LocalGitRepository::open(&test_repo.workdir).unwrap();
+        repo.create_and_checkout_branch("PROJ-1-feature", "develop")
+            .unwrap();
+        test_repo.commit("shared.txt", "Mine");
+        test_repo.commit("feature.txt", "Add feature");
+        test_repo.commit_on_origin("develop", "shared.txt", "Theirs");
+        repo.fetch("develop").unwrap();
+
+        let result = repo.rebase("origin/develop");
+
+        assert!(matches!(
+            result,
+            Err(GitError::RebaseConflict(b, files)) if b == "PROJ-1-feature" && files == ["shared.txt"]
+        ));
+        assert_eq!(
+            repo.operation_in_progress().unwrap(),
+            Some(Operation::Rebase)
+        );
+        assert!(test_repo
+            .file_content("shared.txt")
+            .unwrap()
+            .contains("<<<<<<<"));
+        assert!(matches!(
+            repo.continue_operation(),
+            Err(GitError::UnresolvedConflicts(files)) if files == ["shared.txt"]
+        ));
+
+        test_repo.resolve("shared.txt", "Both");
+        repo.continue_operation().unwrap();
+
+        assert_eq!(repo.operation_in_progress().unwrap(), None);
+        assert_eq!(test_repo.head_branch(), "PROJ-1-feature");
+        assert_eq!(test_repo.last_commit("PROJ-1-feature").0, "Add feature");
+        assert_eq!(
+            repo.ahead_behind("PROJ-1-feature", "origin/develop")
+                .unwrap(),
+            Some((2, 0))
+        );
+        assert_eq!(
+            test_repo.file_content("shared.txt").as_deref(),
+            Some("Both")
+        );
+    }
+
+    #[test]
+    fn test_rebase_abort() {
+        let test_repo = TestRepository::new();
+        let repo = LocalGitRepository::open(&test_repo.workdir).unwrap();
+        repo.create_and_checkout_branch("PROJ-1-feature", "develop")
+            .unwrap();
+        test_repo.commit("shared.txt", "Mine");
+        test_repo.commit_on_origin("develop", "shared.txt", "Theirs");
+        repo.fetch("develop").unwrap();
+        assert!(repo.rebase("origin/develop").is_err());
+
+        repo.abort_operation().unwrap();
+
+        assert_eq!(repo.operation_in_progress().unwrap(), None);
+        assert_eq!(test_repo.head_branch(), "PROJ-1-feature");
+        assert_eq!(test_repo.last_commit("PROJ-1-feature").0, "Mine");
+        assert_eq!(
+            test_repo.file_content("shared.txt").as_deref(),
+            Some("Mine")
+        );
+        assert!(repo.is_clean().unwrap());
+        assert!(matches!(
+            repo.abort_operation(),
+            Err(GitError::NothingInProgress)
+        ));
+    }
+
+    #[test]
+    fn test_merge_remote_base() {
+        let test_repo = TestRepository::new();
+        let repo = LocalGitRepository::open(&test_repo.workdir).unwrap();
+        repo.create_and_checkout_branch("PROJ-1-feature", "develop")
+            .unwrap();
+        test_repo.commit("feature.txt", "Add feature");
+        test_repo.commit_on_origin("develop", "remote.txt", "Remote change");
+        repo.fetch("develop").unwrap();
+
+        repo.merge("origin/develop", "Merge develop").unwrap();
+
+        assert_eq!(
+            test_repo.last_commit("PROJ-1-feature"),
+            ("Merge develop".to_string(), 2)
+        );
+        assert_eq!(
+            test_repo.file_content("remote.txt").as_deref(),
+            Some("Remote change")
+        );
+    }
+
+    #[test]
+    fn test_merge_fast_forward() {
+        let test_repo = TestRepository::new();
+        let repo = LocalGitRepository::open(&test_repo.workdir).unwrap();
+        repo.create_and_checkout_branch("PROJ-1-feature", "develop")
+            .unwrap();
+        test_repo.commit_on_origin("develop", "remote.txt", "Remote change");
+        repo.fetch("develop").unwrap();
+
+        repo.merge("origin/develop", "Merge develop").unwrap();
+
+        assert_eq!(
+            test_repo.last_commit("PROJ-1-feature"),
+            ("Remote change".to_string(), 1)
+        );
+        assert_eq!(
+            test_repo.file_content("remote.txt").as_deref(),
+            Some("Remote change")
+        );
+    }
+
+    #[test]
+    fn test_merge_conflict_then_continue() {
+        let test_repo = TestRepository::new();
+        let repo = LocalGitRepository::open(&test_repo.workdir).unwrap();
+        repo.create_and_checkout_branch("PROJ-1-feature", "develop")
+            .unwrap();
+        test_repo.commit("shared.txt", "Mine");
+        test_repo.commit_on_origin("develop", "shared.txt", "Theirs");
+        repo.fetch("develop").unwrap();
+
+        let result = repo.merge("origin/develop", "Merge develop");
+
+        assert!(matches!(
+            result,
+            Err(GitError::MergeConflict(b, target, files))
+                if b == "origin/develop" && target == "PROJ-1-feature" && files == ["shared.txt"]
+        ));
+        assert_eq!(
+            repo.operation_in_progress().unwrap(),
+            Some(Operation::Merge)
+        );
+
+        test_repo.resolve("shared.txt", "Both");
+        repo.continue_operation().unwrap();
+
+        assert_eq!(repo.operation_in_progress().unwrap(), None);
+        assert_eq!(
+            test_repo.last_commit("PROJ-1-feature"),
+            ("Merge develop".to_string(), 2)
+        );
+        assert!(repo.is_clean().unwrap());
+    }
+
+    #[test]
+    fn test_merge_abort() {
+        let test_repo = TestRepository::new();
+        let repo = LocalGitRepository::open(&test_repo.workdir).unwrap();
+        repo.create_and_checkout_branch("PROJ-1-feature", "develop")
+            .unwrap();
+        test_repo.commit("shared.txt", "Mine");
+        test_repo.commit_on_origin("develop", "shared.txt", "Theirs");
+        repo.fetch("develop").unwrap();
+        assert!(repo.merge("origin/develop", "Merge develop").is_err());
+
+        repo.abort_operation().unwrap();
+
+        assert_eq!(repo.operation_in_progress().unwrap(), None);
+        assert_eq!(
+            test_repo.last_commit("PROJ-1-feature"),
+            ("Mine".to_string(), 1)
+        );
+        assert_eq!(
+            test_repo.file_content("shared.txt").as_deref(),
+            Some("Mine")
+        );
+        assert!(repo.is_clean().unwrap());
+    }
+
+    #[test]
+    fn test_add_list_and_remove_worktree() {
+        let test_repo = TestRepository::new();
+        let repo = LocalGitRepository::open(&test_repo.workdir).unwrap();
+        let path = test_repo
+            .workdir
+            .with_file_name("work.worktrees")
+            .join("PROJ-1-feature");
+
+        repo.add_worktree(&path, "PROJ-1-feature", "origin/develop")
+            .unwrap();
+
+        let worktrees = repo.worktrees().unwrap();
+        assert_eq!(worktrees.len(), 1);
+        assert!(same_path(&worktrees[0].path, &path));
+        assert_eq!(worktrees[0].branch.as_deref(), Some("PROJ-1-feature"));
+        assert!(path.join("README.md").exists());
+        assert_eq!(test_repo.head_branch(), "main");
+
+        repo.remove_worktree(&path).unwrap();
+
+        assert!(repo.worktrees().unwrap().is_empty());
+        assert!(!path.exists());
+        assert!(repo
+            .branches()
+            .unwrap()
+            .contains(&"PROJ-1-feature".to_string()));
+    }
+
+    #[test]
+    fn test_add_worktree_for_existing_branch_keeps_it() {
+        let test_repo = TestRepository::new();
+        let repo = LocalGitRepository::open(&test_repo.workdir).unwrap();
+        repo.create_and_checkout_branch("PROJ-1-feature", "develop")
+            .unwrap();
+        test_repo.commit("fix.txt", "Fix the login");
+        test_repo.checkout("main");
+        let path = test_repo
+            .workdir
+            .with_file_name("work.worktrees")
+            .join("PROJ-1-feature");
+
+        let result = repo.add_worktree(&path, "PROJ-1-feature", "develop");
+
+        assert!(matches!(result, Err(GitError::BranchExists(_))));
+        assert!(!path.exists());
+        assert_eq!(
+            test_repo.last_commit("PROJ-1-feature"),
+            ("Fix the login".to_string(), 1)
+        );
+    }
+
+    #[test]
+    fn test_remove_worktree_with_changes() {
+        let test_repo = TestRepository::new();
+        let repo = LocalGitRepository::open(&test_repo.workdir).unwrap();
+        let path = test_repo
+            .workdir
+            .with_file_name("work.worktrees")
+            .join("PROJ-1-feature");
+        repo.add_worktree(&path, "PROJ-1-feature", "develop")
+            .unwrap();
+        std::fs::write(path.join("draft.txt"), "Draft").unwrap();
+
+        let result = repo.remove_worktree(&path);
+
+        assert!(matches!(result, Err(GitError::DirtyWorktree(_))));
+        assert!(path.join("draft.txt").exists());
+        assert!(matches!(
+            repo.remove_worktree(&test_repo.workdir.join("missing")),
+            Err(GitError::WorktreeNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_hooks_dir() {
+        let test_repo = TestRepository::new();
+        let repo = LocalGitRepository::open(&test_repo.workdir).unwrap();
+        assert!(same_path(
+            &repo.hooks_dir().unwrap(),
+            &test_repo.workdir.join(".git").join("hooks")
+        ));
+
+        let mut config = test_repo.repository().config().unwrap();
+        config.set_str("core.hooksPath", ".githooks").unwrap();
+        assert_eq!(
+            repo.hooks_dir().unwrap(),
+            test_repo.workdir.join(".githooks")
+        );
+    }
+
+    #[test]
+    fn test_commits_since_last_tag() {
+        let test_repo = TestRepository::new();
+        let repo = LocalGitRepository::open(&test_repo.workdir).unwrap();
+        assert_eq!(repo.last_tag("HEAD", "v").unwrap(), None);
+
+        test_repo.tag("v1.0.0");
+        test_repo.commit("login.rs", "feat(login): add SSO\n\nRefs PROJ-1\n");
+        test_repo.commit("README.md", "docs: explain SSO");
+
+        assert_eq!(
+            repo.last_tag("HEAD", "v").unwrap(),
+            Some("v1.0.0".to_string())
+        );
+        // Other tags, like deployment ones or ones sharing the prefix, are not releases
+        test_repo.tag("deploy-42");
+        test_repo.tag("v-old");
+        test_repo.tag("vendor-x");
+        assert_eq!(
+            repo.last_tag("HEAD", "v").unwrap(),
+            Some("v1.0.0".to_string())
+        );
+        assert_eq!(repo.last_tag("HEAD", "deploy-").unwrap(), None);
+        assert!(matches!(
+            repo.last_tag("missing", "v"),
+            Err(GitError::CommitNotFound(_))
+        ));
+        let commits = repo.commits("v1.0.0..HEAD").unwrap();
+        let messages: Vec<&str> = commits.iter().map(|c| c.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec!["docs: explain SSO", "feat(login): add SSO\n\nRefs PROJ-1"]
+        );
+        assert_eq!(commits[1].subject(), "feat(login): add SSO");
+        assert_eq!(repo.commits("HEAD").unwrap().len(), 3);
+        assert!(matches!(
+            repo.commits("v2.0.0..HEAD"),
+            Err(GitError::CommitNotFound(range)) if range == "v2.0.0..HEAD"
+        ));
+        assert_eq!(repo.commits("v1.0.0...HEAD").unwrap(), commits);
+    }
+
+    #[test]
+    fn test_commit_tag_and_push_refs() {
+        let test_repo = TestRepository::new();
+        let repo = LocalGitRepository::open(&test_repo.workdir).unwrap();
+        std::fs::write(test_repo.workdir.join("VERSION"), "1.1.0\n").unwrap();
+
+        repo.commit_files(&[Path::new("VERSION")], "chore(release): 1.1.0")
+            .unwrap();
+        repo.create_tag("v1.1.0", "Release 1.1.0").unwrap();
+        assert!(matches!(
+            repo.create_tag("v1.1.0", "Release 1.1.0"),
+            Err(GitError::TagExists(tag)) if tag == "v1.1.0"
+        ));
+        repo.push_refs(&[
+            "refs/heads/main".to_string(),
+            "refs/tags/v1.1.0".to_string(),
+        ])
+        .unwrap();
+
+        assert_eq!(test_repo.last_commit("main").0, "chore(release): 1.1.0");
+        assert!(repo.is_clean().unwrap());
+        assert_eq!(test_repo.origin_tags(), vec!["v1.1.0"]);
+        assert_eq!(
+            repo.last_tag("HEAD", "v").unwrap(),
+            Some("v1.1.0".to_string())
+        );
+        let origin = git2::Repository::open_bare(&test_repo.origin).unwrap();
+        let origin_main = origin.revparse_single("main").unwrap().id();
+        let local_main = test_repo.repository().revparse_single("main").unwrap().id();
+        assert_eq!(origin_main, local_main);
+    }
+
+    #[test]
+    fn test_push_refs_rejected_when_remote_moved() {
+        let test_repo = TestRepository::new();
+        let repo = LocalGitRepository::open(&test_repo.workdir).unwrap();
+        test_repo.commit_on_origin("main", "remote.txt", "Remote change");
+        test_repo.commit("local.txt", "Local change");
+
+        assert!(matches!(
+            repo.push_refs(&["refs/heads/main".to_string()]),
+            Err(GitError::NonFastForward(branch)) if branch == "main"
+        ));
+    }
+
+    #[test]
+    fn test_fast_forward_from_origin() {
+        let test_repo = TestRepository::new();
+        test_repo.commit_on_origin("develop", "remote.txt", "Remote change");
+        let repo = LocalGitRepository::open(&test_repo.workdir).unwrap();
+
+        repo.fetch("develop").unwrap();
+        repo.fast_forward("develop").unwrap();
+
+        assert_eq!(test_repo.last_commit("develop").0, "Remote change");
+        assert_eq!(test_repo.head_branch(), "main");
+    }
+
+    #[test]
+    fn test_fast_forward_checked_out_branch() {
+        let test_repo = TestRepository::new();
+        test_repo.commit_on_origin("main", "remote.txt", "Remote change");
+        let repo = LocalGitRepository::open(&test_repo.workdir).unwrap();
+
+        repo.fetch("main").unwrap();
+        repo.fast_forward("main").unwrap();
+
+        assert_eq!(
+            test_repo.file_content("remote.txt").as_deref(),
+            Some("Remote change")
+        );
+        assert!(repo.is_clean().unwrap());
+    }
+
+    #[test]
+    fn test_ahead_behind() {
+        let test_repo = TestRepository::new();
+        test_repo.commit_on_origin("main", "remote.txt", "Remote change");
+        test_repo.commit("local.txt", "Local change");
+        test_repo.commit("other.txt", "Other change");
+        let repo = LocalGitRepository::open(&test_repo.workdir).unwrap();
+        repo.fetch("main").unwrap();
+
+        assert_eq!(
+            repo.ahead_behind("main", "origin/main").unwrap(),
+            Some((2, 1))
+        );
+        assert_eq!(repo.ahead_behind("main", "develop").unwrap(), Some((2, 0)));
+        assert_eq!(repo.ahead_behind("main", "origin/missing").unwrap(), None);
+    }
+
+    #[test]
+    fn test_fast_forward_diverged_branch() {
+        let test_repo = TestRepository::new();
+        test_repo.commit_on_origin("main", "remote.txt", "Remote change");
+        test_repo.commit("local.txt", "Local change");
+        let repo = LocalGitRepository::open(&test_repo.workdir).unwrap();
+
+        repo.fetch("main").unwrap();
+        let result = repo.fast_forward("main");
+
+        assert!(
+            matches!(result, Err(GitError::Diverged(b, upstream)) if b == "main" && upstream == "origin/main")
+        );
+    }
+
+    #[test]
+    fn test_merge_into() {
+        let test_repo = TestRepository::new();
+        let repo = LocalGitRepository::open(&test_repo.workdir).unwrap();
+        repo.create_and_checkout_branch("feature", "develop")
+            .unwrap();
+        test_repo.commit("feature.txt", "Add feature");
+
+        repo.merge_into("feature", "develop", MergeStrategy::Merge, "Merge feature")
+            .unwrap();
+
+        assert_eq!(test_repo.head_branch(), "develop");
+        assert_eq!(
+            test_repo.last_commit("develop"),
+            ("Merge feature".to_string(), 2)
+        );
+        assert!(test_repo.file_content("feature.txt").is_some());
+        assert!(repo.is_clean().unwrap());
+    }
+
+    #[test]
+    fn test_merge_into_failed_checkout_keeps_target() {
+        let test_repo = TestRepository::new();
+        let repo = LocalGitRepository::open(&test_repo.workdir).unwrap();
+        repo.create_and_checkout_branch("feature", "develop")
+            .unwrap();
+        test_repo.commit("feature.txt", "Add feature");
+        test_repo.checkout("develop");
+        test_repo.commit("develop.txt", "Add develop");
+        test_repo.checkout("feature");
+        std::fs::write(test_repo.workdir.join("develop.txt"), "untracked").unwrap();
+
+        let result = repo.merge_into("feature", "develop", MergeStrategy::Merge, "Merge feature");
+
+        assert!(matches!(
+            result,
+            Err(GitError::CannotCheckoutBranch(branch, _)) if branch == "develop"
+        ));
+        assert_eq!(test_repo.head_branch(), "feature");
+        assert_eq!(
+            test_repo.last_commit("develop"),
+            ("Add develop".to_string(), 1)
+        );
+    }
+
+    #[test]
+    fn test_squash_into() {
+        let test_repo = TestRepository::new();
+        let repo = LocalGitRepository::open(&test_repo.workdir).unwrap();
+        repo.create_and_checkout_branch("feature", "develop")
+            .unwrap();
+        test_repo.commit("feature.txt", "Add feature");
+        test_repo.commit("other.txt", "Add other");
+
+        repo.merge_into(
+            "feature",
+            "develop",
+            MergeStrategy::Squash,
+            "PROJ-1 Feature",
+        )
+        .unwrap();
+
+        assert_eq!(
+            test_repo.last_commit("develop"),
+            ("PROJ-1 Feature".to_string(), 1)
+        );
+        assert!(test_repo.file_content("other.txt").is_some());
+    }
+
+    #[test]
+    fn test_merge_into_already_merged() {
+        let test_repo = TestRepository::new();
+        let repo = LocalGitRepository::open(&test_repo.workdir).unwrap();
+        repo.create_and_checkout_branch("feature", "develop")
+            .unwrap();
+        test_repo.commit("feature.txt", "Add feature");
+        repo.merge_into("feature", "develop", MergeStrategy::Merge, "Merge feature")
+            .unwrap();
+        test_repo.checkout("feature");
+
+        repo.merge_into("feature", "develop", MergeStrategy::Merge, "Merge again")
+            .unwrap();
+
+        assert_eq!(test_repo.head_branch(), "develop");
+        assert_eq!(
+            test_repo.last_commit("develop"),
+            ("Merge feature".to_string(), 2)
+        );
+    }
+
+    #[test]
+    fn test_cherry_pick() {
+        let test_repo = TestRepository::new();
+        let repo = LocalGitRepository::open(&test_repo.workdir).unwrap();
+        repo.create_and_checkout_branch("feature", "develop")
+            .unwrap();
+        test_repo.commit("a.txt", "Add a");
+        test_repo.commit("b.txt", "Add b");
+        let ids: Vec<String> = repo
+            .commits("develop..feature")
+            .unwrap()
+            .into_iter()
+            .rev()
+            .map(|commit| commit.id)
+            .collect();
+        repo.create_and_checkout_branch("backport", "main").unwrap();
+
+        repo.cherry_pick(&ids).unwrap();
+
+        assert_eq!(test_repo.head_branch(), "backport");
+        assert_eq!(test_repo.last_commit("backport"), ("Add b".to_string(), 1));
+        assert!(test_repo.file_content("a.txt").is_some());
+        let picked = repo.commits("main..backport").unwrap();
+        assert_eq!(picked.len(), 2);
+        assert!(picked[0]
+            .message
+            .ends_with(&format!("(cherry picked from commit {})", ids[1])));
+        assert!(repo.is_clean().unwrap());
+    }
+
+    #[test]
+    fn test_cherry_pick_skips_empty_picks() {
+        let test_repo = TestRepository::new();
+        let repo = LocalGitRepository::open(&test_repo.workdir).unwrap();
+        repo.create_and_checkout_branch("feature", "develop")
+            .unwrap();
+        test_repo.commit("a.txt", "Add a");
+        test_repo.commit("b.txt", "Add b");
+        let ids: Vec<String> = repo
+            .commits("develop..feature")
+            .unwrap()
+            .into_iter()
+            .rev()
+            .map(|commit| commit.id)
+            .collect();
+        repo.create_and_checkout_branch("backport", "main").unwrap();
+        test_repo.commit("a.txt", "Add a");
+
+        repo.cherry_pick(&ids).unwrap();
+
+        assert_eq!(test_repo.last_commit("backport"), ("Add b".to_string(), 1));
+        assert_eq!(repo.commits("main..backport").unwrap().len(), 2);
+        assert!(repo.is_clean().unwrap());
+    }
+
+    #[test]
+    fn test_cherry_pick_with_conflicts() {
+        let test_repo = TestRepository::new();
+        let repo = LocalGitRepository::open(&test_repo.workdir).unwrap();
+        repo.create_and_checkout_branch("feature", "develop")
+            .unwrap();
+        test_repo.commit("a.txt", "Add a");
+        test_repo.commit("README.md", "Feature readme");
+        let ids: Vec<String> = repo
+            .commits("develop..feature")
+            .unwrap()
+            .into_iter()
+            .rev()
+            .map(|commit| commit.id)
+            .collect();
+        test_repo.checkout("main");
+        test_repo.commit("README.md", "Main readme");
+        repo.create_and_checkout_branch("backport", "main").unwrap();
+
+        let result = repo.cherry_pick(&ids);
+
+        assert!(matches!(
+            result,
+            Err(GitError::CherryPickConflict(id, branch, files))
+                if id == ids[1] && branch == "backport" && files == vec!["README.md"]
+        ));
+        assert_eq!(test_repo.last_commit("backport").0, "Main readme");
+        assert!(test_repo.file_content("a.txt").is_none());
+        assert_eq!(repo.operation_in_progress().unwrap(), None);
+        assert!(repo.is_clean().unwrap());
+    }
+
+    #[test]
+    fn test_merge_into_with_conflicts() {
+        let test_repo = TestRepository::new();
+        let repo = LocalGitRepository::open(&test_repo.workdir).unwrap();
+        repo.create_and_checkout_branch("feature", "develop")
+            .unwrap();
+        test_repo.commit("README.md", "Feature readme");
+        test_repo.checkout("develop");
+        test_repo.commit("README.md", "Develop readme");
+        test_repo.checkout("feature");
+
+        let result = repo.merge_into("feature", "develop", MergeStrategy::Merge, "Merge");
+
+        assert!(matches!(
+            result,
+            Err(GitError::MergeConflict(_, _, files)) if files == vec!["README.md"]
+        ));
+        assert_eq!(test_repo.head_branch(), "feature");
+        assert_eq!(test_repo.last_commit("develop").0, "Develop readme");
+        assert!(repo.is_clean().unwrap());
+    }
+
+    #[test]
+    fn test_delete_branches() {
+        let test_repo = TestRepository::new();
+        let repo = LocalGitRepository::open(&test_repo.workdir).unwrap();
+        repo.create_and_checkout_branch("feature", "develop")
+            .unwrap();
+        repo.push(false).unwrap();
+        test_repo.checkout("develop");
+
+        repo.delete_branch("feature").unwrap();
+        repo.delete_remote_branch("feature").unwrap();
+
+        assert!(!repo.branches().unwrap().contains(&"feature".to_string()));
+        assert_eq!(test_repo.origin_branches(), vec!["develop", "main"]);
+    }
+
+    #[test]
+    fn test_valid_branch_names() {
+        assert!(validate_branch_name("PROJ-123-some-feature").is_ok());
+        assert!(validate_branch_name("feature/PROJ-123").is_ok());
+        assert!(validate_branch_name("jdoe/2023-09-01-fix").is_ok());
+    }
+
+    #[test]
+    fn test_invalid_branch_names() {
+        for name in [
+            "",
+            "@",
+            "-feature",
+            "feature/",
+            "a//b",
+            "feature.",
+            "a..b",
+            "a@{b",
+            "with space",
+            "what?",
+            "a/.hidden",
+            "branch.lock",
+        ] {
+            assert!(
+                matches!(
+                    validate_branch_name(name),
+                    Err(GitError::InvalidBranchName(_, _))
+                ),
+                "{} should be invalid",
+                name
+            );
+        }
+    }
 
     #[test]
     fn test_branch_name_convertion() {
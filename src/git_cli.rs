@@ -0,0 +1,1574 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+use crate::config::MergeStrategy;
+use crate::git::{
+    push_rejection, same_path, CommitInfo, GitError, GitRepository, Operation, Worktree,
+    DEFAULT_REMOTE,
+};
+use crate::release;
+
+/// Git repository driven through the `git` command line.
+///
+/// Unlike `LocalGitRepository`, every operation goes through the user's git installation so
+/// credential helpers, hooks, `includeIf` configuration and ssh setup are all honoured.
+pub struct CliGitRepository {
+    workdir: PathBuf,
+    remote: String,
+}
+
+impl CliGitRepository {
+    pub fn discover() -> Result<Self, GitError> {
+        Self::open(Path::new("."))
+    }
+
+    pub fn open(path: &Path) -> Result<Self, GitError> {
+        let output = Command::new("git")
+            .args(["rev-parse", "--show-toplevel"])
+            .current_dir(path)
+            .output()
+            .map_err(|_| GitError::CannotOpenRepository)?;
+
+        if !output.status.success() {
+            return Err(GitError::CannotOpenRepository);
+        }
+
+        let workdir = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        Ok(Self {
+            workdir: PathBuf::from(workdir),
+            remote: DEFAULT_REMOTE.to_string(),
+        })
+    }
+
+    fn output(&self, args: &[&str]) -> Result<Output, GitError> {
+        Command::new("git")
+            .args(args)
+            .current_dir(&self.workdir)
+            // Keep the messages git proposes, like when continuing a rebase, instead of asking
+            .env("GIT_EDITOR", "true")
+            .output()
+            .map_err(|e| GitError::CommandFailed(args.join(" "), e.to_string()))
+    }
+
+    /// Run git with the given arguments and return its trimmed standard output
+    fn git(&self, args: &[&str]) -> Result<String, GitError> {
+        let output = self.output(args)?;
+
+        if output.status.success() {
+            Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+        } else {
+            Err(GitError::CommandFailed(
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ))
+        }
+    }
+
+    /// Run git with the given arguments and only report whether it succeeded
+    fn git_succeeds(&self, args: &[&str]) -> Result<bool, GitError> {
+        Ok(self.output(args)?.status.success())
+    }
+
+    /// Whether a local branch exists
+    fn branch_exists(&self, branch: &str) -> Result<bool, GitError> {
+        let local_ref = format!("refs/heads/{}", branch);
+        self.git_succeeds(&["rev-parse", "--verify", "--quiet", &local_ref])
+    }
+
+    /// Path of a file in the git directory, e.g. `MERGE_HEAD`
+    fn git_path(&self, name: &str) -> Result<PathBuf, GitError> {
+        Ok(self
+            .workdir
+            .join(self.git(&["rev-parse", "--git-path", name])?))
+    }
+
+    fn conflicting_files(&self) -> Result<Vec<String>, GitError> {
+        let files = self.git(&["diff", "--name-only", "--diff-filter=U"])?;
+        Ok(files.lines().map(String::from).collect())
+    }
+
+    /// Turn the failure of a command into `conflict` when it left conflicting files behind
+    fn stopped_on_conflicts(
+        &self,
+        result: Result<String, GitError>,
+        conflict: impl FnOnce(Vec<String>) -> GitError,
+    ) -> Result<(), GitError> {
+        let error = match result {
+            Ok(_) => return Ok(()),
+            Err(error) => error,
+        };
+
+        let files = self.conflicting_files()?;
+        if files.is_empty() {
+            Err(error)
+        } else {
+            Err(conflict(files))
+        }
+    }
+
+    /// Run `git push --porcelain` and report the status of each pushed reference.
+    ///
+    /// Git talks to the terminal so credential prompts, progress and hook output reach the user,
+    /// only the statuses are read from the standard output.
+    fn push_porcelain(&self, args: &[&str]) -> Result<(), GitError> {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(&self.workdir)
+            .stdin(Stdio::inherit())
+            .stderr(Stdio::inherit())
+            .output()
+            .map_err(|e| GitError::CommandFailed("push".to_string(), e.to_string()))?;
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        for (reference, status) in stdout.lines().filter_map(parse_push_status) {
+            match status {
+                PushStatus::Rejected(reason) => {
+                    eprintln!("{}: rejected ({})", reference, reason);
+                    return Err(push_rejection(reference, reason));
+                }
+                PushStatus::UpToDate => eprintln!("{}: up to date", reference),
+                PushStatus::Pushed => eprintln!("{}: pushed", reference),
+            }
+        }
+
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(GitError::CommandFailed(
+                args.join(" "),
+                output.status.to_string(),
+            ))
+        }
+    }
+
+    /// Branch being rebased, HEAD is detached until the rebase is done
+    fn rebased_branch(&self) -> Result<String, GitError> {
+        let merge_head_name = self.git_path("rebase-merge/head-name")?;
+        let apply_head_name = self.git_path("rebase-apply/head-name")?;
+        let head_name = fs::read_to_string(merge_head_name)
+            .or_else(|_| fs::read_to_string(apply_head_name))
+            .map_err(|_| GitError::NotInABranch)?;
+        Ok(head_name
+            .trim()
+            .trim_start_matches("refs/heads/")
+            .to_string())
+    }
+}
+
+impl GitRepository for CliGitRepository {
+    fn with_remote(self, remote: &str) -> Self {
+        Self {
+            remote: remote.to_string(),
+            ..self
+        }
+    }
+
+    fn remote(&self) -> &str {
+        &self.remote
+    }
+
+    fn create_and_checkout_branch(
+        &self,
+        new_branch: &str,
+        from_branch: &str,
+    ) -> Result<(), GitError> {
+        let local_ref = format!("refs/heads/{}", from_branch);
+        let remote_ref = format!("refs/remotes/{}", from_branch);
+        if !self.git_succeeds(&["rev-parse", "--verify", "--quiet", &local_ref])?
+            && !self.git_succeeds(&["rev-parse", "--verify", "--quiet", &remote_ref])?
+        {
+            return Err(GitError::BranchNotFound(from_branch.to_string()));
+        }
+
+        if self.branch_exists(new_branch)? {
+            return Err(GitError::BranchExists(new_branch.to_string()));
+        }
+
+        // The new branch is pushed under its own name, it must not track the base branch
+        let result = self.git(&["checkout", "--no-track", "-b", new_branch, from_branch]);
+        if result.is_err()
+            && self.branch_exists(new_branch)?
+            && self.current_branch().ok().as_deref() != Some(new_branch)
+        {
+            // Do not leave the branch just created behind when it was never checked out
+            let _ = self.git(&["branch", "-D", new_branch]);
+        }
+        result.map(|_| ())
+    }
+
+    fn checkout_branch(&self, branch: &str) -> Result<(), GitError> {
+        if !self.branch_exists(branch)? {
+            return Err(GitError::BranchNotFound(branch.to_string()));
+        }
+        self.git(&["checkout", branch]).map(|_| ())
+    }
+
+    fn add_worktree(
+        &self,
+        path: &Path,
+        new_branch: &str,
+        from_branch: &str,
+    ) -> Result<(), GitError> {
+        let local_ref = format!("refs/heads/{}", from_branch);
+        let remote_ref = format!("refs/remotes/{}", from_branch);
+        if !self.git_succeeds(&["rev-parse", "--verify", "--quiet", &local_ref])?
+            && !self.git_succeeds(&["rev-parse", "--verify", "--quiet", &remote_ref])?
+        {
+            return Err(GitError::BranchNotFound(from_branch.to_string()));
+        }
+
+        if self.branch_exists(new_branch)? {
+            return Err(GitError::BranchExists(new_branch.to_string()));
+        }
+
+        let path = path.to_string_lossy();
+        let result = self.git(&[
+            "worktree",
+            "add",
+            "--no-track",
+            "-b",
+            new_branch,
+            &path,
+            from_branch,
+        ]);
+        if result.is_err() && self.branch_exists(new_branch)? {
+            // Do not leave the branch just created behind when it was never checked out
+            let _ = self.git(&["branch", "-D", new_branch]);
+        }
+        result.map(|_| ())
+    }
+
+    fn worktrees(&self) -> Result<Vec<Worktree>, GitError> {
+        let output = self.git(&["worktree", "list", "--porcelain"])?;
+        Ok(parse_worktrees(&output))
+    }
+
+    fn main_worktree(&self) -> Result<Self, GitError> {
+        // The main checkout is always listed first
+        let output = self.git(&["worktree", "list", "--porcelain"])?;
+        let workdir = output
+            .lines()
+            .next()
+            .and_then(|line| line.strip_prefix("worktree "))
+            .ok_or(GitError::CannotOpenRepository)?;
+        Ok(Self {
+            workdir: PathBuf::from(workdir),
+            remote: self.remote.clone(),
+        })
+    }
+
+    fn remove_worktree(&self, path: &Path) -> Result<(), GitError> {
+        let worktree = self
+            .worktrees()?
+            .into_iter()
+            .find(|w| same_path(&w.path, path))
+            .ok_or_else(|| GitError::WorktreeNotFound(path.display().to_string()))?;
+
+        let path = worktree.path.to_string_lossy();
+        let changes = self.git(&["-C", &path, "status", "--porcelain"])?;
+        if !changes.is_empty() {
+            return Err(GitError::DirtyWorktree(path.to_string()));
+        }
+
+        self.git(&["worktree", "remove", &path]).map(|_| ())
+    }
+
+    fn push(&self, force_with_lease: bool) -> Result<(), GitError> {
+        let branch_name = self.current_branch()?;
+
+        if !self.git_succeeds(&["remote", "get-url", &self.remote])? {
+            return Err(GitError::RemoteNotFound(self.remote.clone()));
+        }
+
+        let mut args = vec!["push", "--porcelain", "--set-upstream"];
+        let lease;
+        if force_with_lease {
+            // Where the remote branch was at the last fetch, an empty value means it must not exist
+            let remote_ref = format!("refs/remotes/{}", self.remote_branch(&branch_name));
+            let expected = self
+                .git(&["rev-parse", "--verify", "--quiet", &remote_ref])
+                .unwrap_or_default();
+            lease = format!("--force-with-lease=refs/heads/{}:{}", branch_name, expected);
+            args.push(&lease);
+        }
+        args.extend([self.remote.as_str(), branch_name.as_str()]);
+
+        self.push_porcelain(&args)
+    }
+
+    fn workdir(&self) -> Option<&Path> {
+        Some(&self.workdir)
+    }
+
+    fn branches(&self) -> Result<Vec<String>, GitError> {
+        let output = self.git(&["for-each-ref", "--format=%(refname:short)", "refs/heads"])?;
+
+        Ok(output.lines().map(String::from).collect())
+    }
+
+    fn current_branch(&self) -> Result<String, GitError> {
+        self.git(&["symbolic-ref", "--quiet", "--short", "HEAD"])
+            .map_err(|_| GitError::NotInABranch)
+    }
+
+    fn hooks_dir(&self) -> Result<PathBuf, GitError> {
+        self.git_path("hooks")
+    }
+
+    fn commits(&self, range: &str) -> Result<Vec<CommitInfo>, GitError> {
+        // Any range git understands, like `A..B` or `A...B`, is given to git as it is
+        if !self.git_succeeds(&["rev-parse", range, "--"])? {
+            return Err(GitError::CommitNotFound(range.to_string()));
+        }
+
+        // Messages can hold any character but NUL, it separates the commits
+        let output = self.git(&["log", "--no-merges", "--format=%H%n%B%x00", range])?;
+        Ok(output
+            .split('\0')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let (id, message) = entry.split_once('\n').unwrap_or((entry, ""));
+                CommitInfo {
+                    id: id.to_string(),
+                    message: message.trim_end().to_string(),
+                }
+            })
+            .collect())
+    }
+
+    fn last_tag(&self, revision: &str, prefix: &str) -> Result<Option<String>, GitError> {
+        let commit = format!("{}^{{commit}}", revision);
+        if !self.git_succeeds(&["rev-parse", "--verify", "--quiet", &commit])? {
+            return Err(GitError::CommitNotFound(revision.to_string()));
+        }
+
+        // The pattern is a glob, other tags sharing the prefix like `v-old` are excluded
+        let pattern = format!("{}*", prefix);
+        let tags = self.git(&["tag", "--list", &pattern])?;
+        let mut args = vec!["describe", "--tags", "--abbrev=0", "--match", &pattern];
+        for tag in tags.lines() {
+            if !release::is_release_tag(tag, prefix) {
+                args.extend(["--exclude", tag]);
+            }
+        }
+        args.push(revision);
+
+        // Fails when no tag can be reached
+        let output = self.output(&args)?;
+        Ok(output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string()))
+    }
+
+    fn commit_files(&self, files: &[&Path], message: &str) -> Result<(), GitError> {
+        let mut args = vec!["add", "--"];
+        args.extend(files.iter().filter_map(|file| file.to_str()));
+        self.git(&args)?;
+        self.git(&["commit", "-m", message])?;
+        Ok(())
+    }
+
+    fn create_tag(&self, tag: &str, message: &str) -> Result<(), GitError> {
+        let tag_ref = format!("refs/tags/{}", tag);
+        if self.git_succeeds(&["rev-parse", "--verify", "--quiet", &tag_ref])? {
+            return Err(GitError::TagExists(tag.to_string()));
+        }
+
+        self.git(&["tag", "--annotate", "-m", message, tag])?;
+        Ok(())
+    }
+
+    fn push_refs(&self, refs: &[String]) -> Result<(), GitError> {
+        if !self.git_succeeds(&["remote", "get-url", &self.remote])? {
+            return Err(GitError::RemoteNotFound(self.remote.clone()));
+        }
+
+        let mut args = vec!["push", "--porcelain", self.remote.as_str()];
+        args.extend(refs.iter().map(String::as_str));
+        self.push_porcelain(&args)
+    }
+
+    fn remote_url(&self) -> Result<String, GitError> {
+        self.git(&["remote", "get-url", &self.remote])
+            .map_err(|_| GitError::RemoteNotFound(self.remote.clone()))
+    }
+
+    fn needs_push(&self) -> Result<bool, GitError> {
+        let branch_name = self.current_branch()?;
+        let local = self
+            .git(&["rev-parse", "HEAD"])
+            .map_err(|_| GitError::CannotGetHead)?;
+
+        let remote_ref = format!("refs/remotes/{}", self.remote_branch(&branch_name));
+        match self.git(&["rev-parse", "--verify", "--quiet", &remote_ref]) {
+            Ok(remote) => Ok(remote != local),
+            Err(_) => Ok(true),
+        }
+    }
+
+    fn ahead_behind(&self, branch: &str, other: &str) -> Result<Option<(usize, usize)>, GitError> {
+        let exists = |name: &str| {
+            self.git_succeeds(&[
+                "rev-parse",
+                "--verify",
+                "--quiet",
+                &format!("{}^{{commit}}", name),
+            ])
+        };
+        if !exists(branch)? || !exists(other)? {
+            return Ok(None);
+        }
+
+        let range = format!("{}...{}", branch, other);
+        let output = self.git(&["rev-list", "--left-right", "--count", &range])?;
+        let counts: Vec<usize> = output
+            .split_whitespace()
+            .filter_map(|count| count.parse().ok())
+            .collect();
+
+        match counts[..] {
+            [ahead, behind] => Ok(Some((ahead, behind))),
+            _ => Err(GitError::CommandFailed(
+                format!("rev-list --left-right --count {}", range),
+                format!("unexpected output {}", output),
+            )),
+        }
+    }
+
+    fn is_clean(&self) -> Result<bool, GitError> {
+        Ok(self
+            .git(&["status", "--porcelain", "--untracked-files=no"])?
+            .is_empty())
+    }
+
+    fn stash(&self, message: &str) -> Result<(), GitError> {
+        self.git(&["stash", "push", "--message", message])
+            .map(|_| ())
+    }
+
+    fn stash_pop(&self) -> Result<(), GitError> {
+        self.git(&["stash", "pop"]).map(|_| ()).map_err(|e| {
+            GitError::CannotRestoreStash(self.current_branch().unwrap_or_default(), e.to_string())
+        })
+    }
+
+    fn fetch(&self, branch: &str) -> Result<(), GitError> {
+        self.git(&["fetch", &self.remote, branch]).map(|_| ())
+    }
+
+    fn fast_forward(&self, branch: &str) -> Result<(), GitError> {
+        let local_ref = format!("refs/heads/{}", branch);
+        let remote_branch = self.remote_branch(branch);
+        let remote_ref = format!("refs/remotes/{}", remote_branch);
+        if !self.git_succeeds(&["rev-parse", "--verify", "--quiet", &remote_ref])? {
+            return Err(GitError::BranchNotFound(remote_branch));
+        }
+
+        if self.git_succeeds(&["merge-base", "--is-ancestor", &remote_ref, &local_ref])? {
+            return Ok(());
+        }
+        if !self.git_succeeds(&["merge-base", "--is-ancestor", &local_ref, &remote_ref])? {
+            return Err(GitError::Diverged(branch.to_string(), remote_branch));
+        }
+
+        if self.current_branch().ok().as_deref() == Some(branch) {
+            self.git(&["merge", "--ff-only", &remote_ref]).map(|_| ())
+        } else {
+            let refspec = format!("{}:{}", remote_ref, local_ref);
+            self.git(&["fetch", ".", &refspec]).map(|_| ())
+        }
+    }
+
+    fn merge_into(
+        &self,
+        branch: &str,
+        target: &str,
+        strategy: MergeStrategy,
+        message: &str,
+    ) -> Result<(), GitError> {
+        let previous = self.current_branch().ok();
+        self.git(&["checkout", target])?;
+
+        let merged = match strategy {
+            MergeStrategy::Merge => self.git(&["merge", "--no-ff", "-m", message, branch]),
+            MergeStrategy::Squash => self
+                .git(&["merge", "--squash", branch])
+                .and_then(|_| self.git(&["commit", "-m", message])),
+        };
+
+        match merged {
+            Ok(_) => Ok(()),
+            Err(error) => {
+                let conflicts = self.git(&["diff", "--name-only", "--diff-filter=U"])?;
+
+                // Go back to where we started so the user can fix the branch, in its own
+                // worktree when it has one
+                self.git(&["reset", "--merge"])?;
+                let in_worktree = self
+                    .worktrees()?
+                    .iter()
+                    .any(|w| w.branch.as_deref() == Some(branch));
+                let back = if in_worktree {
+                    previous.as_deref().unwrap_or(target)
+                } else {
+                    branch
+                };
+                self.git(&["checkout", back])?;
+
+                if conflicts.is_empty() {
+                    Err(error)
+                } else {
+                    Err(GitError::MergeConflict(
+                        branch.to_string(),
+                        target.to_string(),
+                        conflicts.lines().map(String::from).collect(),
+                    ))
+                }
+            }
+        }
+    }
+
+    fn cherry_pick(&self, commits: &[String]) -> Result<(), GitError> {
+        let branch = self.current_branch()?;
+        let mut args = vec!["cherry-pick", "-x"];
+        args.extend(commits.iter().map(String::as_str));
+
+        let mut result = self.git(&args);
+        let (error, picked, files) = loop {
+            let error = match result {
+                Ok(_) => return Ok(()),
+                Err(error) => error,
+            };
+            let picked = match self.git(&["rev-parse", "--verify", "--quiet", "CHERRY_PICK_HEAD"]) {
+                Ok(picked) => picked,
+                // Failed before applying anything, e.g. an unknown commit
+                Err(_) => return Err(error),
+            };
+            let files = self.conflicting_files()?;
+
+            // Stopped on a commit whose changes the branch already has, the next ones are picked
+            if files.is_empty() && self.git_succeeds(&["diff", "--cached", "--quiet"])? {
+                result = self.git(&["cherry-pick", "--skip"]);
+                continue;
+            }
+            break (error, picked, files);
+        };
+
+        // Go back to where the branch was before the first commit
+        self.git(&["cherry-pick", "--abort"])?;
+
+        if files.is_empty() {
+            Err(error)
+        } else {
+            Err(GitError::CherryPickConflict(picked, branch, files))
+        }
+    }
+
+    fn rebase(&self, onto: &str) -> Result<(), GitError> {
+        let branch = self.current_branch()?;
+        let result = self.git(&["rebase", onto]);
+        self.stopped_on_conflicts(result, |files| GitError::RebaseConflict(branch, files))
+    }
+
+    fn merge(&self, branch: &str, message: &str) -> Result<(), GitError> {
+        let current = self.current_branch()?;
+        let result = self.git(&["merge", "--no-edit", "-m", message, branch]);
+        self.stopped_on_conflicts(result, |files| {
+            GitError::MergeConflict(branch.to_string(), current, files)
+        })
+    }
+
+    fn operation_in_progress(&self) -> Result<Option<Operation>, GitError> {
+        if self.git_path("rebase-merge")?.exists() || self.git_path("rebase-apply")?.exists() {
+            Ok(Some(Operation::Rebase))
+        } else if self.git_path("MERGE_HEAD")?.exists() {
+            Ok(Some(Operation::Merge))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn continue_operation(&self) -> Result<(), GitError> {
+        let operation = self
+            .operation_in_progress()?
+            .ok_or(GitError::NothingInProgress)?;
+
+        let files = self.conflicting_files()?;
+        if !files.is_empty() {
+            return Err(GitError::UnresolvedConflicts(files));
+        }
+
+        match operation {
+            Operation::Rebase => {
+                let branch = self.rebased_branch()?;
+                let result = self.git(&["rebase", "--continue"]);
+                self.stopped_on_conflicts(result, |files| GitError::RebaseConflict(branch, files))
+            }
+            Operation::Merge => self.git(&["commit", "--no-edit"]).map(|_| ()),
+        }
+    }
+
+    fn abort_operation(&self) -> Result<(), GitError> {
+        match self.operation_in_progress()? {
+            Some(Operation::Rebase) => self.git(&["rebase", "--abort"]).map(|_| ()),
+            Some(Operation::Merge) => self.git(&["merge", "--abort"]).map(|_| ()),
+            None => Err(GitError::NothingInProgress),
+        }
+    }
+
+    fn delete_branch(&self, branch: &str) -> Result<(), GitError> {
+        self.git(&["branch", "-D", branch]).map(|_| ())
+    }
+
+    fn delete_remote_branch(&self, branch: &str) -> Result<(), GitError> {
+        let remote_ref = format!("refs/remotes/{}", self.remote_branch(branch));
+        if !self.git_succeeds(&["rev-parse", "--verify", "--quiet", &remote_ref])? {
+            return Ok(());
+        }
+
+        self.git(&["push", &self.remote, "--delete", branch])
+            .map(|_| ())
+    }
+}
+
+/// Linked worktrees from `git worktree list --porcelain`, whose first entry is the main checkout
+fn parse_worktrees(output: &str) -> Vec<Worktree> {
+    output
+        .split("\n\n")
+        .skip(1)
+        .filter_map(|entry| {
+            let mut path = None;
+            let mut branch = None;
+            for line in entry.lines() {
+                match line.split_once(' ') {
+                    Some(("worktree", value)) => path = Some(PathBuf::from(value)),
+                    Some(("branch", value)) => {
+                        branch = Some(value.trim_start_matches("refs/heads/").to_string())
+                    }
+                    // The directory was deleted by hand
+                    _ if line.starts_with("prunable") => return None,
+                    _ => {}
+                }
+            }
+            Some(Worktree {
+                path: path?,
+                branch,
+            })
+        })
+        .collect()
+}
+
+/// Outcome of a reference in the porcelain output of `git push`
+#[derive(Debug, PartialEq, Eq)]
+enum PushStatus<'a> {
+    Pushed,
+    UpToDate,
+    Rejected(&'a str),
+}
+
+/// Parse a reference line of `git push --porcelain`, like
+/// `!<tab>refs/heads/a:refs/heads/a<tab>[rejected] (non-fast-forward)`
+fn parse_push_status(line: &str) -> Option<(&str, PushStatus<'_>)> {
+    let mut fields = line.split('\t');
+    let flag = fields.next()?;
+    let (_, reference) = fields.next()?.split_once(':')?;
+    let summary = fields.next()?;
+
+    let status = match flag {
+        "!" => PushStatus::Rejected(
+            summary
+                .split_once(" (")
+                .map(|(_, reason)| reason.trim_end_matches(')'))
+                .unwrap_or(summary),
+        ),
+        "=" => PushStatus::UpToDate,
+        _ => PushStatus::Pushed,
+    };
+    Some((reference, status))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestRepository;
+
+    #[test]
+    fn test_list_branches() {
+        let test_repo = TestRepository::new();
+        let repo = CliGitRepository::open(&test_repo.workdir).unwrap();
+
+        assert_eq!(repo.branches().unwrap(), vec!["develop", "main"]);
+        assert_eq!(repo.current_branch().unwrap(), "main");
+        assert_eq!(
+            repo.remote_url().unwrap(),
+            test_repo.origin.to_str().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_create_and_checkout_branch() {
+        let test_repo = TestRepository::new();
+        let repo = CliGitRepository::open(&test_repo.workdir).unwrap();
+
+        repo.create_and_checkout_branch("PROJ-1-feature", "develop")
+            .unwrap();
+
+        assert_eq!(test_repo.head_branch(), "PROJ-1-feature");
+    }
+
+    #[test]
+    fn test_checkout_branch() {
+        let test_repo = TestRepository::new();
+        let repo = CliGitRepository::open(&test_repo.workdir).unwrap();
+
+        repo.checkout_branch("develop").unwrap();
+
+        assert_eq!(test_repo.head_branch(), "develop");
+        assert!(matches!(
+            repo.checkout_branch("missing"),
+            Err(GitError::BranchNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_create_existing_branch_keeps_it() {
+        let test_repo = TestRepository::new();
+        let repo = CliGitRepository::open(&test_repo.workdir).unwrap();
+        repo.create_and_checkout_branch("PROJ-1-feature", "develop")
+            .unwrap();
+        test_repo.commit("fix.txt", "Fix the login");
+        test_repo.checkout("main");
+
+        let result = repo.create_and_checkout_branch("PROJ-1-feature", "develop");
+
+        assert!(matches!(result, Err(GitError::BranchExists(_))));
+        assert_eq!(test_repo.head_branch(), "main");
+        assert_eq!(
+            test_repo.last_commit("PROJ-1-feature"),
+            ("Fix the login".to_string(), 1)
+        );
+    }
+
+    #[test]
+    fn test_create_branch_from_remote_branch() {
+        let test_repo = TestRepository::new();
+        test_repo.commit_on_origin("develop", "remote.txt", "Remote change");
+        let repo = CliGitRepository::open(&test_repo.workdir).unwrap();
+        repo.fetch("develop").unwrap();
+
+        repo.create_and_checkout_branch("PROJ-1-feature", "origin/develop")
+            .unwrap();
+
+        assert_eq!(test_repo.head_branch(), "PROJ-1-feature");
+        assert_eq!(
+            test_repo.file_content("remote.txt").as_deref(),
+            Some("Remote change")
+        );
+        assert_eq!(test_repo.last_commit("develop").0, "Initial commit");
+    }
+
+    #[test]
+    fn test_create_branch_with_conflicting_changes() {
+        let test_repo = TestRepository::new();
+        test_repo.checkout("develop");
+        test_repo.commit("README.md", "Develop readme");
+        test_repo.checkout("main");
+        std::fs::write(test_repo.workdir.join("README.md"), "Local change").unwrap();
+        let repo = CliGitRepository::open(&test_repo.workdir).unwrap();
+
+        let result = repo.create_and_checkout_branch("PROJ-1-feature", "develop");
+
+        assert!(result.is_err());
+        assert_eq!(test_repo.head_branch(), "main");
+        assert!(!repo
+            .branches()
+            .unwrap()
+            .contains(&"PROJ-1-feature".to_string()));
+        assert_eq!(
+            test_repo.file_content("README.md").as_deref(),
+            Some("Local change")
+        );
+    }
+
+    #[test]
+    fn test_stash_and_restore_on_new_branch() {
+        let test_repo = TestRepository::new();
+        std::fs::write(test_repo.workdir.join("README.md"), "Local change").unwrap();
+        let repo = CliGitRepository::open(&test_repo.workdir).unwrap();
+
+        repo.stash("wf start").unwrap();
+        assert!(repo.is_clean().unwrap());
+        repo.create_and_checkout_branch("PROJ-1-feature", "develop")
+            .unwrap();
+        repo.stash_pop().unwrap();
+
+        assert!(!repo.is_clean().unwrap());
+        assert_eq!(
+            test_repo.file_content("README.md").as_deref(),
+            Some("Local change")
+        );
+        assert!(repo.stash_pop().is_err());
+    }
+
+    #[test]
+    fn test_stash_pop_conflict_keeps_stash() {
+        let test_repo = TestRepository::new();
+        std::fs::write(test_repo.workdir.join("README.md"), "Local change").unwrap();
+        let repo = CliGitRepository::open(&test_repo.workdir).unwrap();
+        repo.stash("wf start").unwrap();
+        test_repo.commit("README.md", "Other change");
+
+        let result = repo.stash_pop();
+
+        assert!(matches!(result, Err(GitError::CannotRestoreStash(b, _)) if b == "main"));
+        let mut stashes = 0;
+        test_repo
+            .repository()
+            .stash_foreach(|_, _, _| {
+                stashes += 1;
+                true
+            })
+            .unwrap();
+        assert_eq!(stashes, 1);
+    }
+
+    #[test]
+    fn test_create_branch_from_missing_branch() {
+        let test_repo = TestRepository::new();
+        let repo = CliGitRepository::open(&test_repo.workdir).unwrap();
+
+        let result = repo.create_and_checkout_branch("PROJ-1-feature", "missing");
+
+        assert!(matches!(result, Err(GitError::BranchNotFound(b)) if b == "missing"));
+        assert_eq!(test_repo.head_branch(), "main");
+    }
+
+    #[test]
+    fn test_parse_push_status() {
+        assert_eq!(
+            parse_push_status("*\trefs/heads/a:refs/heads/a\t[new branch]"),
+            Some(("refs/heads/a", PushStatus::Pushed))
+        );
+        assert_eq!(
+            parse_push_status("=\trefs/heads/a:refs/heads/a\t[up to date]"),
+            Some(("refs/heads/a", PushStatus::UpToDate))
+        );
+        assert_eq!(
+            parse_push_status("!\trefs/heads/a:refs/heads/a\t[rejected] (fetch first)"),
+            Some(("refs/heads/a", PushStatus::Rejected("fetch first")))
+        );
+        assert_eq!(
+            parse_push_status(
+                "!\trefs/heads/a:refs/heads/a\t[remote rejected] (protected branch hook declined)"
+            ),
+            Some((
+                "refs/heads/a",
+                PushStatus::Rejected("protected branch hook declined")
+            ))
+        );
+        assert_eq!(parse_push_status("To /tmp/origin.git"), None);
+        assert_eq!(parse_push_status("Done"), None);
+    }
+
+    #[test]
+    fn test_push_new_branch() {
+        let test_repo = TestRepository::new();
+        let repo = CliGitRepository::open(&test_repo.workdir).unwrap();
+        repo.create_and_checkout_branch("PROJ-1-feature", "develop")
+            .unwrap();
+        test_repo.commit("feature.txt", "Add feature");
+
+        assert!(repo.needs_push().unwrap());
+        repo.push(false).unwrap();
+
+        assert!(!repo.needs_push().unwrap());
+        assert_eq!(
+            test_repo.origin_branches(),
+            vec!["PROJ-1-feature", "develop", "main"]
+        );
+        assert_eq!(
+            test_repo.upstream("PROJ-1-feature").as_deref(),
+            Some("origin/PROJ-1-feature")
+        );
+    }
+
+    #[test]
+    fn test_push_to_configured_remote() {
+        let test_repo = TestRepository::new();
+        test_repo.rename_origin("upstream");
+        let repo = CliGitRepository::open(&test_repo.workdir)
+            .unwrap()
+            .with_remote("upstream");
+        repo.create_and_checkout_branch("PROJ-1-feature", "develop")
+            .unwrap();
+        assert_eq!(test_repo.upstream("PROJ-1-feature"), None);
+
+        repo.push(false).unwrap();
+
+        assert!(!repo.needs_push().unwrap());
+        assert_eq!(
+            test_repo.upstream("PROJ-1-feature").as_deref(),
+            Some("upstream/PROJ-1-feature")
+        );
+        assert_eq!(
+            repo.ahead_behind("PROJ-1-feature", "upstream/develop")
+                .unwrap(),
+            Some((0, 0))
+        );
+    }
+
+    #[test]
+    fn test_push_to_missing_remote() {
+        let test_repo = TestRepository::new();
+        let repo = CliGitRepository::open(&test_repo.workdir)
+            .unwrap()
+            .with_remote("missing");
+
+        let result = repo.push(false);
+
+        assert!(matches!(result, Err(GitError::RemoteNotFound(r)) if r == "missing"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_push_rejected_by_remote_hook() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let test_repo = TestRepository::new();
+        let hook = test_repo.origin.join("hooks").join("pre-receive");
+        std::fs::write(&hook, "#!/bin/sh\nexit 1\n").unwrap();
+        std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let repo = CliGitRepository::open(&test_repo.workdir).unwrap();
+        repo.create_and_checkout_branch("PROJ-1-feature", "develop")
+            .unwrap();
+
+        let result = repo.push(false);
+
+        assert!(matches!(
+            result,
+            Err(GitError::PushRejected(b, reason))
+                if b == "PROJ-1-feature" && reason == "pre-receive hook declined"
+        ));
+        assert_eq!(test_repo.origin_branches(), vec!["develop", "main"]);
+    }
+
+    #[test]
+    fn test_push_rejected_when_remote_moved() {
+        let test_repo = TestRepository::new();
+        let repo = CliGitRepository::open(&test_repo.workdir).unwrap();
+        repo.create_and_checkout_branch("PROJ-1-feature", "develop")
+            .unwrap();
+        repo.push(false).unwrap();
+        test_repo.commit_on_origin("PROJ-1-feature", "remote.txt", "Remote change");
+        test_repo.commit("feature.txt", "Add feature");
+
+        let result = repo.push(false);
+
+        assert!(matches!(result, Err(GitError::NonFastForward(b)) if b == "PROJ-1-feature"));
+        assert_eq!(test_repo.last_commit("PROJ-1-feature").0, "Add feature");
+    }
+
+    #[test]
+    fn test_force_with_lease_needs_fetch() {
+        let test_repo = TestRepository::new();
+        let repo = CliGitRepository::open(&test_repo.workdir).unwrap();
+        repo.create_and_checkout_branch("PROJ-1-feature", "develop")
+            .unwrap();
+        repo.push(false).unwrap();
+        test_repo.commit_on_origin("PROJ-1-feature", "remote.txt", "Remote change");
+        test_repo.commit("feature.txt", "Add feature");
+
+        let result = repo.push(true);
+        assert!(matches!(result, Err(GitError::StaleLease(b)) if b == "PROJ-1-feature"));
+
+        repo.fetch("PROJ-1-feature").unwrap();
+        repo.push(true).unwrap();
+        assert!(!repo.needs_push().unwrap());
+        assert_eq!(
+            repo.ahead_behind("PROJ-1-feature", "origin/PROJ-1-feature")
+                .unwrap(),
+            Some((0, 0))
+        );
+    }
+
+    #[test]
+    fn test_rebase_onto_remote_base() {
+        let test_repo = TestRepository::new();
+        let repo = CliGitRepository::open(&test_repo.workdir).unwrap();
+        repo.create_and_checkout_branch("PROJ-1-feature", "develop")
+            .unwrap();
+        test_repo.commit("feature.txt", "Add feature");
+        test_repo.commit_on_origin("develop", "remote.txt", "Remote change");
+        repo.fetch("develop").unwrap();
+
+        repo.rebase("origin/develop").unwrap();
+
+        assert_eq!(repo.operation_in_progress().unwrap(), None);
+        assert_eq!(test_repo.head_branch(), "PROJ-1-feature");
+        assert_eq!(
+            test_repo.last_commit("PROJ-1-feature"),
+            ("Add feature".to_string(), 1)
+        );
+        assert_eq!(
+            repo.ahead_behind("PROJ-1-feature", "origin/develop")
+                .unwrap(),
+            Some((1, 0))
+        );
+        assert_eq!(
+            test_repo.file_content("remote.txt").as_deref(),
+            Some("Remote change")
+        );
+    }
+
+    #[test]
+    fn test_rebase_conflict_then_continue() {
+        let test_repo = TestRepository::new();
+        let repo = CliGitRepository::open(&test_repo.workdir).unwrap();
+        repo.create_and_checkout_branch("PROJ-1-feature", "develop")
+            .unwrap();
+        test_repo.commit("shared.txt", "Mine");
+        test_repo.commit("feature.txt", "Add feature");
+        test_repo.commit_on_origin("develop", "shared.txt", "Theirs");
+        repo.fetch("develop").unwrap();
+
+        let result = repo.rebase("origin/develop");
+
+        assert!(matches!(
+            result,
+            Err(GitError::RebaseConflict(b, files)) if b == "PROJ-1-feature" && files == ["shared.txt"]
+        ));
+        assert_eq!(
+            repo.operation_in_progress().unwrap(),
+            Some(Operation::Rebase)
+        );
+        assert!(test_repo
+            .file_content("shared.txt")
+            .unwrap()
+            .contains("<<<<<<<"));
+        assert!(matches!(
+            repo.continue_operation(),
+            Err(GitError::UnresolvedConflicts(files)) if files == ["shared.txt"]
+        ));
+
+        test_repo.resolve("shared.txt", "Both");
+        repo.continue_operation().unwrap();
+
+        assert_eq!(repo.operation_in_progress().unwrap(), None);
+        assert_eq!(test_repo.head_branch(), "PROJ-1-feature");
+        assert_eq!(test_repo.last_commit("PROJ-1-feature").0, "Add feature");
+        assert_eq!(
+            repo.ahead_behind("PROJ-1-feature", "origin/develop")
+                .unwrap(),
+            Some((2, 0))
+        );
+        assert_eq!(
+            test_repo.file_content("shared.txt").as_deref(),
+            Some("Both")
+        );
+    }
+
+    #[test]
+    fn test_rebase_abort() {
+        let test_repo = TestRepository::new();
+        let repo = CliGitRepository::open(&test_repo.workdir).unwrap();
+        repo.create_and_checkout_branch("PROJ-1-feature", "develop")
+            .unwrap();
+        test_repo.commit("shared.txt", "Mine");
+        test_repo.commit_on_origin("develop", "shared.txt", "Theirs");
+        repo.fetch("develop").unwrap();
+        assert!(repo.rebase("origin/develop").is_err());
+
+        repo.abort_operation().unwrap();
+
+        assert_eq!(repo.operation_in_progress().unwrap(), None);
+        assert_eq!(test_repo.head_branch(), "PROJ-1-feature");
+        assert_eq!(test_repo.last_commit("PROJ-1-feature").0, "Mine");
+        assert_eq!(
+            test_repo.file_content("shared.txt").as_deref(),
+            Some("Mine")
+        );
+        assert!(repo.is_clean().unwrap());
+        assert!(matches!(
+            repo.abort_operation(),
+            Err(GitError::NothingInProgress)
+        ));
+    }
+
+    #[test]
+    fn test_merge_remote_base() {
+        let test_repo = TestRepository::new();
+        let repo = CliGitRepository::open(&test_repo.workdir).unwrap();
+        repo.create_and_checkout_branch("PROJ-1-feature", "develop")
+            .unwrap();
+        test_repo.commit("feature.txt", "Add feature");
+        test_repo.commit_on_origin("develop", "remote.txt", "Remote change");
+        repo.fetch("develop").unwrap();
+
+        repo.merge("origin/develop", "Merge develop").unwrap();
+
+        assert_eq!(
+            test_repo.last_commit("PROJ-1-feature"),
+            ("Merge develop".to_string(), 2)
+        );
+        assert_eq!(
+            test_repo.file_content("remote.txt").as_deref(),
+            Some("Remote change")
+        );
+    }
+
+    #[test]
+    fn test_merge_fast_forward() {
+        let test_repo = TestRepository::new();
+        let repo = CliGitRepository::open(&test_repo.workdir).unwrap();
+        repo.create_and_checkout_branch("PROJ-1-feature", "develop")
+            .unwrap();
+        test_repo.commit_on_origin("develop", "remote.txt", "Remote change");
+        repo.fetch("develop").unwrap();
+
+        repo.merge("origin/develop", "Merge develop").unwrap();
+
+        assert_eq!(
+            test_repo.last_commit("PROJ-1-feature"),
+            ("Remote change".to_string(), 1)
+        );
+        assert_eq!(
+            test_repo.file_content("remote.txt").as_deref(),
+            Some("Remote change")
+        );
+    }
+
+    #[test]
+    fn test_merge_conflict_then_continue() {
+        let test_repo = TestRepository::new();
+        let repo = CliGitRepository::open(&test_repo.workdir).unwrap();
+        repo.create_and_checkout_branch("PROJ-1-feature", "develop")
+            .unwrap();
+        test_repo.commit("shared.txt", "Mine");
+        test_repo.commit_on_origin("develop", "shared.txt", "Theirs");
+        repo.fetch("develop").unwrap();
+
+        let result = repo.merge("origin/develop", "Merge develop");
+
+        assert!(matches!(
+            result,
+            Err(GitError::MergeConflict(b, target, files))
+                if b == "origin/develop" && target == "PROJ-1-feature" && files == ["shared.txt"]
+        ));
+        assert_eq!(
+            repo.operation_in_progress().unwrap(),
+            Some(Operation::Merge)
+        );
+
+        test_repo.resolve("shared.txt", "Both");
+        repo.continue_operation().unwrap();
+
+        assert_eq!(repo.operation_in_progress().unwrap(), None);
+        assert_eq!(
+            test_repo.last_commit("PROJ-1-feature"),
+            ("Merge develop".to_string(), 2)
+        );
+        assert!(repo.is_clean().unwrap());
+    }
+
+    #[test]
+    fn test_merge_abort() {
+        let test_repo = TestRepository::new();
+        let repo = CliGitRepository::open(&test_repo.workdir).unwrap();
+        repo.create_and_checkout_branch("PROJ-1-feature", "develop")
+            .unwrap();
+        test_repo.commit("shared.txt", "Mine");
+        test_repo.commit_on_origin("develop", "shared.txt", "Theirs");
+        repo.fetch("develop").unwrap();
+        assert!(repo.merge("origin/develop", "Merge develop").is_err());
+
+        repo.abort_operation().unwrap();
+
+        assert_eq!(repo.operation_in_progress().unwrap(), None);
+        assert_eq!(
+            test_repo.last_commit("PROJ-1-feature"),
+            ("Mine".to_string(), 1)
+        );
+        assert_eq!(
+            test_repo.file_content("shared.txt").as_deref(),
+            Some("Mine")
+        );
+        assert!(repo.is_clean().unwrap());
+    }
+
+    #[test]
+    fn test_add_list_and_remove_worktree() {
+        let test_repo = TestRepository::new();
+        let repo = CliGitRepository::open(&test_repo.workdir).unwrap();
+        let path = test_repo
+            .workdir
+            .with_file_name("work.worktrees")
+            .join("PROJ-1-feature");
+
+        repo.add_worktree(&path, "PROJ-1-feature", "origin/develop")
+            .unwrap();
+
+        let worktrees = repo.worktrees().unwrap();
+        assert_eq!(worktrees.len(), 1);
+        assert!(same_path(&worktrees[0].path, &path));
+        assert_eq!(worktrees[0].branch.as_deref(), Some("PROJ-1-feature"));
+        assert!(path.join("README.md").exists());
+        assert_eq!(test_repo.head_branch(), "main");
+
+        repo.remove_worktree(&path).unwrap();
+
+        assert!(repo.worktrees().unwrap().is_empty());
+        assert!(!path.exists());
+        assert!(repo
+            .branches()
+            .unwrap()
+            .contains(&"PROJ-1-feature".to_string()));
+    }
+
+    #[test]
+    fn test_add_worktree_for_existing_branch_keeps_it() {
+        let test_repo = TestRepository::new();
+        let repo = CliGitRepository::open(&test_repo.workdir).unwrap();
+        repo.create_and_checkout_branch("PROJ-1-feature", "develop")
+            .unwrap();
+        test_repo.commit("fix.txt", "Fix the login");
+        test_repo.checkout("main");
+        let path = test_repo
+            .workdir
+            .with_file_name("work.worktrees")
+            .join("PROJ-1-feature");
+
+        let result = repo.add_worktree(&path, "PROJ-1-feature", "develop");
+
+        assert!(matches!(result, Err(GitError::BranchExists(_))));
+        assert!(!path.exists());
+        assert_eq!(
+            test_repo.last_commit("PROJ-1-feature"),
+            ("Fix the login".to_string(), 1)
+        );
+    }
+
+    #[test]
+    fn test_remove_worktree_with_changes() {
+        let test_repo = TestRepository::new();
+        let repo = CliGitRepository::open(&test_repo.workdir).unwrap();
+        let path = test_repo
+            .workdir
+            .with_file_name("work.worktrees")
+            .join("PROJ-1-feature");
+        repo.add_worktree(&path, "PROJ-1-feature", "develop")
+            .unwrap();
+        std::fs::write(path.join("draft.txt"), "Draft").unwrap();
+
+        let result = repo.remove_worktree(&path);
+
+        assert!(matches!(result, Err(GitError::DirtyWorktree(_))));
+        assert!(path.join("draft.txt").exists());
+        assert!(matches!(
+            repo.remove_worktree(&test_repo.workdir.join("missing")),
+            Err(GitError::WorktreeNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_hooks_dir() {
+        let test_repo = TestRepository::new();
+        let repo = CliGitRepository::open(&test_repo.workdir).unwrap();
+        assert!(same_path(
+            &repo.hooks_dir().unwrap(),
+            &test_repo.workdir.join(".git").join("hooks")
+        ));
+
+        let mut config = test_repo.repository().config().unwrap();
+        config.set_str("core.hooksPath", ".githooks").unwrap();
+        assert_eq!(
+            repo.hooks_dir().unwrap(),
+            test_repo.workdir.join(".githooks")
+        );
+    }
+
+    #[test]
+    fn test_commits_since_last_tag() {
+        let test_repo = TestRepository::new();
+        let repo = CliGitRepository::open(&test_repo.workdir).unwrap();
+        assert_eq!(repo.last_tag("HEAD", "v").unwrap(), None);
+
+        test_repo.tag("v1.0.0");
+        test_repo.commit("login.rs", "feat(login): add SSO\n\nRefs PROJ-1\n");
+        test_repo.commit("README.md", "docs: explain SSO");
+
+        assert_eq!(
+            repo.last_tag("HEAD", "v").unwrap(),
+            Some("v1.0.0".to_string())
+        );
+        // Other tags, like deployment ones or ones sharing the prefix, are not releases
+        test_repo.tag("deploy-42");
+        test_repo.tag("v-old");
+        test_repo.tag("vendor-x");
+        assert_eq!(
+            repo.last_tag("HEAD", "v").unwrap(),
+            Some("v1.0.0".to_string())
+        );
+        assert_eq!(repo.last_tag("HEAD", "deploy-").unwrap(), None);
+        assert!(matches!(
+            repo.last_tag("missing", "v"),
+            Err(GitError::CommitNotFound(_))
+        ));
+        let commits = repo.commits("v1.0.0..HEAD").unwrap();
+        let messages: Vec<&str> = commits.iter().map(|c| c.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec!["docs: explain SSO", "feat(login): add SSO\n\nRefs PROJ-1"]
+        );
+        assert_eq!(commits[1].subject(), "feat(login): add SSO");
+        assert_eq!(repo.commits("HEAD").unwrap().len(), 3);
+        assert!(matches!(
+            repo.commits("v2.0.0..HEAD"),
+            Err(GitError::CommitNotFound(range)) if range == "v2.0.0..HEAD"
+        ));
+        assert_eq!(repo.commits("v1.0.0...HEAD").unwrap(), commits);
+    }
+
+    #[test]
+    fn test_commit_tag_and_push_refs() {
+        let test_repo = TestRepository::new();
+        let repo = CliGitRepository::open(&test_repo.workdir).unwrap();
+        std::fs::write(test_repo.workdir.join("VERSION"), "1.1.0\n").unwrap();
+
+        repo.commit_files(&[Path::new("VERSION")], "chore(release): 1.1.0")
+            .unwrap();
+        repo.create_tag("v1.1.0", "Release 1.1.0").unwrap();
+        assert!(matches!(
+            repo.create_tag("v1.1.0", "Release 1.1.0"),
+            Err(GitError::TagExists(tag)) if tag == "v1.1.0"
+        ));
+        repo.push_refs(&[
+            "refs/heads/main".to_string(),
+            "refs/tags/v1.1.0".to_string(),
+        ])
+        .unwrap();
+
+        assert_eq!(test_repo.last_commit("main").0, "chore(release): 1.1.0");
+        assert!(repo.is_clean().unwrap());
+        assert_eq!(test_repo.origin_tags(), vec!["v1.1.0"]);
+        assert_eq!(
+            repo.last_tag("HEAD", "v").unwrap(),
+            Some("v1.1.0".to_string())
+        );
+        let origin = git2::Repository::open_bare(&test_repo.origin).unwrap();
+        let origin_main = origin.revparse_single("main").unwrap().id();
+        let local_main = test_repo.repository().revparse_single("main").unwrap().id();
+        assert_eq!(origin_main, local_main);
+    }
+
+    #[test]
+    fn test_push_refs_rejected_when_remote_moved() {
+        let test_repo = TestRepository::new();
+        let repo = CliGitRepository::open(&test_repo.workdir).unwrap();
+        test_repo.commit_on_origin("main", "remote.txt", "Remote change");
+        test_repo.commit("local.txt", "Local change");
+
+        assert!(matches!(
+            repo.push_refs(&["refs/heads/main".to_string()]),
+            Err(GitError::NonFastForward(branch)) if branch == "main"
+        ));
+    }
+
+    #[test]
+    fn test_fast_forward_from_origin() {
+        let test_repo = TestRepository::new();
+        test_repo.commit_on_origin("develop", "remote.txt", "Remote change");
+        let repo = CliGitRepository::open(&test_repo.workdir).unwrap();
+
+        repo.fetch("develop").unwrap();
+        repo.fast_forward("develop").unwrap();
+
+        assert_eq!(test_repo.last_commit("develop").0, "Remote change");
+        assert_eq!(test_repo.head_branch(), "main");
+    }
+
+    #[test]
+    fn test_ahead_behind() {
+        let test_repo = TestRepository::new();
+        test_repo.commit_on_origin("main", "remote.txt", "Remote change");
+        test_repo.commit("local.txt", "Local change");
+        let repo = CliGitRepository::open(&test_repo.workdir).unwrap();
+        repo.fetch("main").unwrap();
+
+        assert_eq!(
+            repo.ahead_behind("main", "origin/main").unwrap(),
+            Some((1, 1))
+        );
+        assert_eq!(repo.ahead_behind("main", "origin/missing").unwrap(), None);
+    }
+
+    #[test]
+    fn test_fast_forward_diverged_branch() {
+        let test_repo = TestRepository::new();
+        test_repo.commit_on_origin("main", "remote.txt", "Remote change");
+        test_repo.commit("local.txt", "Local change");
+        let repo = CliGitRepository::open(&test_repo.workdir).unwrap();
+
+        repo.fetch("main").unwrap();
+        let result = repo.fast_forward("main");
+
+        assert!(
+            matches!(result, Err(GitError::Diverged(b, upstream)) if b == "main" && upstream == "origin/main")
+        );
+    }
+
+    #[test]
+    fn test_squash_into() {
+        let test_repo = TestRepository::new();
+        let repo = CliGitRepository::open(&test_repo.workdir).unwrap();
+        repo.create_and_checkout_branch("feature", "develop")
+            .unwrap();
+        test_repo.commit("feature.txt", "Add feature");
+        test_repo.commit("other.txt", "Add other");
+
+        repo.merge_into(
+            "feature",
+            "develop",
+            MergeStrategy::Squash,
+            "PROJ-1 Feature",
+        )
+        .unwrap();
+
+        assert_eq!(test_repo.head_branch(), "develop");
+        assert_eq!(
+            test_repo.last_commit("develop"),
+            ("PROJ-1 Feature".to_string(), 1)
+        );
+    }
+
+    #[test]
+    fn test_merge_into_already_merged() {
+        let test_repo = TestRepository::new();
+        let repo = CliGitRepository::open(&test_repo.workdir).unwrap();
+        repo.create_and_checkout_branch("feature", "develop")
+            .unwrap();
+        test_repo.commit("feature.txt", "Add feature");
+        repo.merge_into("feature", "develop", MergeStrategy::Merge, "Merge feature")
+            .unwrap();
+        test_repo.checkout("feature");
+
+        repo.merge_into("feature", "develop", MergeStrategy::Merge, "Merge again")
+            .unwrap();
+
+        assert_eq!(test_repo.head_branch(), "develop");
+        assert_eq!(
+            test_repo.last_commit("develop"),
+            ("Merge feature".to_string(), 2)
+        );
+    }
+
+    #[test]
+    fn test_cherry_pick() {
+        let test_repo = TestRepository::new();
+        let repo = CliGitRepository::open(&test_repo.workdir).unwrap();
+        repo.create_and_checkout_branch("feature", "develop")
+            .unwrap();
+        test_repo.commit("a.txt", "Add a");
+        test_repo.commit("b.txt", "Add b");
+        let ids: Vec<String> = repo
+            .commits("develop..feature")
+            .unwrap()
+            .into_iter()
+            .rev()
+            .map(|commit| commit.id)
+            .collect();
+        repo.create_and_checkout_branch("backport", "main").unwrap();
+
+        repo.cherry_pick(&ids).unwrap();
+
+        assert_eq!(test_repo.head_branch(), "backport");
+        assert_eq!(test_repo.last_commit("backport"), ("Add b".to_string(), 1));
+        assert!(test_repo.file_content("a.txt").is_some());
+        let picked = repo.commits("main..backport").unwrap();
+        assert_eq!(picked.len(), 2);
+        assert!(picked[0]
+            .message
+            .ends_with(&format!("(cherry picked from commit {})", ids[1])));
+        assert!(repo.is_clean().unwrap());
+    }
+
+    #[test]
+    fn test_cherry_pick_skips_empty_picks() {
+        let test_repo = TestRepository::new();
+        let repo = CliGitRepository::open(&test_repo.workdir).unwrap();
+        repo.create_and_checkout_branch("feature", "develop")
+            .unwrap();
+        test_repo.commit("a.txt", "Add a");
+        test_repo.commit("b.txt", "Add b");
+        let ids: Vec<String> = repo
+            .commits("develop..feature")
+            .unwrap()
+            .into_iter()
+            .rev()
+            .map(|commit| commit.id)
+            .collect();
+        repo.create_and_checkout_branch("backport", "main").unwrap();
+        test_repo.commit("a.txt", "Add a");
+
+        repo.cherry_pick(&ids).unwrap();
+
+        assert_eq!(test_repo.last_commit("backport"), ("Add b".to_string(), 1));
+        assert_eq!(repo.commits("main..backport").unwrap().len(), 2);
+        assert!(repo.is_clean().unwrap());
+    }
+
+    #[test]
+    fn test_cherry_pick_with_conflicts() {
+        let test_repo = TestRepository::new();
+        let repo = CliGitRepository::open(&test_repo.workdir).unwrap();
+        repo.create_and_checkout_branch("feature", "develop")
+            .unwrap();
+        test_repo.commit("a.txt", "Add a");
+        test_repo.commit("README.md", "Feature readme");
+        let ids: Vec<String> = repo
+            .commits("develop..feature")
+            .unwrap()
+            .into_iter()
+            .rev()
+            .map(|commit| commit.id)
+            .collect();
+        test_repo.checkout("main");
+        test_repo.commit("README.md", "Main readme");
+        repo.create_and_checkout_branch("backport", "main").unwrap();
+
+        let result = repo.cherry_pick(&ids);
+
+        assert!(matches!(
+            result,
+            Err(GitError::CherryPickConflict(id, branch, files))
+                if id == ids[1] && branch == "backport" && files == vec!["README.md"]
+        ));
+        assert_eq!(test_repo.last_commit("backport").0, "Main readme");
+        assert!(test_repo.file_content("a.txt").is_none());
+        assert_eq!(repo.operation_in_progress().unwrap(), None);
+        assert!(repo.is_clean().unwrap());
+    }
+
+    #[test]
+    fn test_merge_into_with_conflicts() {
+        let test_repo = TestRepository::new();
+        let repo = CliGitRepository::open(&test_repo.workdir).unwrap();
+        repo.create_and_checkout_branch("feature", "develop")
+            .unwrap();
+        test_repo.commit("README.md", "Feature readme");
+        test_repo.checkout("develop");
+        test_repo.commit("README.md", "Develop readme");
+        test_repo.checkout("feature");
+
+        let result = repo.merge_into("feature", "develop", MergeStrategy::Merge, "Merge");
+
+        assert!(matches!(
+            result,
+            Err(GitError::MergeConflict(_, _, files)) if files == vec!["README.md"]
+        ));
+        assert_eq!(test_repo.head_branch(), "feature");
+        assert!(repo.is_clean().unwrap());
+    }
+}
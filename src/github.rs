@@ -0,0 +1,490 @@
+use async_trait::async_trait;
+use reqwest::{Client, RequestBuilder, Response, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use url::ParseError;
+
+use crate::config::GitHubConfig;
+use crate::hosting::{
+    HostingError, HostingProvider, NewPullRequest, PullRequest, RemoteRepository,
+};
+use crate::prompt::InquirePrompter;
+use crate::secret::Secret;
+use crate::tracker::{issue_number_from_branch, open_or_closed, Issue, IssueTracker, TrackerError};
+
+const SEARCH_PAGE_SIZE: usize = 50;
+
+pub struct GitHubServer {
+    api_url: Url,
+    token: Secret,
+}
+
+/// GitHub Issues of a given repository
+pub struct GitHubIssueTracker {
+    server: GitHubServer,
+    repository: RemoteRepository,
+}
+
+#[derive(Debug, Serialize)]
+struct GitHubRestNewPull<'a> {
+    title: &'a str,
+    body: &'a str,
+    head: &'a str,
+    base: &'a str,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubRestPull {
+    number: u64,
+    html_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubRestIssue {
+    number: u64,
+    title: String,
+    state: String,
+    html_url: String,
+    #[serde(default)]
+    labels: Vec<GitHubRestLabel>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubRestLabel {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubRestSearch {
+    total_count: usize,
+    items: Vec<GitHubRestIssue>,
+}
+
+#[derive(Debug, Serialize)]
+struct GitHubRestIssueState<'a> {
+    state: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+struct GitHubRestComment<'a> {
+    body: &'a str,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubRestError {
+    message: String,
+    #[serde(default)]
+    errors: Vec<GitHubRestErrorDetail>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubRestErrorDetail {
+    message: Option<String>,
+}
+
+impl GitHubServer {
+    /// Server from the configuration, the prompter asks for the passphrase of an encrypted token
+    pub fn from_config(
+        config: &GitHubConfig,
+        prompter: InquirePrompter,
+    ) -> Result<Self, HostingError> {
+        let api_url = Url::parse(&config.api_url)?;
+        Ok(GitHubServer {
+            api_url,
+            token: config.token.resolve(prompter)?,
+        })
+    }
+
+    /// Build an url relative to the api url, keeping any path prefix (GitHub Enterprise uses `/api/v3`)
+    fn endpoint(&self, path: &str) -> Result<Url, ParseError> {
+        let base = self.api_url.as_str().trim_end_matches('/');
+        Url::parse(&format!("{}/{}", base, path))
+    }
+
+    fn authenticated(&self, request: RequestBuilder) -> RequestBuilder {
+        request
+            .bearer_auth(self.token.expose())
+            .header("Accept", "application/vnd.github+json")
+            .header("User-Agent", env!("CARGO_PKG_NAME"))
+    }
+}
+
+#[async_trait]
+impl HostingProvider for GitHubServer {
+    async fn create_pull_request(
+        &self,
+        repository: &RemoteRepository,
+        pull_request: &NewPullRequest,
+    ) -> Result<PullRequest, HostingError> {
+        let url = self.endpoint(&format!(
+            "repos/{}/{}/pulls",
+            repository.owner, repository.name
+        ))?;
+
+        let client = Client::builder().build()?;
+
+        let response = self
+            .authenticated(client.post(url))
+            .json(&GitHubRestNewPull {
+                title: &pull_request.title,
+                body: &pull_request.body,
+                head: &pull_request.head,
+                base: &pull_request.base,
+            })
+            .send()
+            .await?;
+
+        match response.status() {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Err(HostingError::Unauthorized),
+            StatusCode::UNPROCESSABLE_ENTITY => {
+                let error = response.json::<GitHubRestError>().await?;
+                let details: Vec<String> =
+                    error.errors.into_iter().filter_map(|e| e.message).collect();
+                let message = if details.is_empty() {
+                    error.message
+                } else {
+                    format!("{} ({})", error.message, details.join(", "))
+                };
+                Err(HostingError::PullRequestRejected(message))
+            }
+            _ => {
+                let rest_pull = response
+                    .error_for_status()?
+                    .json::<GitHubRestPull>()
+                    .await?;
+                Ok(PullRequest {
+                    number: rest_pull.number,
+                    url: rest_pull.html_url,
+                })
+            }
+        }
+    }
+}
+
+impl From<GitHubRestIssue> for Issue {
+    fn from(rest_issue: GitHubRestIssue) -> Self {
+        Issue {
+            key: rest_issue.number.to_string(),
+            summary: rest_issue.title,
+            status: rest_issue.state,
+            issue_type: rest_issue.labels.into_iter().next().map(|l| l.name),
+            url: rest_issue.html_url,
+        }
+    }
+}
+
+impl GitHubIssueTracker {
+    pub fn new(server: GitHubServer, repository: RemoteRepository) -> Self {
+        Self { server, repository }
+    }
+
+    fn issue_endpoint(&self, key: &str, suffix: &str) -> Result<Url, ParseError> {
+        self.server.endpoint(&format!(
+            "repos/{}/{}/issues/{}{}",
+            self.repository.owner, self.repository.name, key, suffix
+        ))
+    }
+
+    fn check(response: Response, key: &str) -> Result<Response, TrackerError> {
+        match response.status() {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Err(TrackerError::Unauthorized),
+            StatusCode::NOT_FOUND => Err(TrackerError::IssueNotFound(key.to_string())),
+            _ => Ok(response.error_for_status()?),
+        }
+    }
+}
+
+#[async_trait]
+impl IssueTracker for GitHubIssueTracker {
+    async fn get_issue(&self, key: &str) -> Result<Issue, TrackerError> {
+        let key = key.trim_start_matches('#');
+        let url = self.issue_endpoint(key, "")?;
+
+        let client = Client::builder().build()?;
+
+        let response = self.server.authenticated(client.get(url)).send().await?;
+
+        let rest_issue = Self::check(response, key)?
+            .json::<GitHubRestIssue>()
+            .await?;
+
+        Ok(rest_issue.into())
+    }
+
+    async fn search_issues(&self, query: &str) -> Result<Vec<Issue>, TrackerError> {
+        let url = self.server.endpoint("search/issues")?;
+        let query = format!(
+            "repo:{}/{} is:issue {}",
+            self.repository.owner, self.repository.name, query
+        );
+        let per_page = SEARCH_PAGE_SIZE.to_string();
+
+        let client = Client::builder().build()?;
+
+        let mut issues: Vec<Issue> = Vec::new();
+        for page in 1.. {
+            let response = self
+                .server
+                .authenticated(client.get(url.clone()))
+                .query(&[
+                    ("q", query.as_str()),
+                    ("per_page", &per_page),
+                    ("page", &page.to_string()),
+                ])
+                .send()
+                .await?;
+
+            let result = Self::check(response, &query)?
+                .json::<GitHubRestSearch>()
+                .await?;
+
+            let page_len = result.items.len();
+            issues.extend(result.items.into_iter().map(Issue::from));
+
+            if page_len < SEARCH_PAGE_SIZE || issues.len() >= result.total_count {
+                break;
+            }
+        }
+
+        Ok(issues)
+    }
+
+    async fn transition_to_status(
+        &self,
+        issue: &Issue,
+        status: &str,
+    ) -> Result<bool, TrackerError> {
+        let open = open_or_closed(status).ok_or_else(|| {
+            TrackerError::TransitionNotFound(
+                issue.key.clone(),
+                status.to_string(),
+                "open, closed".to_string(),
+            )
+        })?;
+
+        if open_or_closed(&issue.status) == Some(open) {
+            return Ok(false);
+        }
+
+        let url = self.issue_endpoint(&issue.key, "")?;
+
+        let client = Client::builder().build()?;
+
+        let response = self
+            .server
+            .authenticated(client.patch(url))
+            .json(&GitHubRestIssueState {
+                state: if open { "open" } else { "closed" },
+            })
+            .send()
+            .await?;
+
+        Self::check(response, &issue.key)?;
+        Ok(true)
+    }
+
+    async fn add_comment(&self, issue: &Issue, body: &str) -> Result<(), TrackerError> {
+        let url = self.issue_endpoint(&issue.key, "/comments")?;
+
+        let client = Client::builder().build()?;
+
+        let response = self
+            .server
+            .authenticated(client.post(url))
+            .json(&GitHubRestComment { body })
+            .send()
+            .await?;
+
+        Self::check(response, &issue.key)?;
+        Ok(())
+    }
+
+    fn default_query(&self) -> &str {
+        "is:open assignee:@me"
+    }
+
+    fn find_issue_key(&self, text: &str) -> Option<String> {
+        issue_number_from_branch(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mockito::Matcher;
+
+    use super::*;
+    use crate::secret::SecretRef;
+
+    fn server(api_url: &str) -> GitHubServer {
+        GitHubServer::from_config(
+            &GitHubConfig {
+                api_url: api_url.to_string(),
+                token: SecretRef::Plain("secret".to_string()),
+            },
+            InquirePrompter::new(false),
+        )
+        .unwrap()
+    }
+
+    fn remote() -> RemoteRepository {
+        RemoteRepository {
+            owner: "owner".to_string(),
+            name: "repo".to_string(),
+        }
+    }
+
+    fn new_pull() -> NewPullRequest {
+        NewPullRequest {
+            title: "PROJ-1 Some title".to_string(),
+            body: "".to_string(),
+            head: "PROJ-1-some-title".to_string(),
+            base: "develop".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_pull_request() {
+        let mut mock_server = mockito::Server::new_async().await;
+        let mock = mock_server
+            .mock("POST", "/api/v3/repos/owner/repo/pulls")
+            .match_header("authorization", "Bearer secret")
+            .match_body(Matcher::PartialJsonString(
+                r#"{"title": "PROJ-1 Some title", "head": "PROJ-1-some-title", "base": "develop"}"#
+                    .to_string(),
+            ))
+            .with_status(201)
+            .with_body(r#"{"number": 42, "html_url": "https://github.com/owner/repo/pull/42"}"#)
+            .create_async()
+            .await;
+
+        let github = server(&format!("{}/api/v3/", mock_server.url()));
+        let result = github
+            .create_pull_request(&remote(), &new_pull())
+            .await
+            .unwrap();
+
+        mock.assert_async().await;
+        assert_eq!(result.number, 42);
+        assert_eq!(result.url, "https://github.com/owner/repo/pull/42");
+    }
+
+    #[tokio::test]
+    async fn test_create_pull_request_rejected() {
+        let mut mock_server = mockito::Server::new_async().await;
+        mock_server
+            .mock("POST", "/repos/owner/repo/pulls")
+            .with_status(422)
+            .with_body(r#"{"message": "Validation Failed", "errors": [{"message": "A pull request already exists"}]}"#)
+            .create_async()
+            .await;
+
+        let github = server(&mock_server.url());
+        let result = github.create_pull_request(&remote(), &new_pull()).await;
+
+        match result {
+            Err(HostingError::PullRequestRejected(message)) => {
+                assert_eq!(message, "Validation Failed (A pull request already exists)")
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    fn tracker(api_url: &str) -> GitHubIssueTracker {
+        GitHubIssueTracker::new(server(api_url), remote())
+    }
+
+    const ISSUE: &str = r#"{"number": 12, "title": "Fix the bug", "state": "open", "html_url": "https://github.com/owner/repo/issues/12"}"#;
+
+    #[tokio::test]
+    async fn test_get_issue() {
+        let mut mock_server = mockito::Server::new_async().await;
+        mock_server
+            .mock("GET", "/repos/owner/repo/issues/12")
+            .with_body(ISSUE)
+            .create_async()
+            .await;
+
+        let issue = tracker(&mock_server.url()).get_issue("#12").await.unwrap();
+
+        assert_eq!(issue.key, "12");
+        assert_eq!(issue.summary, "Fix the bug");
+        assert_eq!(issue.url, "https://github.com/owner/repo/issues/12");
+    }
+
+    #[tokio::test]
+    async fn test_get_missing_issue() {
+        let mut mock_server = mockito::Server::new_async().await;
+        mock_server
+            .mock("GET", "/repos/owner/repo/issues/13")
+            .with_status(404)
+            .create_async()
+            .await;
+
+        let result = tracker(&mock_server.url()).get_issue("13").await;
+
+        assert!(matches!(result, Err(TrackerError::IssueNotFound(key)) if key == "13"));
+    }
+
+    #[tokio::test]
+    async fn test_search_issues() {
+        let mut mock_server = mockito::Server::new_async().await;
+        mock_server
+            .mock("GET", "/search/issues")
+            .match_query(Matcher::UrlEncoded(
+                "q".to_string(),
+                "repo:owner/repo is:issue is:open".to_string(),
+            ))
+            .with_body(format!(r#"{{"total_count": 1, "items": [{}]}}"#, ISSUE))
+            .create_async()
+            .await;
+
+        let issues = tracker(&mock_server.url())
+            .search_issues("is:open")
+            .await
+            .unwrap();
+
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].to_string(), "12 Fix the bug [open]");
+    }
+
+    #[tokio::test]
+    async fn test_close_issue() {
+        let mut mock_server = mockito::Server::new_async().await;
+        let mock = mock_server
+            .mock("PATCH", "/repos/owner/repo/issues/12")
+            .match_body(Matcher::JsonString(r#"{"state": "closed"}"#.to_string()))
+            .with_body(ISSUE)
+            .create_async()
+            .await;
+
+        let tracker = tracker(&mock_server.url());
+        let issue = Issue {
+            key: "12".to_string(),
+            summary: "Fix the bug".to_string(),
+            status: "open".to_string(),
+            issue_type: None,
+            url: "".to_string(),
+        };
+
+        assert!(tracker.transition_to_status(&issue, "Done").await.unwrap());
+        mock.assert_async().await;
+        assert!(matches!(
+            tracker.transition_to_status(&issue, "In Review").await,
+            Err(TrackerError::TransitionNotFound(_, _, _))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_create_pull_request_unauthorized() {
+        let mut mock_server = mockito::Server::new_async().await;
+        mock_server
+            .mock("POST", "/repos/owner/repo/pulls")
+            .with_status(401)
+            .create_async()
+            .await;
+
+        let github = server(&mock_server.url());
+        let result = github.create_pull_request(&remote(), &new_pull()).await;
+
+        assert!(matches!(result, Err(HostingError::Unauthorized)));
+    }
+}
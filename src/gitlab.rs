@@ -0,0 +1,290 @@
+use async_trait::async_trait;
+use reqwest::{Client, RequestBuilder, Response, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use url::ParseError;
+
+use crate::config::GitLabConfig;
+use crate::hosting::RemoteRepository;
+use crate::prompt::InquirePrompter;
+use crate::secret::Secret;
+use crate::tracker::{issue_number_from_branch, open_or_closed, Issue, IssueTracker, TrackerError};
+
+const SEARCH_PAGE_SIZE: usize = 50;
+
+/// GitLab Issues of a given project
+pub struct GitLabIssueTracker {
+    api_url: Url,
+    token: Secret,
+    project: RemoteRepository,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitLabRestIssue {
+    iid: u64,
+    title: String,
+    state: String,
+    web_url: String,
+    #[serde(default)]
+    labels: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct GitLabRestStateEvent<'a> {
+    state_event: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+struct GitLabRestNote<'a> {
+    body: &'a str,
+}
+
+impl From<GitLabRestIssue> for Issue {
+    fn from(rest_issue: GitLabRestIssue) -> Self {
+        Issue {
+            key: rest_issue.iid.to_string(),
+            summary: rest_issue.title,
+            status: rest_issue.state,
+            issue_type: rest_issue.labels.into_iter().next(),
+            url: rest_issue.web_url,
+        }
+    }
+}
+
+impl GitLabIssueTracker {
+    pub fn new(
+        config: &GitLabConfig,
+        project: RemoteRepository,
+        prompter: InquirePrompter,
+    ) -> Result<Self, TrackerError> {
+        Ok(Self {
+            api_url: Url::parse(&config.api_url)?,
+            token: config.token.resolve(prompter)?,
+            project,
+        })
+    }
+
+    /// Build an url for the project issues, the project is referenced by its url-encoded path
+    fn issues_endpoint(&self, suffix: &str) -> Result<Url, ParseError> {
+        let base = self.api_url.as_str().trim_end_matches('/');
+        let project = format!("{}/{}", self.project.owner, self.project.name).replace('/', "%2F");
+        Url::parse(&format!("{}/projects/{}/issues{}", base, project, suffix))
+    }
+
+    fn authenticated(&self, request: RequestBuilder) -> RequestBuilder {
+        request.header("PRIVATE-TOKEN", self.token.expose())
+    }
+
+    fn check(response: Response, key: &str) -> Result<Response, TrackerError> {
+        match response.status() {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Err(TrackerError::Unauthorized),
+            StatusCode::NOT_FOUND => Err(TrackerError::IssueNotFound(key.to_string())),
+            _ => Ok(response.error_for_status()?),
+        }
+    }
+}
+
+#[async_trait]
+impl IssueTracker for GitLabIssueTracker {
+    async fn get_issue(&self, key: &str) -> Result<Issue, TrackerError> {
+        let key = key.trim_start_matches('#');
+        let url = self.issues_endpoint(&format!("/{}", key))?;
+
+        let client = Client::builder().build()?;
+
+        let response = self.authenticated(client.get(url)).send().await?;
+
+        let rest_issue = Self::check(response, key)?
+            .json::<GitLabRestIssue>()
+            .await?;
+
+        Ok(rest_issue.into())
+    }
+
+    /// The query is a list of url parameters for the GitLab issues api, e.g. `state=opened&labels=bug`
+    async fn search_issues(&self, query: &str) -> Result<Vec<Issue>, TrackerError> {
+        let url = self.issues_endpoint("")?;
+        let parameters: Vec<(String, String)> = url::form_urlencoded::parse(query.as_bytes())
+            .into_owned()
+            .collect();
+        let per_page = SEARCH_PAGE_SIZE.to_string();
+
+        let client = Client::builder().build()?;
+
+        let mut issues: Vec<Issue> = Vec::new();
+        for page in 1.. {
+            let response = self
+                .authenticated(client.get(url.clone()))
+                .query(&parameters)
+                .query(&[("per_page", &per_page), ("page", &page.to_string())])
+                .send()
+                .await?;
+
+            let result = Self::check(response, query)?
+                .json::<Vec<GitLabRestIssue>>()
+                .await?;
+
+            let page_len = result.len();
+            issues.extend(result.into_iter().map(Issue::from));
+
+            if page_len < SEARCH_PAGE_SIZE {
+                break;
+            }
+        }
+
+        Ok(issues)
+    }
+
+    async fn transition_to_status(
+        &self,
+        issue: &Issue,
+        status: &str,
+    ) -> Result<bool, TrackerError> {
+        let open = open_or_closed(status).ok_or_else(|| {
+            TrackerError::TransitionNotFound(
+                issue.key.clone(),
+                status.to_string(),
+                "opened, closed".to_string(),
+            )
+        })?;
+
+        if open_or_closed(&issue.status) == Some(open) {
+            return Ok(false);
+        }
+
+        let url = self.issues_endpoint(&format!("/{}", issue.key))?;
+
+        let client = Client::builder().build()?;
+
+        let response = self
+            .authenticated(client.put(url))
+            .json(&GitLabRestStateEvent {
+                state_event: if open { "reopen" } else { "close" },
+            })
+            .send()
+            .await?;
+
+        Self::check(response, &issue.key)?;
+        Ok(true)
+    }
+
+    async fn add_comment(&self, issue: &Issue, body: &str) -> Result<(), TrackerError> {
+        let url = self.issues_endpoint(&format!("/{}/notes", issue.key))?;
+
+        let client = Client::builder().build()?;
+
+        let response = self
+            .authenticated(client.post(url))
+            .json(&GitLabRestNote { body })
+            .send()
+            .await?;
+
+        Self::check(response, &issue.key)?;
+        Ok(())
+    }
+
+    fn default_query(&self) -> &str {
+        "state=opened&scope=assigned_to_me"
+    }
+
+    fn find_issue_key(&self, text: &str) -> Option<String> {
+        issue_number_from_branch(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mockito::Matcher;
+
+    use super::*;
+    use crate::secret::SecretRef;
+
+    fn tracker(api_url: &str) -> GitLabIssueTracker {
+        GitLabIssueTracker::new(
+            &GitLabConfig {
+                api_url: api_url.to_string(),
+                token: SecretRef::Plain("secret".to_string()),
+            },
+            RemoteRepository {
+                owner: "group/sub".to_string(),
+                name: "project".to_string(),
+            },
+            InquirePrompter::new(false),
+        )
+        .unwrap()
+    }
+
+    const ISSUE: &str = r#"{"id": 1234, "iid": 3, "title": "Fix the bug", "state": "opened", "web_url": "https://gitlab.com/group/sub/project/-/issues/3"}"#;
+
+    #[tokio::test]
+    async fn test_get_issue() {
+        let mut mock_server = mockito::Server::new_async().await;
+        mock_server
+            .mock("GET", "/api/v4/projects/group%2Fsub%2Fproject/issues/3")
+            .match_header("private-token", "secret")
+            .with_body(ISSUE)
+            .create_async()
+            .await;
+
+        let issue = tracker(&format!("{}/api/v4", mock_server.url()))
+            .get_issue("3")
+            .await
+            .unwrap();
+
+        assert_eq!(issue.key, "3");
+        assert_eq!(issue.status, "opened");
+        assert_eq!(issue.url, "https://gitlab.com/group/sub/project/-/issues/3");
+    }
+
+    #[tokio::test]
+    async fn test_search_issues() {
+        let mut mock_server = mockito::Server::new_async().await;
+        mock_server
+            .mock("GET", "/projects/group%2Fsub%2Fproject/issues")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("state".to_string(), "opened".to_string()),
+                Matcher::UrlEncoded("scope".to_string(), "assigned_to_me".to_string()),
+                Matcher::UrlEncoded("page".to_string(), "1".to_string()),
+            ]))
+            .with_body(format!("[{}]", ISSUE))
+            .create_async()
+            .await;
+
+        let tracker = tracker(&mock_server.url());
+        let issues = tracker
+            .search_issues(tracker.default_query())
+            .await
+            .unwrap();
+
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].summary, "Fix the bug");
+    }
+
+    #[tokio::test]
+    async fn test_close_issue() {
+        let mut mock_server = mockito::Server::new_async().await;
+        let mock = mock_server
+            .mock("PUT", "/projects/group%2Fsub%2Fproject/issues/3")
+            .match_body(Matcher::JsonString(
+                r#"{"state_event": "close"}"#.to_string(),
+            ))
+            .with_body(ISSUE)
+            .create_async()
+            .await;
+
+        let issue = Issue {
+            key: "3".to_string(),
+            summary: "Fix the bug".to_string(),
+            status: "opened".to_string(),
+            issue_type: None,
+            url: "".to_string(),
+        };
+
+        let result = tracker(&mock_server.url())
+            .transition_to_status(&issue, "closed")
+            .await
+            .unwrap();
+
+        mock.assert_async().await;
+        assert!(result);
+    }
+}
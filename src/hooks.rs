@@ -0,0 +1,348 @@
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
+use regex::Regex;
+use thiserror::Error;
+
+use crate::config::{CommitMessageConfig, ConventionalCommitsConfig};
+use crate::conventional::{self, ConventionalError};
+
+#[derive(Debug, Error)]
+pub enum HookError {
+    #[error("Cannot install hook {0}: {1}")]
+    CannotInstall(String, io::Error),
+    #[error("Cannot install hook {0}, {1} already exists")]
+    PreviousHookExists(String, String),
+    #[error("Cannot update commit message {0}: {1}")]
+    CannotUpdateMessage(String, io::Error),
+    #[error("The commit message must contain the issue key {0}, please add it or commit with --no-verify")]
+    MissingIssueKey(String),
+    #[error("{0}, please fix the message or commit with --no-verify")]
+    NotConventional(#[from] ConventionalError),
+}
+
+/// Git hooks managed by wf
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Hook {
+    /// Add the issue key to the message before the editor opens
+    PrepareCommitMsg,
+    /// Check the final message contains the issue key
+    CommitMsg,
+}
+
+impl Hook {
+    pub const ALL: [Hook; 2] = [Hook::PrepareCommitMsg, Hook::CommitMsg];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Hook::PrepareCommitMsg => "prepare-commit-msg",
+            Hook::CommitMsg => "commit-msg",
+        }
+    }
+}
+
+/// What `install_hook` did
+#[derive(Debug, PartialEq, Eq)]
+pub enum Installed {
+    Created,
+    /// A hook installed by wf was replaced
+    Updated,
+    /// Another hook was there, it was renamed to this path and is run first
+    Chained(PathBuf),
+}
+
+/// Template used when none is configured
+pub const DEFAULT_TEMPLATE: &str = "{key} {message}";
+
+/// Template used when none is configured and commits follow Conventional Commits, the type
+/// must stay first
+pub const CONVENTIONAL_TEMPLATE: &str = "{message} ({key})";
+
+/// Marks the hooks written by wf, other hooks are never overwritten
+const MARKER: &str = "# Installed by wf";
+
+/// Write a hook calling `executable` in `hooks_dir`.
+///
+/// An existing hook not written by wf is renamed with a `.pre-wf` suffix and run before wf.
+pub fn install_hook(
+    hooks_dir: &Path,
+    hook: Hook,
+    executable: &Path,
+) -> Result<Installed, HookError> {
+    let path = hooks_dir.join(hook.name());
+    let previous = hooks_dir.join(format!("{}.pre-wf", hook.name()));
+    let cannot_install = |e| HookError::CannotInstall(path.display().to_string(), e);
+
+    fs::create_dir_all(hooks_dir).map_err(cannot_install)?;
+
+    let installed = match fs::read_to_string(&path) {
+        Ok(content) if content.contains(MARKER) => Installed::Updated,
+        Ok(_) if previous.exists() => {
+            return Err(HookError::PreviousHookExists(
+                path.display().to_string(),
+                previous.display().to_string(),
+            ))
+        }
+        Ok(_) => {
+            fs::rename(&path, &previous).map_err(cannot_install)?;
+            Installed::Chained(previous)
+        }
+        Err(e) if e.kind() == ErrorKind::NotFound => Installed::Created,
+        Err(e) => return Err(cannot_install(e)),
+    };
+
+    fs::write(&path, hook_script(hook, executable)).map_err(cannot_install)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).map_err(cannot_install)?;
+    }
+
+    Ok(installed)
+}
+
+fn hook_script(hook: Hook, executable: &Path) -> String {
+    format!(
+        r#"#!/bin/sh
+{marker}, a hook that was already there is kept as {name}.pre-wf and run first
+previous="$(dirname "$0")/{name}.pre-wf"
+if [ -x "$previous" ]; then
+    "$previous" "$@" || exit $?
+fi
+if [ ! -x "{executable}" ]; then
+    echo "wf not found at {executable}, skipping the {name} hook" >&2
+    exit 0
+fi
+exec "{executable}" hooks run {name} "$@"
+"#,
+        marker = MARKER,
+        name = hook.name(),
+        executable = executable.display()
+    )
+}
+
+/// Run by `prepare-commit-msg`: add the issue key to the message in `file`.
+///
+/// Messages of merges, squashes and amended or reused commits are left as they are.
+pub fn prepare_commit_msg(
+    file: &Path,
+    source: Option<&str>,
+    key: &str,
+    config: &CommitMessageConfig,
+) -> Result<(), HookError> {
+    if matches!(source, Some("merge" | "squash" | "commit")) {
+        return Ok(());
+    }
+
+    let cannot_update = |e| HookError::CannotUpdateMessage(file.display().to_string(), e);
+    let message = fs::read_to_string(file).map_err(cannot_update)?;
+    let updated = add_issue_key(&message, key, config.template.as_deref());
+    if updated != message {
+        fs::write(file, updated).map_err(cannot_update)?;
+    }
+    Ok(())
+}
+
+/// Run by `commit-msg`: refuse a message in `file` without the issue key when it is required
+pub fn check_commit_msg(
+    file: &Path,
+    key: &str,
+    config: &CommitMessageConfig,
+) -> Result<(), HookError> {
+    if !config.require_key {
+        return Ok(());
+    }
+
+    let message = read_message(file)?;
+    if is_generated(&message) {
+        return Ok(());
+    }
+
+    match contains_issue_key(&message, key) {
+        true => Ok(()),
+        false => Err(HookError::MissingIssueKey(key.to_string())),
+    }
+}
+
+/// Run by `commit-msg`: refuse a message in `file` not following Conventional Commits
+pub fn lint_commit_msg(file: &Path, config: &ConventionalCommitsConfig) -> Result<(), HookError> {
+    let message = read_message(file)?;
+    if is_generated(&message) {
+        return Ok(());
+    }
+
+    let message: Vec<&str> = message.lines().filter(|line| !is_comment(line)).collect();
+    conventional::lint(&message.join("\n"), config)?;
+    Ok(())
+}
+
+fn read_message(file: &Path) -> Result<String, HookError> {
+    fs::read_to_string(file)
+        .map_err(|e| HookError::CannotUpdateMessage(file.display().to_string(), e))
+}
+
+/// Messages written by git for merges, reverts and commits to squash later
+pub fn is_generated(message: &str) -> bool {
+    let generated = ["Merge ", "Revert ", "fixup! ", "squash! ", "amend! "];
+    generated.iter().any(|prefix| message.starts_with(prefix))
+}
+
+/// Render the subject line with the template unless the message already has the issue key.
+///
+/// The body and the comments added by git are kept as they are.
+pub fn add_issue_key(message: &str, key: &str, template: Option<&str>) -> String {
+    if contains_issue_key(message, key) {
+        return message.to_string();
+    }
+
+    let (subject, rest) = match message.split_once('\n') {
+        Some((subject, _)) if is_comment(subject) => ("", message),
+        Some((subject, rest)) => (subject, rest),
+        None => (message, ""),
+    };
+
+    let subject = template
+        .unwrap_or(DEFAULT_TEMPLATE)
+        .replace("{key}", key)
+        .replace("{message}", subject);
+    format!("{}\n{}", subject.trim_end(), rest)
+}
+
+/// Whether the message, comments aside, mentions the key as a whole word
+pub fn contains_issue_key(message: &str, key: &str) -> bool {
+    let pattern = format!(r"(^|[^A-Za-z0-9]){}($|[^0-9])", regex::escape(key));
+    let regex = match Regex::new(&pattern) {
+        Ok(regex) => regex,
+        Err(_) => return false,
+    };
+
+    message
+        .lines()
+        .filter(|line| !is_comment(line))
+        .any(|line| regex.is_match(line))
+}
+
+/// Lines git adds to the message as help, `#123` alone is an issue reference
+fn is_comment(line: &str) -> bool {
+    line == "#" || line.starts_with("# ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_issue_key_to_subject() {
+        assert_eq!(
+            add_issue_key("Fix the login\n\nDetails\n", "PROJ-1", None),
+            "PROJ-1 Fix the login\n\nDetails\n"
+        );
+        assert_eq!(
+            add_issue_key("Fix the login", "PROJ-1", Some("[{key}] {message}")),
+            "[PROJ-1] Fix the login\n"
+        );
+    }
+
+    #[test]
+    fn test_add_issue_key_to_empty_message() {
+        let message = "\n# Please enter the commit message for your changes.\n";
+        assert_eq!(
+            add_issue_key(message, "PROJ-1", None),
+            "PROJ-1\n# Please enter the commit message for your changes.\n"
+        );
+        assert_eq!(
+            add_issue_key("# Comment only\n", "PROJ-1", None),
+            "PROJ-1\n# Comment only\n"
+        );
+    }
+
+    #[test]
+    fn test_add_issue_key_once() {
+        let message = "Fix the login\n\nRefs PROJ-1\n";
+        assert_eq!(add_issue_key(message, "PROJ-1", None), message);
+        assert_eq!(
+            add_issue_key("PROJ-12 Fix the login", "PROJ-1", None),
+            "PROJ-1 PROJ-12 Fix the login\n"
+        );
+    }
+
+    #[test]
+    fn test_contains_issue_key_ignores_comments() {
+        assert!(contains_issue_key("#123 Fix the login", "#123"));
+        assert!(!contains_issue_key(
+            "Fix\n# On branch PROJ-1-fix\n",
+            "PROJ-1"
+        ));
+        assert!(!contains_issue_key("XPROJ-1 Fix", "PROJ-1"));
+    }
+
+    #[test]
+    fn test_check_commit_msg() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("COMMIT_EDITMSG");
+        let config = CommitMessageConfig {
+            template: None,
+            require_key: true,
+        };
+
+        fs::write(&file, "Fix the login\n").unwrap();
+        assert!(matches!(
+            check_commit_msg(&file, "PROJ-1", &config),
+            Err(HookError::MissingIssueKey(key)) if key == "PROJ-1"
+        ));
+        assert!(check_commit_msg(&file, "PROJ-1", &CommitMessageConfig::default()).is_ok());
+
+        fs::write(&file, "Merge branch 'develop' into feature\n").unwrap();
+        assert!(check_commit_msg(&file, "PROJ-1", &config).is_ok());
+    }
+
+    #[test]
+    fn test_lint_commit_msg() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("COMMIT_EDITMSG");
+        let config = ConventionalCommitsConfig::default();
+
+        fs::write(&file, "feat: add SSO\n# Please enter the commit message\n").unwrap();
+        assert!(lint_commit_msg(&file, &config).is_ok());
+
+        fs::write(&file, "Add SSO\n").unwrap();
+        assert!(matches!(
+            lint_commit_msg(&file, &config),
+            Err(HookError::NotConventional(
+                ConventionalError::InvalidSubject(_)
+            ))
+        ));
+
+        fs::write(&file, "fixup! feat: add SSO\n").unwrap();
+        assert!(lint_commit_msg(&file, &config).is_ok());
+    }
+
+    #[test]
+    fn test_install_keeps_existing_hook() {
+        let dir = tempfile::tempdir().unwrap();
+        let hooks_dir = dir.path().join("hooks");
+        fs::create_dir(&hooks_dir).unwrap();
+        fs::write(hooks_dir.join("commit-msg"), "#!/bin/sh\nexit 0\n").unwrap();
+        let executable = Path::new("/usr/local/bin/wf");
+
+        let installed = install_hook(&hooks_dir, Hook::CommitMsg, executable).unwrap();
+        assert_eq!(
+            installed,
+            Installed::Chained(hooks_dir.join("commit-msg.pre-wf"))
+        );
+        assert_eq!(
+            fs::read_to_string(hooks_dir.join("commit-msg.pre-wf")).unwrap(),
+            "#!/bin/sh\nexit 0\n"
+        );
+        let script = fs::read_to_string(hooks_dir.join("commit-msg")).unwrap();
+        assert!(script.contains("exec \"/usr/local/bin/wf\" hooks run commit-msg \"$@\""));
+
+        let installed = install_hook(&hooks_dir, Hook::CommitMsg, executable).unwrap();
+        assert_eq!(installed, Installed::Updated);
+
+        let installed = install_hook(&hooks_dir, Hook::PrepareCommitMsg, executable).unwrap();
+        assert_eq!(installed, Installed::Created);
+    }
+}
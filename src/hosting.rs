@@ -0,0 +1,127 @@
+use async_trait::async_trait;
+use reqwest::Error as ReqwestError;
+use thiserror::Error;
+use url::{ParseError, Url};
+
+use crate::secret::SecretError;
+
+#[derive(Debug)]
+pub struct NewPullRequest {
+    pub title: String,
+    pub body: String,
+    pub head: String,
+    pub base: String,
+}
+
+#[derive(Debug)]
+pub struct PullRequest {
+    pub number: u64,
+    pub url: String,
+}
+
+/// Repository as known by the hosting provider, i.e. `owner/name`
+#[derive(Debug, PartialEq, Eq)]
+pub struct RemoteRepository {
+    pub owner: String,
+    pub name: String,
+}
+
+#[derive(Debug, Error)]
+pub enum HostingError {
+    #[error("{0}")]
+    RequestError(#[from] ReqwestError),
+    #[error("Invalid server url {0}")]
+    InvalidUrl(#[from] ParseError),
+    #[error("Cannot find repository owner and name in remote url {0}")]
+    UnsupportedRemoteUrl(String),
+    #[error("Authentication refused by hosting provider, please check your token")]
+    Unauthorized,
+    #[error("Pull request refused: {0}")]
+    PullRequestRejected(String),
+    #[error("Cannot read token: {0}")]
+    SecretError(#[from] SecretError),
+}
+
+#[async_trait]
+pub trait HostingProvider {
+    async fn create_pull_request(
+        &self,
+        repository: &RemoteRepository,
+        pull_request: &NewPullRequest,
+    ) -> Result<PullRequest, HostingError>;
+}
+
+impl RemoteRepository {
+    /// Extract owner and name from a remote url.
+    ///
+    /// Supports http(s) and ssh urls (`https://host/owner/name.git`, `ssh://git@host/owner/name.git`)
+    /// as well as the scp-like syntax (`git@host:owner/name.git`).
+    pub fn from_url(remote_url: &str) -> Result<Self, HostingError> {
+        let unsupported = || HostingError::UnsupportedRemoteUrl(remote_url.to_string());
+
+        let path = if remote_url.contains("://") {
+            Url::parse(remote_url)
+                .map_err(|_| unsupported())?
+                .path()
+                .to_string()
+        } else {
+            remote_url
+                .split_once(':')
+                .map(|(_, path)| path.to_string())
+                .ok_or_else(unsupported)?
+        };
+
+        let path = path.trim_matches('/');
+        let path = path.strip_suffix(".git").unwrap_or(path);
+
+        match path.rsplit_once('/') {
+            Some((owner, name)) if !owner.is_empty() && !name.is_empty() => Ok(Self {
+                owner: owner.to_string(),
+                name: name.to_string(),
+            }),
+            _ => Err(unsupported()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn remote(owner: &str, name: &str) -> RemoteRepository {
+        RemoteRepository {
+            owner: owner.to_string(),
+            name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_remote_from_scp_url() {
+        let result = RemoteRepository::from_url("git@github.com:jpthomasset/workflow.git");
+        assert_eq!(result.unwrap(), remote("jpthomasset", "workflow"));
+    }
+
+    #[test]
+    fn test_remote_from_https_url() {
+        let result = RemoteRepository::from_url("https://github.com/jpthomasset/workflow");
+        assert_eq!(result.unwrap(), remote("jpthomasset", "workflow"));
+    }
+
+    #[test]
+    fn test_remote_from_ssh_url() {
+        let result = RemoteRepository::from_url("ssh://git@github.com/jpthomasset/workflow.git/");
+        assert_eq!(result.unwrap(), remote("jpthomasset", "workflow"));
+    }
+
+    #[test]
+    fn test_remote_keep_nested_owner() {
+        let result = RemoteRepository::from_url("git@gitlab.com:group/sub/project.git");
+        assert_eq!(result.unwrap(), remote("group/sub", "project"));
+    }
+
+    #[test]
+    fn test_remote_reject_url_without_owner() {
+        let result = RemoteRepository::from_url("/tmp/some-repo.git");
+        assert!(matches!(result, Err(HostingError::UnsupportedRemoteUrl(_))));
+    }
+}
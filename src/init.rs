@@ -3,11 +3,18 @@ use std::cmp::Ordering;
 use inquire::{
     min_length, required,
     validator::{StringValidator, Validation},
-    Select, Text,
+    Confirm, Password, PasswordDisplayMode, Select, Text,
 };
 use url::Url;
 
-use crate::config::{BranchsName, Config, ConfigError, JiraConfig, RepoConfig};
+use crate::git::validate_branch_name;
+use crate::prompt::{InquirePrompter, Prompter};
+use crate::secret::{Secret, SecretRef, SecretStorage};
+
+use crate::config::{
+    BranchsName, Config, ConfigError, GitBackend, GitHubConfig, GitLabConfig, IssueTrackerKind,
+    JiraConfig, RepoConfig, GITHUB_API_URL, GITLAB_API_URL,
+};
 
 #[derive(Clone, Default)]
 pub struct UrlValidator {}
@@ -21,7 +28,31 @@ impl StringValidator for UrlValidator {
     }
 }
 
-pub fn init_config(old_config: Option<&Config>) -> Result<Config, ConfigError> {
+#[derive(Clone, Default)]
+pub struct BranchNameValidator {}
+
+impl StringValidator for BranchNameValidator {
+    fn validate(&self, input: &str) -> Result<Validation, inquire::CustomUserError> {
+        match validate_branch_name(input) {
+            Ok(_) => Ok(Validation::Valid),
+            Err(e) => Ok(Validation::Invalid(e.to_string().into())),
+        }
+    }
+}
+
+/// Answers to the configuration questions given on the command line
+#[derive(Debug, Default)]
+pub struct ConfigAnswers {
+    pub jira_host: Option<String>,
+    pub jira_user: Option<String>,
+    pub jira_token: Option<Secret>,
+}
+
+pub fn init_config(
+    old_config: Option<&Config>,
+    prompter: &impl Prompter,
+    answers: &ConfigAnswers,
+) -> Result<Config, ConfigError> {
     let banner = format!(
         "
     ██╗    ██╗ ██████╗ ██████╗ ██╗  ██╗███████╗██╗      ██████╗ ██╗    ██╗
@@ -39,51 +70,270 @@ pub fn init_config(old_config: Option<&Config>) -> Result<Config, ConfigError> {
 
     let jira_config = old_config.and_then(|c| c.jira.as_ref());
 
-    let old_host = jira_config.map(|j| j.host.as_ref()).unwrap_or_default();
+    let use_jira = answers.jira_host.is_some()
+        || prompter.confirm(
+            Confirm::new("Do you want to configure Jira to track issues?")
+                .with_default(jira_config.is_some() || old_config.is_none_or(|c| c.is_not_set())),
+        )?;
+
+    let jira = if use_jira {
+        let old_host = jira_config.map(|j| j.host.as_ref()).unwrap_or_default();
+
+        let host = prompter.text(
+            Text::new("What's the url of your Jira instance?")
+                .with_validator(required!())
+                .with_validator(UrlValidator::default())
+                .with_default(old_host),
+            answers.jira_host.as_deref(),
+            "--jira-host or WF_JIRA_HOST",
+        )?;
+
+        let old_user = jira_config.map(|j| j.user.as_ref()).unwrap_or_default();
+
+        let user = prompter.text(
+            Text::new("What's your username?")
+                .with_validator(required!())
+                .with_validator(min_length!(3))
+                .with_default(old_user),
+            answers.jira_user.as_deref(),
+            "--jira-user or WF_JIRA_USER",
+        )?;
+
+        if prompter.is_interactive() {
+            println!("A token is required to authenticate you on Jira. You can create a token from https://id.atlassian.com/manage-profile/security/api-tokens");
+        }
+
+        let token = prompt_secret(
+            prompter,
+            "jira",
+            "What's your token?",
+            jira_config.map(|j| &j.token),
+            answers.jira_token.as_ref(),
+            "--jira-token-stdin",
+        )?;
+
+        Some(JiraConfig { host, user, token })
+    } else {
+        None
+    };
+
+    let github_config = old_config.and_then(|c| c.github.as_ref());
+
+    let use_github = prompter.confirm(
+        Confirm::new("Do you want to configure GitHub for pull requests and issues?")
+            .with_default(github_config.is_some()),
+    )?;
+
+    let github = if use_github {
+        let old_api_url = github_config
+            .map(|g| g.api_url.as_ref())
+            .unwrap_or(GITHUB_API_URL);
+
+        let api_url = prompter.text(
+            Text::new("What's the url of the GitHub API?")
+                .with_validator(required!())
+                .with_validator(UrlValidator::default())
+                .with_default(old_api_url),
+            None,
+            "interactive mode",
+        )?;
+
+        if prompter.is_interactive() {
+            println!("A personal access token with repository access is required to open pull requests. You can create one from https://github.com/settings/tokens");
+        }
+
+        let token = prompt_secret(
+            prompter,
+            "github",
+            "What's your GitHub token?",
+            github_config.map(|g| &g.token),
+            None,
+            "interactive mode",
+        )?;
+
+        Some(GitHubConfig { api_url, token })
+    } else {
+        None
+    };
+
+    let gitlab_config = old_config.and_then(|c| c.gitlab.as_ref());
 
-    let host = Text::new("What's the url of your Jira instance?")
-        .with_validator(required!())
-        .with_validator(UrlValidator::default())
-        .with_default(old_host)
-        .prompt()?;
+    let use_gitlab = prompter.confirm(
+        Confirm::new("Do you want to configure GitLab to track issues?")
+            .with_default(gitlab_config.is_some()),
+    )?;
 
-    let old_user = jira_config.map(|j| j.user.as_ref()).unwrap_or_default();
+    let gitlab = if use_gitlab {
+        let old_api_url = gitlab_config
+            .map(|g| g.api_url.as_ref())
+            .unwrap_or(GITLAB_API_URL);
+
+        let api_url = prompter.text(
+            Text::new("What's the url of the GitLab API?")
+                .with_validator(required!())
+                .with_validator(UrlValidator::default())
+                .with_default(old_api_url),
+            None,
+            "interactive mode",
+        )?;
+
+        if prompter.is_interactive() {
+            println!("A personal access token with api scope is required. You can create one from your GitLab user settings, in Access Tokens");
+        }
 
-    let user = Text::new("What's your username?")
-        .with_validator(required!())
-        .with_validator(min_length!(3))
-        .with_default(old_user)
-        .prompt()?;
+        let token = prompt_secret(
+            prompter,
+            "gitlab",
+            "What's your GitLab token?",
+            gitlab_config.map(|g| &g.token),
+            None,
+            "interactive mode",
+        )?;
 
-    println!("A token is required to authenticate you on Jira. You can create a token from https://id.atlassian.com/manage-profile/security/api-tokens");
+        Some(GitLabConfig { api_url, token })
+    } else {
+        None
+    };
 
-    let token = Text::new("What's your token?")
-        .with_validator(required!())
-        .with_validator(min_length!(3))
-        .prompt()?;
+    let old_backend = old_config.map(|c| c.git_backend).unwrap_or_default();
+    let backends = vec![GitBackend::Libgit2, GitBackend::Cli];
+    let backend_index = backends.iter().position(|b| *b == old_backend).unwrap_or(0);
+
+    let git_backend = prompter.select(
+        Select::new("How do you want to run git operations?", backends)
+            .with_help_message(
+                "The git command line honours your credential helpers, hooks and ssh setup.",
+            )
+            .with_starting_cursor(backend_index),
+        None,
+    )?;
 
     Ok(Config {
-        jira: Some(JiraConfig { host, user, token }),
+        jira,
+        github,
+        gitlab,
+        git_backend,
+        // Not prompted, only set by editing the configuration
+        git_credentials: old_config.and_then(|c| c.git_credentials.clone()),
     })
 }
 
+/// Ask where a token should be stored, then the token itself when it is not read from a command.
+///
+/// Returns the reference to the secret to keep in the configuration. In non-interactive mode the
+/// previous secret is kept when no new token is given.
+fn prompt_secret(
+    prompter: &impl Prompter,
+    name: &str,
+    message: &str,
+    old_secret: Option<&SecretRef>,
+    answer: Option<&Secret>,
+    hint: &str,
+) -> Result<SecretRef, ConfigError> {
+    if let (None, Some(old_secret), false) = (answer, old_secret, prompter.is_interactive()) {
+        return Ok(old_secret.clone());
+    }
+
+    let storages = vec![
+        SecretStorage::Keyring,
+        SecretStorage::EncryptedFile,
+        SecretStorage::Command,
+    ];
+    let storage_index = old_secret
+        .and_then(|s| s.storage())
+        .and_then(|old| storages.iter().position(|s| *s == old))
+        .unwrap_or(0);
+
+    let storage = prompter.select(
+        Select::new("Where do you want to store the token?", storages)
+            .with_starting_cursor(storage_index),
+        None,
+    )?;
+
+    if storage == SecretStorage::Command && answer.is_none() {
+        let old_command = match old_secret {
+            Some(SecretRef::Command { command }) => command.as_str(),
+            _ => "",
+        };
+
+        let command = prompter.text(
+            Text::new("What command prints the token?")
+                .with_validator(required!())
+                .with_default(old_command),
+            None,
+            hint,
+        )?;
+
+        let secret = SecretRef::Command { command };
+        secret.resolve(InquirePrompter::new(prompter.is_interactive()))?;
+        return Ok(secret);
+    }
+
+    let token = prompter.password(
+        Password::new(message)
+            .with_display_mode(PasswordDisplayMode::Masked)
+            .with_validator(required!())
+            .without_confirmation(),
+        answer,
+        hint,
+    )?;
+
+    let prompter = InquirePrompter::new(prompter.is_interactive());
+    Ok(SecretRef::store(storage, name, &token, prompter)?)
+}
+
 pub fn init_repo_config(
-    _old_config: Option<&RepoConfig>,
+    old_config: Option<&RepoConfig>,
     branches: &[String],
+    prompter: &impl Prompter,
+    base: Option<&str>,
 ) -> Result<RepoConfig, ConfigError> {
-    // let branches_config = old_config.and_then(|c| c.branches.as_ref());
-
     let sorted_branches = sort_branches(branches);
+    let old_dev = old_config
+        .and_then(|c| c.branches.as_ref())
+        .map(|b| b.dev.as_str());
+    let dev_index = sorted_branches
+        .iter()
+        .position(|b| Some(b.as_str()) == old_dev)
+        .unwrap_or(0);
 
-    let dev = Select::new(
-        "What branch do you want to use as base branch for features?",
-        sorted_branches,
-    )
-    .prompt()?;
+    let dev = prompter.select(
+        Select::new(
+            "What branch do you want to use as base branch for features?",
+            sorted_branches,
+        )
+        .with_starting_cursor(dev_index),
+        base,
+    )?;
 
-    Ok(RepoConfig {
-        branches: Some(BranchsName { dev }),
-    })
+    let old_tracker = old_config.map(|c| c.tracker).unwrap_or_default();
+    let trackers = vec![
+        IssueTrackerKind::Jira,
+        IssueTrackerKind::GitHub,
+        IssueTrackerKind::GitLab,
+    ];
+    let tracker_index = trackers.iter().position(|t| *t == old_tracker).unwrap_or(0);
+
+    let tracker = prompter.select(
+        Select::new("Where are the issues of this repository tracked?", trackers)
+            .with_starting_cursor(tracker_index),
+        None,
+    )?;
+
+    // Keep settings that are not prompted, like issue transitions
+    let mut repo_config = old_config.cloned().unwrap_or_default();
+    repo_config.branches = Some(match repo_config.branches.take() {
+        Some(branches) => BranchsName { dev, ..branches },
+        None => BranchsName {
+            dev,
+            main: None,
+            release: None,
+            hotfix: None,
+        },
+    });
+    repo_config.tracker = tracker;
+
+    Ok(repo_config)
 }
 
 fn sort_branches<T: AsRef<str>>(branches: &[T]) -> Vec<String> {
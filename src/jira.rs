@@ -1,5 +1,6 @@
 use std::fmt::Display;
 
+use async_trait::async_trait;
 use reqwest::Client;
 use reqwest::Error as ReqwestError;
 use reqwest::StatusCode;
@@ -9,6 +10,14 @@ use thiserror::Error;
 use url::ParseError;
 
 use crate::config::JiraConfig;
+use crate::prompt::InquirePrompter;
+use crate::secret::{Secret, SecretError};
+use crate::tracker::{Issue, IssueTracker, TrackerError};
+
+const SEARCH_PAGE_SIZE: &str = "50";
+
+pub const DEFAULT_JQL: &str =
+    "assignee = currentUser() AND statusCategory != Done ORDER BY updated DESC";
 
 pub struct JiraServer {
     host: Url,
@@ -21,7 +30,7 @@ pub struct JiraCredentials {
 }
 
 pub struct JiraUser(String);
-pub struct JiraToken(String);
+pub struct JiraToken(Secret);
 
 #[derive(Debug)]
 pub struct JiraIssue {
@@ -29,6 +38,15 @@ pub struct JiraIssue {
     pub key: String,
     pub summary: String,
     pub status: JiraStatus,
+    pub issue_type: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct JiraRestSearchResult {
+    #[serde(rename = "startAt")]
+    start_at: usize,
+    total: usize,
+    issues: Vec<JiraRestIssue>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -42,6 +60,12 @@ struct JiraRestIssue {
 struct JiraRestFields {
     summary: String,
     status: JiraRestStatus,
+    issuetype: Option<JiraRestIssueType>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct JiraRestIssueType {
+    name: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -56,6 +80,40 @@ pub struct JiraStatus {
     pub name: String,
 }
 
+#[derive(Debug)]
+pub struct JiraTransition {
+    pub id: String,
+    pub name: String,
+    pub to: JiraStatus,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct JiraRestTransitions {
+    transitions: Vec<JiraRestTransition>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct JiraRestTransition {
+    id: String,
+    name: String,
+    to: JiraRestStatus,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct JiraRestComment<'a> {
+    body: &'a str,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct JiraRestDoTransition {
+    transition: JiraRestTransitionId,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct JiraRestTransitionId {
+    id: String,
+}
+
 #[derive(Debug, Error)]
 pub enum JiraError {
     #[error("{0}")]
@@ -64,6 +122,31 @@ pub enum JiraError {
     InvalidUrl(#[from] ParseError),
     #[error("Issue {0} not found")]
     IssueNotFound(String),
+    #[error("Issue {0} cannot be moved to status {1}, available statuses are: {2}")]
+    TransitionNotFound(String, String, String),
+    #[error("Cannot read Jira token: {0}")]
+    SecretError(#[from] SecretError),
+}
+
+impl Display for JiraIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} [{}]", self.key, self.summary, self.status.name)
+    }
+}
+
+impl From<JiraRestIssue> for JiraIssue {
+    fn from(rest_issue: JiraRestIssue) -> Self {
+        JiraIssue {
+            id: rest_issue.id,
+            key: rest_issue.key,
+            summary: rest_issue.fields.summary,
+            status: JiraStatus {
+                id: rest_issue.fields.status.id,
+                name: rest_issue.fields.status.name,
+            },
+            issue_type: rest_issue.fields.issuetype.map(|t| t.name),
+        }
+    }
 }
 
 impl JiraUser {
@@ -80,13 +163,7 @@ impl Display for JiraUser {
 
 impl JiraToken {
     pub fn new(token: String) -> Self {
-        Self(token)
-    }
-}
-
-impl Display for JiraToken {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
+        Self(Secret::new(token))
     }
 }
 
@@ -96,21 +173,23 @@ impl JiraCredentials {
     }
 }
 
-impl TryFrom<&JiraConfig> for JiraServer {
-    type Error = JiraError;
-
-    fn try_from(config: &JiraConfig) -> Result<Self, Self::Error> {
+impl JiraServer {
+    /// Server from the configuration, the prompter asks for the passphrase of an encrypted token
+    pub fn from_config(config: &JiraConfig, prompter: InquirePrompter) -> Result<Self, JiraError> {
         let host = Url::parse(&config.host)?;
         let user = JiraUser(config.user.clone());
-        let token = JiraToken(config.token.clone());
+        let token = JiraToken(config.token.resolve(prompter)?);
         Ok(JiraServer {
             host,
             credentials: JiraCredentials { user, token },
         })
     }
-}
 
-impl JiraServer {
+    /// Url of the issue page in the Jira web interface
+    pub fn browse_url(&self, key: &str) -> Result<Url, JiraError> {
+        browse_url(&self.host, key)
+    }
+
     pub async fn get_issue(&self, key_or_id: &str) -> Result<JiraIssue, JiraError> {
         let url = self.host.join("/rest/api/2/issue/")?.join(key_or_id)?;
 
@@ -118,7 +197,10 @@ impl JiraServer {
 
         let response = client
             .get(url)
-            .basic_auth(&self.credentials.user, Some(&self.credentials.token))
+            .basic_auth(
+                &self.credentials.user,
+                Some(self.credentials.token.0.expose()),
+            )
             .send()
             .await?;
 
@@ -128,14 +210,552 @@ impl JiraServer {
 
         let rest_issue = response.error_for_status()?.json::<JiraRestIssue>().await?;
 
-        Ok(JiraIssue {
-            id: rest_issue.id,
-            key: rest_issue.key,
-            summary: rest_issue.fields.summary,
-            status: JiraStatus {
-                id: rest_issue.fields.status.id,
-                name: rest_issue.fields.status.name,
-            },
+        Ok(rest_issue.into())
+    }
+
+    /// Run a JQL search and return all matching issues, following pagination
+    pub async fn search_issues(&self, jql: &str) -> Result<Vec<JiraIssue>, JiraError> {
+        let url = self.host.join("/rest/api/2/search")?;
+
+        let client = Client::builder().build()?;
+
+        let mut issues: Vec<JiraIssue> = Vec::new();
+        loop {
+            let start_at = issues.len().to_string();
+            let response = client
+                .get(url.clone())
+                .basic_auth(
+                    &self.credentials.user,
+                    Some(self.credentials.token.0.expose()),
+                )
+                .query(&[
+                    ("jql", jql),
+                    ("startAt", &start_at),
+                    ("maxResults", SEARCH_PAGE_SIZE),
+                    ("fields", "summary,status,issuetype"),
+                ])
+                .send()
+                .await?;
+
+            let page = response
+                .error_for_status()?
+                .json::<JiraRestSearchResult>()
+                .await?;
+
+            let page_len = page.issues.len();
+            issues.extend(page.issues.into_iter().map(JiraIssue::from));
+
+            if page_len == 0 || page.start_at + page_len >= page.total {
+                return Ok(issues);
+            }
+        }
+    }
+
+    /// List the transitions currently available for an issue
+    pub async fn get_transitions(&self, key_or_id: &str) -> Result<Vec<JiraTransition>, JiraError> {
+        let url = self.transitions_url(key_or_id)?;
+
+        let client = Client::builder().build()?;
+
+        let response = client
+            .get(url)
+            .basic_auth(
+                &self.credentials.user,
+                Some(self.credentials.token.0.expose()),
+            )
+            .send()
+            .await?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            return Err(JiraError::IssueNotFound(key_or_id.to_string()));
+        }
+
+        let rest_transitions = response
+            .error_for_status()?
+            .json::<JiraRestTransitions>()
+            .await?;
+
+        Ok(rest_transitions
+            .transitions
+            .into_iter()
+            .map(|t| JiraTransition {
+                id: t.id,
+                name: t.name,
+                to: JiraStatus {
+                    id: t.to.id,
+                    name: t.to.name,
+                },
+            })
+            .collect())
+    }
+
+    /// Apply a transition, as returned by `get_transitions`, to an issue
+    pub async fn transition_issue(
+        &self,
+        key_or_id: &str,
+        transition_id: &str,
+    ) -> Result<(), JiraError> {
+        let url = self.transitions_url(key_or_id)?;
+
+        let client = Client::builder().build()?;
+
+        let response = client
+            .post(url)
+            .basic_auth(
+                &self.credentials.user,
+                Some(self.credentials.token.0.expose()),
+            )
+            .json(&JiraRestDoTransition {
+                transition: JiraRestTransitionId {
+                    id: transition_id.to_string(),
+                },
+            })
+            .send()
+            .await?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            return Err(JiraError::IssueNotFound(key_or_id.to_string()));
+        }
+
+        response.error_for_status()?;
+        Ok(())
+    }
+
+    /// Move an issue to the given status name.
+    ///
+    /// Returns `false` when the issue is already in that status and nothing was done.
+    pub async fn transition_to_status(
+        &self,
+        key: &str,
+        current_status: &str,
+        status: &str,
+    ) -> Result<bool, JiraError> {
+        if current_status.eq_ignore_ascii_case(status) {
+            return Ok(false);
+        }
+
+        let transitions = self.get_transitions(key).await?;
+
+        match transitions
+            .iter()
+            .find(|t| t.to.name.eq_ignore_ascii_case(status))
+        {
+            Some(transition) => {
+                self.transition_issue(key, &transition.id).await?;
+                Ok(true)
+            }
+            None => Err(JiraError::TransitionNotFound(
+                key.to_string(),
+                status.to_string(),
+                transitions
+                    .iter()
+                    .map(|t| t.to.name.as_str())
+                    .collect::<Vec<&str>>()
+                    .join(", "),
+            )),
+        }
+    }
+
+    pub async fn add_comment(&self, key_or_id: &str, body: &str) -> Result<(), JiraError> {
+        let url = self
+            .host
+            .join("/rest/api/2/issue/")?
+            .join(&format!("{}/comment", key_or_id))?;
+
+        let client = Client::builder().build()?;
+
+        let response = client
+            .post(url)
+            .basic_auth(
+                &self.credentials.user,
+                Some(self.credentials.token.0.expose()),
+            )
+            .json(&JiraRestComment { body })
+            .send()
+            .await?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            return Err(JiraError::IssueNotFound(key_or_id.to_string()));
+        }
+
+        response.error_for_status()?;
+        Ok(())
+    }
+
+    fn to_issue(&self, issue: JiraIssue) -> Result<Issue, JiraError> {
+        Ok(Issue {
+            url: self.browse_url(&issue.key)?.to_string(),
+            key: issue.key,
+            summary: issue.summary,
+            status: issue.status.name,
+            issue_type: issue.issue_type,
         })
     }
+
+    fn transitions_url(&self, key_or_id: &str) -> Result<Url, JiraError> {
+        Ok(self
+            .host
+            .join("/rest/api/2/issue/")?
+            .join(&format!("{}/transitions", key_or_id))?)
+    }
+}
+
+#[async_trait]
+impl IssueTracker for JiraServer {
+    async fn get_issue(&self, key: &str) -> Result<Issue, TrackerError> {
+        let issue = JiraServer::get_issue(self, key).await?;
+        Ok(self.to_issue(issue)?)
+    }
+
+    async fn search_issues(&self, query: &str) -> Result<Vec<Issue>, TrackerError> {
+        let issues = JiraServer::search_issues(self, query).await?;
+        issues
+            .into_iter()
+            .map(|issue| self.to_issue(issue).map_err(TrackerError::from))
+            .collect()
+    }
+
+    async fn transition_to_status(
+        &self,
+        issue: &Issue,
+        status: &str,
+    ) -> Result<bool, TrackerError> {
+        Ok(JiraServer::transition_to_status(self, &issue.key, &issue.status, status).await?)
+    }
+
+    async fn add_comment(&self, issue: &Issue, body: &str) -> Result<(), TrackerError> {
+        Ok(JiraServer::add_comment(self, &issue.key, body).await?)
+    }
+
+    fn default_query(&self) -> &str {
+        DEFAULT_JQL
+    }
+
+    fn find_issue_key(&self, text: &str) -> Option<String> {
+        issue_key_from_branch(text)
+    }
+}
+
+/// Url of the page of an issue on a Jira server, no connection to the server is needed
+pub fn browse_url(host: &Url, key: &str) -> Result<Url, JiraError> {
+    Ok(host.join("/browse/")?.join(key)?)
+}
+
+/// Find the first Jira issue key (e.g. `PROJ-123`) in a branch name
+pub fn issue_key_from_branch(branch: &str) -> Option<String> {
+    let starts = std::iter::once(0).chain(
+        branch
+            .char_indices()
+            .filter(|(_, c)| !c.is_ascii_alphanumeric())
+            .map(|(i, c)| i + c.len_utf8()),
+    );
+
+    starts
+        .filter_map(|start| parse_issue_key(&branch[start..]))
+        .next()
+}
+
+fn parse_issue_key(input: &str) -> Option<String> {
+    let (project, rest) = input.split_once('-')?;
+    let mut project_chars = project.chars();
+    let valid_project = project_chars.next().is_some_and(|c| c.is_ascii_uppercase())
+        && project_chars.all(|c| c.is_ascii_uppercase() || c.is_ascii_digit());
+
+    let number: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+    let ends_properly = rest[number.len()..]
+        .chars()
+        .next()
+        .is_none_or(|c| !c.is_ascii_alphanumeric());
+
+    if valid_project && !number.is_empty() && ends_properly {
+        Some(format!("{}-{}", project, number))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secret::SecretRef;
+    use crate::test_support::MockJira;
+
+    const TRANSITIONS: &[(&str, &str)] = &[("11", "In Progress"), ("21", "In Review")];
+
+    #[tokio::test]
+    async fn test_get_issue() {
+        let mut mock_jira = MockJira::new().await;
+        mock_jira.issue(("PROJ-1", "Fix the login", "To Do")).await;
+
+        let issue = mock_jira.jira().get_issue("PROJ-1").await.unwrap();
+
+        assert_eq!(issue.key, "PROJ-1");
+        assert_eq!(issue.summary, "Fix the login");
+        assert_eq!(issue.status.name, "To Do");
+        assert_eq!(issue.issue_type.as_deref(), Some("Story"));
+    }
+
+    #[tokio::test]
+    async fn test_get_issue_as_tracker_issue() {
+        let mut mock_jira = MockJira::new().await;
+        mock_jira.issue(("PROJ-1", "Fix the login", "To Do")).await;
+        let tracker: &dyn IssueTracker = &mock_jira.jira();
+
+        let issue = tracker.get_issue("PROJ-1").await.unwrap();
+
+        assert_eq!(issue.url, format!("{}/browse/PROJ-1", mock_jira.url()));
+    }
+
+    #[tokio::test]
+    async fn test_get_missing_issue() {
+        let mut mock_jira = MockJira::new().await;
+        mock_jira
+            .error(
+                "GET",
+                "/rest/api/2/issue/PROJ-404",
+                404,
+                "Issue does not exist or you do not have permission to see it.",
+            )
+            .await;
+
+        let result = mock_jira.jira().get_issue("PROJ-404").await;
+
+        assert!(matches!(result, Err(JiraError::IssueNotFound(key)) if key == "PROJ-404"));
+    }
+
+    #[tokio::test]
+    async fn test_get_issue_server_error() {
+        let mut mock_jira = MockJira::new().await;
+        mock_jira
+            .error("GET", "/rest/api/2/issue/PROJ-1", 500, "Internal error")
+            .await;
+
+        let result = mock_jira.jira().get_issue("PROJ-1").await;
+
+        match result {
+            Err(JiraError::RequestError(e)) => {
+                assert_eq!(e.status(), Some(StatusCode::INTERNAL_SERVER_ERROR))
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_issue_with_wrong_token() {
+        let mut mock_jira = MockJira::new().await;
+        let mock = mock_jira.issue(("PROJ-1", "Fix the login", "To Do")).await;
+        let unauthorized = mock_jira
+            .unauthorized("GET", "/rest/api/2/issue/PROJ-1", "wrong")
+            .await;
+        let jira = JiraServer::from_config(
+            &JiraConfig {
+                token: SecretRef::Plain("wrong".to_string()),
+                ..mock_jira.config()
+            },
+            InquirePrompter::new(false),
+        )
+        .unwrap();
+
+        let result = jira.get_issue("PROJ-1").await;
+
+        match result {
+            Err(JiraError::RequestError(e)) => {
+                assert_eq!(e.status(), Some(StatusCode::UNAUTHORIZED))
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+        mock.expect(0).assert_async().await;
+        unauthorized.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_get_issue_malformed_json() {
+        let mut mock_jira = MockJira::new().await;
+        mock_jira.malformed("GET", "/rest/api/2/issue/PROJ-1").await;
+
+        let result = mock_jira.jira().get_issue("PROJ-1").await;
+
+        match result {
+            Err(JiraError::RequestError(e)) => assert!(e.is_decode()),
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_issues_follow_pages() {
+        let mut mock_jira = MockJira::new().await;
+        let jql = "assignee = currentUser()";
+        mock_jira
+            .search_page_at(
+                jql,
+                0,
+                3,
+                &[("PROJ-1", "First", "To Do"), ("PROJ-2", "Second", "To Do")],
+            )
+            .await;
+        mock_jira
+            .search_page_at(jql, 2, 3, &[("PROJ-3", "Third", "In Progress")])
+            .await;
+
+        let result = mock_jira.jira().search_issues(jql).await.unwrap();
+
+        let keys: Vec<&str> = result.iter().map(|i| i.key.as_str()).collect();
+        assert_eq!(keys, vec!["PROJ-1", "PROJ-2", "PROJ-3"]);
+        assert_eq!(result[2].to_string(), "PROJ-3 Third [In Progress]");
+    }
+
+    #[tokio::test]
+    async fn test_search_issues_malformed_json() {
+        let mut mock_jira = MockJira::new().await;
+        mock_jira.malformed("GET", "/rest/api/2/search").await;
+
+        let result = mock_jira.jira().search_issues(DEFAULT_JQL).await;
+
+        assert!(matches!(result, Err(JiraError::RequestError(e)) if e.is_decode()));
+    }
+
+    #[tokio::test]
+    async fn test_get_transitions() {
+        let mut mock_jira = MockJira::new().await;
+        mock_jira.transitions("PROJ-1", TRANSITIONS).await;
+
+        let result = mock_jira.jira().get_transitions("PROJ-1").await.unwrap();
+
+        let names: Vec<&str> = result.iter().map(|t| t.to.name.as_str()).collect();
+        assert_eq!(names, vec!["In Progress", "In Review"]);
+    }
+
+    #[tokio::test]
+    async fn test_get_transitions_of_missing_issue() {
+        let mut mock_jira = MockJira::new().await;
+        mock_jira
+            .error(
+                "GET",
+                "/rest/api/2/issue/PROJ-404/transitions",
+                404,
+                "Issue does not exist",
+            )
+            .await;
+
+        let result = mock_jira.jira().get_transitions("PROJ-404").await;
+
+        assert!(matches!(result, Err(JiraError::IssueNotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_transition_to_status() {
+        let mut mock_jira = MockJira::new().await;
+        mock_jira.transitions("PROJ-1", TRANSITIONS).await;
+        let post = mock_jira.expect_transition("PROJ-1", "21").await;
+
+        let result = mock_jira
+            .jira()
+            .transition_to_status("PROJ-1", "In Progress", "in review")
+            .await
+            .unwrap();
+
+        post.assert_async().await;
+        assert!(result);
+    }
+
+    #[tokio::test]
+    async fn test_transition_to_current_status_does_nothing() {
+        let mock_jira = MockJira::new().await;
+
+        let result = mock_jira
+            .jira()
+            .transition_to_status("PROJ-1", "In Progress", "In Progress")
+            .await
+            .unwrap();
+
+        assert!(!result);
+    }
+
+    #[tokio::test]
+    async fn test_transition_to_unknown_status() {
+        let mut mock_jira = MockJira::new().await;
+        mock_jira.transitions("PROJ-1", TRANSITIONS).await;
+
+        let result = mock_jira
+            .jira()
+            .transition_to_status("PROJ-1", "To Do", "Done")
+            .await;
+
+        match result {
+            Err(JiraError::TransitionNotFound(_, _, available)) => {
+                assert_eq!(available, "In Progress, In Review")
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_transition_refused() {
+        let mut mock_jira = MockJira::new().await;
+        mock_jira
+            .error(
+                "POST",
+                "/rest/api/2/issue/PROJ-1/transitions",
+                400,
+                "Field resolution is required",
+            )
+            .await;
+
+        let result = mock_jira.jira().transition_issue("PROJ-1", "31").await;
+
+        assert!(matches!(result, Err(JiraError::RequestError(e))
+            if e.status() == Some(StatusCode::BAD_REQUEST)));
+    }
+
+    #[tokio::test]
+    async fn test_add_comment() {
+        let mut mock_jira = MockJira::new().await;
+        let comment = mock_jira
+            .expect_comment("PROJ-1", "Merged in develop")
+            .await;
+
+        mock_jira
+            .jira()
+            .add_comment("PROJ-1", "Merged in develop")
+            .await
+            .unwrap();
+
+        comment.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_add_comment_to_missing_issue() {
+        let mut mock_jira = MockJira::new().await;
+        mock_jira
+            .error(
+                "POST",
+                "/rest/api/2/issue/PROJ-404/comment",
+                404,
+                "Issue does not exist",
+            )
+            .await;
+
+        let result = mock_jira.jira().add_comment("PROJ-404", "Hello").await;
+
+        assert!(matches!(result, Err(JiraError::IssueNotFound(_))));
+    }
+
+    #[test]
+    fn test_issue_key_from_branch() {
+        let result = issue_key_from_branch("PROJ-123-some-feature");
+        assert_eq!(result, Some("PROJ-123".to_string()));
+    }
+
+    #[test]
+    fn test_issue_key_from_prefixed_branch() {
+        let result = issue_key_from_branch("feature/AB2-7");
+        assert_eq!(result, Some("AB2-7".to_string()));
+    }
+
+    #[test]
+    fn test_issue_key_from_branch_without_key() {
+        assert_eq!(issue_key_from_branch("some-feature-12"), None);
+        assert_eq!(issue_key_from_branch("PROJ-12b"), None);
+        assert_eq!(issue_key_from_branch("develop"), None);
+    }
 }
@@ -1,11 +1,27 @@
 pub mod adapt_err;
+pub mod backport;
+pub mod branch_name;
+pub mod changelog;
 pub mod cli;
 pub mod command;
 pub mod config;
+pub mod conventional;
+pub mod credentials;
 pub mod errors;
 pub mod git;
+pub mod git_cli;
+pub mod github;
+pub mod gitlab;
+pub mod hooks;
+pub mod hosting;
 pub mod init;
 pub mod jira;
+pub mod prompt;
+pub mod release;
+pub mod secret;
+#[cfg(test)]
+pub mod test_support;
+pub mod tracker;
 
 pub fn repeat_until_ok<F, T, E>(mut f: F) -> T
 where
@@ -3,11 +3,24 @@ use std::process::ExitCode;
 use clap::Parser;
 
 use workflow::{
-    cli::{WfArgs, WfCommands, WfTestCommands},
-    command,
-    config::{Config, RepoConfig},
+    cli::{
+        WfArgs, WfCommands, WfHooksCommands, WfHotfixCommands, WfInitArgs, WfReleaseCommands,
+        WfTestCommands, WfWorktreeCommands,
+    },
+    command::{self, FinishAnswers, PrAnswers, StartAnswers},
+    config::{Config, GitBackend, IssueTrackerKind, RepoConfig},
+    credentials::GitCredentials,
     errors::WfError,
-    git::{GitRepository, LocalGitRepository},
+    git::{GitError, GitRepository, LocalGitRepository},
+    git_cli::CliGitRepository,
+    github::{GitHubIssueTracker, GitHubServer},
+    gitlab::GitLabIssueTracker,
+    hooks::HookError,
+    hosting::RemoteRepository,
+    init::ConfigAnswers,
+    jira::JiraServer,
+    prompt::{read_secret_from_stdin, InquirePrompter},
+    tracker::IssueTracker,
 };
 
 #[tokio::main]
@@ -21,40 +34,135 @@ async fn main() -> ExitCode {
     }
 }
 
-fn load_config(auto_init: bool) -> Result<Config, WfError> {
+fn load_config(auto_init: bool, prompter: &InquirePrompter) -> Result<Config, WfError> {
     let config = Config::load()?;
 
     if config.is_not_set() && auto_init {
         print!("Configuration is not set, starting initialization");
-        command::command_init_config(config)
+        command::command_init_config(config, prompter, &ConfigAnswers::default())
     } else {
         Ok(config)
     }
 }
 
-fn load_repo_config(repo: &impl GitRepository) -> Result<RepoConfig, WfError> {
+fn load_repo_config(
+    repo: &impl GitRepository,
+    prompter: &InquirePrompter,
+) -> Result<RepoConfig, WfError> {
     let path = repo.workdir().ok_or(WfError::NoGitWorkingDirectory)?;
     let config = RepoConfig::load(path)?;
 
     if config.is_not_set() {
         print!("Repository configuration is not set, starting initialization");
-        command::command_init_repo(config, repo)
+        command::command_init_repo(config, repo, prompter, None)
     } else {
         Ok(config)
     }
 }
 
+/// Open the current repository with its configuration and the configured remote
+fn open_repository<R, D>(
+    discover: &D,
+    prompter: &InquirePrompter,
+) -> Result<(R, RepoConfig), WfError>
+where
+    R: GitRepository,
+    D: Fn() -> Result<R, GitError>,
+{
+    let repo = discover()?;
+    let repo_config = load_repo_config(&repo, prompter)?;
+    let repo = repo.with_remote(repo_config.remote_name());
+    Ok((repo, repo_config))
+}
+
+fn load_issue_tracker(
+    config: &Config,
+    repo_config: &RepoConfig,
+    repo: &impl GitRepository,
+    prompter: &InquirePrompter,
+) -> Result<Box<dyn IssueTracker>, WfError> {
+    let not_configured = || WfError::TrackerNotConfigured(repo_config.tracker.to_string());
+
+    match repo_config.tracker {
+        IssueTrackerKind::Jira => {
+            let jira_config = config.jira.as_ref().ok_or_else(not_configured)?;
+            Ok(Box::new(JiraServer::from_config(jira_config, *prompter)?))
+        }
+        IssueTrackerKind::GitHub => {
+            let github_config = config.github.as_ref().ok_or_else(not_configured)?;
+            let remote = RemoteRepository::from_url(&repo.remote_url()?)?;
+            Ok(Box::new(GitHubIssueTracker::new(
+                GitHubServer::from_config(github_config, *prompter)?,
+                remote,
+            )))
+        }
+        IssueTrackerKind::GitLab => {
+            let gitlab_config = config.gitlab.as_ref().ok_or_else(not_configured)?;
+            let remote = RemoteRepository::from_url(&repo.remote_url()?)?;
+            Ok(Box::new(GitLabIssueTracker::new(
+                gitlab_config,
+                remote,
+                *prompter,
+            )?))
+        }
+    }
+}
+
 async fn run() -> Result<(), WfError> {
     let args = WfArgs::try_parse()?;
-    let auto_init: bool = args.command != WfCommands::Init;
-    let config = load_config(auto_init)?;
-
-    match args.command {
-        WfCommands::Init => {
-            command::command_init_config(config)?;
-            if let Ok(repo) = LocalGitRepository::discover() {
-                let repo_config = load_repo_config(&repo).unwrap_or(RepoConfig::default());
-                command::command_init_repo(repo_config, &repo)?;
+    let prompter = InquirePrompter::new(!args.non_interactive);
+    // Hooks run inside git commands, they must never start asking questions
+    let auto_init: bool = !matches!(
+        args.command,
+        WfCommands::Init(_) | WfCommands::Hooks(WfHooksCommands::Run { .. })
+    );
+    let config = load_config(auto_init, &prompter)?;
+
+    match config.git_backend {
+        GitBackend::Libgit2 => {
+            let credentials =
+                GitCredentials::new(config.git_credentials.clone().unwrap_or_default(), prompter);
+            let discover = || {
+                LocalGitRepository::discover()
+                    .map(|repo| repo.with_credentials(credentials.clone()))
+            };
+            run_command(args.command, config, &prompter, discover).await
+        }
+        GitBackend::Cli => {
+            run_command(args.command, config, &prompter, CliGitRepository::discover).await
+        }
+    }
+}
+
+async fn run_command<R, D>(
+    command: WfCommands,
+    config: Config,
+    prompter: &InquirePrompter,
+    discover: D,
+) -> Result<(), WfError>
+where
+    R: GitRepository,
+    D: Fn() -> Result<R, GitError>,
+{
+    match command {
+        WfCommands::Init(init_args) => {
+            let WfInitArgs {
+                jira_host,
+                jira_user,
+                jira_token_stdin,
+                base,
+            } = init_args;
+            let answers = ConfigAnswers {
+                jira_host,
+                jira_user,
+                jira_token: jira_token_stdin.then(read_secret_from_stdin).transpose()?,
+            };
+
+            command::command_init_config(config, prompter, &answers)?;
+            if let Ok(repo) = discover() {
+                let path = repo.workdir().ok_or(WfError::NoGitWorkingDirectory)?;
+                let repo_config = RepoConfig::load(path).unwrap_or_default();
+                command::command_init_repo(repo_config, &repo, prompter, base.as_deref())?;
             }
         }
 
@@ -64,14 +172,210 @@ async fn run() -> Result<(), WfError> {
             WfTestCommands::All => println!("All"),
         },
 
-        WfCommands::Start { ticket_id } => {
-            let repo = LocalGitRepository::discover()?;
-            let repo_config = load_repo_config(&repo)?;
-            command::command_start(&config, &repo_config, &repo, &ticket_id).await?;
+        WfCommands::Start {
+            ticket_id,
+            branch_name,
+            base,
+            from,
+            dirty,
+            worktree,
+        } => {
+            let (repo, repo_config) = open_repository(&discover, prompter)?;
+            let tracker = load_issue_tracker(&config, &repo_config, &repo, prompter)?;
+            let answers = StartAnswers {
+                ticket_id: ticket_id.as_deref(),
+                branch_name: branch_name.as_deref(),
+                base: base.as_deref(),
+                from,
+                dirty,
+                worktree,
+            };
+            command::command_start(&repo_config, &repo, tracker.as_ref(), prompter, &answers)
+                .await?;
+        }
+
+        WfCommands::Push { force_with_lease } => {
+            let (repo, repo_config) = open_repository(&discover, prompter)?;
+            let tracker = load_issue_tracker(&config, &repo_config, &repo, prompter).ok();
+            command::command_push(&repo_config, &repo, tracker.as_deref(), force_with_lease)
+                .await?;
+        }
+
+        WfCommands::Pr { title, base } => {
+            let github = config
+                .github
+                .as_ref()
+                .ok_or(WfError::HostingNotConfigured)
+                .and_then(|c| GitHubServer::from_config(c, *prompter).map_err(WfError::from))?;
+            let (repo, repo_config) = open_repository(&discover, prompter)?;
+            let tracker = load_issue_tracker(&config, &repo_config, &repo, prompter).ok();
+            let answers = PrAnswers {
+                title: title.as_deref(),
+                base: base.as_deref(),
+            };
+            command::command_pr(
+                &repo_config,
+                &repo,
+                &github,
+                tracker.as_deref(),
+                prompter,
+                &answers,
+            )
+            .await?;
+        }
+
+        WfCommands::Sync {
+            strategy,
+            resume,
+            abort,
+        } => {
+            let (repo, repo_config) = open_repository(&discover, prompter)?;
+            if resume {
+                command::command_sync_continue(&repo)?;
+            } else if abort {
+                command::command_sync_abort(&repo)?;
+            } else {
+                command::command_sync(&repo_config, &repo, strategy)?;
+            }
+        }
+
+        WfCommands::Finish {
+            strategy,
+            delete_branch,
+            message,
+        } => {
+            let (repo, repo_config) = open_repository(&discover, prompter)?;
+            let tracker = load_issue_tracker(&config, &repo_config, &repo, prompter).ok();
+            let answers = FinishAnswers {
+                strategy,
+                delete_branch,
+                message: message.as_deref(),
+            };
+            command::command_finish(&repo_config, &repo, tracker.as_deref(), prompter, &answers)
+                .await?;
+        }
+
+        WfCommands::Status => {
+            let (repo, repo_config) = open_repository(&discover, prompter)?;
+            let tracker = load_issue_tracker(&config, &repo_config, &repo, prompter).ok();
+            let report = command::command_status(&repo_config, &repo, tracker.as_deref()).await?;
+            println!("{}", report);
+        }
+
+        WfCommands::LintCommits { range } => {
+            let (repo, repo_config) = open_repository(&discover, prompter)?;
+            command::command_lint_commits(&repo_config, &repo, range.as_deref())?;
+        }
+
+        WfCommands::Changelog { from, title } => {
+            let (repo, repo_config) = open_repository(&discover, prompter)?;
+            let changelog = command::command_changelog(
+                &repo_config,
+                &repo,
+                config.jira.as_ref(),
+                from.as_deref(),
+                title.as_deref(),
+            )?;
+            print!("{}", changelog);
+        }
+
+        WfCommands::Release(release_command) => {
+            let (repo, repo_config) = open_repository(&discover, prompter)?;
+            match release_command {
+                WfReleaseCommands::Start { version, bump } => {
+                    command::command_release_start(&repo_config, &repo, version.as_deref(), bump)?;
+                }
+                WfReleaseCommands::Finish { push } => {
+                    command::command_release_finish(&repo_config, &repo, push)?;
+                }
+            }
+        }
+
+        WfCommands::Hotfix(WfHotfixCommands::Start {
+            ticket_id,
+            branch_name,
+            from,
+            dirty,
+            worktree,
+        }) => {
+            let (repo, repo_config) = open_repository(&discover, prompter)?;
+            let tracker = load_issue_tracker(&config, &repo_config, &repo, prompter)?;
+            let answers = StartAnswers {
+                ticket_id: ticket_id.as_deref(),
+                branch_name: branch_name.as_deref(),
+                from,
+                dirty,
+                worktree,
+                ..StartAnswers::default()
+            };
+            command::command_hotfix_start(
+                &repo_config,
+                &repo,
+                tracker.as_ref(),
+                prompter,
+                &answers,
+            )
+            .await?;
+        }
+
+        WfCommands::Hotfix(WfHotfixCommands::Finish { push }) => {
+            let (repo, repo_config) = open_repository(&discover, prompter)?;
+            let tracker = load_issue_tracker(&config, &repo_config, &repo, prompter).ok();
+            command::command_hotfix_finish(&repo_config, &repo, tracker.as_deref(), push).await?;
+        }
+
+        WfCommands::Backport {
+            ticket_id,
+            to,
+            comment,
+        } => {
+            let (repo, repo_config) = open_repository(&discover, prompter)?;
+            let tracker = load_issue_tracker(&config, &repo_config, &repo, prompter)?;
+            command::command_backport(
+                &repo_config,
+                &repo,
+                tracker.as_ref(),
+                &ticket_id,
+                &to,
+                comment,
+            )
+            .await?;
+        }
+
+        WfCommands::Worktree(worktree_command) => {
+            let (repo, repo_config) = open_repository(&discover, prompter)?;
+            let tracker = load_issue_tracker(&config, &repo_config, &repo, prompter).ok();
+            match worktree_command {
+                WfWorktreeCommands::List => {
+                    let worktrees =
+                        command::command_worktree_list(&repo_config, &repo, tracker.as_deref())?;
+                    for worktree in worktrees {
+                        println!("{}", worktree);
+                    }
+                }
+                WfWorktreeCommands::Remove { name } => {
+                    command::command_worktree_remove(
+                        &repo_config,
+                        &repo,
+                        tracker.as_deref(),
+                        &name,
+                    )?;
+                }
+            }
+        }
+
+        WfCommands::Hooks(WfHooksCommands::Install) => {
+            let repo = discover()?;
+            let executable = std::env::current_exe()
+                .map_err(|e| HookError::CannotInstall("wf".to_string(), e))?;
+            command::command_install_hooks(&repo, &executable)?;
         }
 
-        WfCommands::Push => {
-            LocalGitRepository::discover()?.push()?;
+        WfCommands::Hooks(WfHooksCommands::Run { hook, args }) => {
+            let repo = discover()?;
+            let path = repo.workdir().ok_or(WfError::NoGitWorkingDirectory)?;
+            let repo_config = RepoConfig::load(path).unwrap_or_default();
+            command::command_run_hook(&repo_config, &repo, hook, &args)?;
         }
 
         WfCommands::Noop => {
@@ -0,0 +1,283 @@
+use std::fmt::Display;
+use std::io::{self, BufRead};
+
+use inquire::{
+    validator::{ErrorMessage, Validation},
+    Confirm, InquireError, Password, Select, Text,
+};
+use thiserror::Error;
+
+use crate::secret::Secret;
+
+#[derive(Debug, Error)]
+pub enum PromptError {
+    #[error("{0}")]
+    InquireError(#[from] InquireError),
+    #[error("\"{0}\" needs an answer in non-interactive mode, please use {1}")]
+    InputRequired(String, String),
+    #[error("Invalid answer {0}: {1}")]
+    InvalidAnswer(String, String),
+    #[error("Cannot read standard input: {0}")]
+    StdinError(#[from] io::Error),
+}
+
+/// Asks the questions of the commands.
+///
+/// Implementations only answer questions in interactive mode. Answers given on the command line
+/// and defaults used in non-interactive mode are handled by the provided methods.
+pub trait Prompter {
+    fn is_interactive(&self) -> bool;
+
+    fn ask_confirm(&self, confirm: Confirm) -> Result<bool, PromptError>;
+
+    fn ask_text(&self, text: Text) -> Result<String, PromptError>;
+
+    fn ask_select<T: Display>(&self, select: Select<T>) -> Result<T, PromptError>;
+
+    fn ask_password(&self, password: Password) -> Result<Secret, PromptError>;
+
+    /// Ask for a confirmation, the default is used in non-interactive mode
+    fn confirm(&self, confirm: Confirm) -> Result<bool, PromptError> {
+        if self.is_interactive() {
+            self.ask_confirm(confirm)
+        } else {
+            Ok(default_confirm(&confirm))
+        }
+    }
+
+    /// Ask for a text unless an answer was given on the command line.
+    ///
+    /// In non-interactive mode the initial value or the default is used. The answer is always
+    /// checked with the validators of the prompt, `hint` tells how to provide a missing answer.
+    fn text(&self, text: Text, answer: Option<&str>, hint: &str) -> Result<String, PromptError> {
+        match answer {
+            Some(answer) => check_text(&text, answer, hint),
+            None if self.is_interactive() => self.ask_text(text),
+            None => check_text(&text, default_text(&text), hint),
+        }
+    }
+
+    /// Choose among options unless an answer was given on the command line.
+    ///
+    /// In non-interactive mode the option under the starting cursor is used.
+    fn select<T: Display>(
+        &self,
+        select: Select<T>,
+        answer: Option<&str>,
+    ) -> Result<T, PromptError> {
+        match answer {
+            Some(answer) => select_option(select, answer),
+            None if self.is_interactive() => self.ask_select(select),
+            None => default_select(select),
+        }
+    }
+
+    /// Ask for a password unless it was given on the command line, there is no default for it
+    fn password(
+        &self,
+        password: Password,
+        answer: Option<&Secret>,
+        hint: &str,
+    ) -> Result<Secret, PromptError> {
+        match answer {
+            Some(answer) => Ok(answer.clone()),
+            None if self.is_interactive() => self.ask_password(password),
+            None => Err(PromptError::InputRequired(
+                password.message.to_string(),
+                hint.to_string(),
+            )),
+        }
+    }
+}
+
+/// Prompter asking the questions in the terminal
+#[derive(Debug, Clone, Copy)]
+pub struct InquirePrompter {
+    interactive: bool,
+}
+
+impl InquirePrompter {
+    pub fn new(interactive: bool) -> Self {
+        Self { interactive }
+    }
+}
+
+impl Prompter for InquirePrompter {
+    fn is_interactive(&self) -> bool {
+        self.interactive
+    }
+
+    fn ask_confirm(&self, confirm: Confirm) -> Result<bool, PromptError> {
+        Ok(confirm.prompt()?)
+    }
+
+    fn ask_text(&self, text: Text) -> Result<String, PromptError> {
+        Ok(text.prompt()?)
+    }
+
+    fn ask_select<T: Display>(&self, select: Select<T>) -> Result<T, PromptError> {
+        Ok(select.prompt()?)
+    }
+
+    fn ask_password(&self, password: Password) -> Result<Secret, PromptError> {
+        Ok(Secret::new(password.prompt()?))
+    }
+}
+
+pub(crate) fn default_confirm(confirm: &Confirm) -> bool {
+    confirm.default.unwrap_or(false)
+}
+
+pub(crate) fn default_text<'a>(text: &Text<'a>) -> &'a str {
+    text.initial_value.or(text.default).unwrap_or_default()
+}
+
+pub(crate) fn default_select<T: Display>(mut select: Select<T>) -> Result<T, PromptError> {
+    let index = select.starting_cursor;
+    if index < select.options.len() {
+        Ok(select.options.swap_remove(index))
+    } else {
+        Err(PromptError::InputRequired(
+            select.message.to_string(),
+            "interactive mode".to_string(),
+        ))
+    }
+}
+
+/// Pick the option displayed as the answer
+pub(crate) fn select_option<T: Display>(
+    mut select: Select<T>,
+    answer: &str,
+) -> Result<T, PromptError> {
+    match select.options.iter().position(|o| o.to_string() == answer) {
+        Some(index) => Ok(select.options.swap_remove(index)),
+        None => Err(PromptError::InvalidAnswer(
+            answer.to_string(),
+            format!("not an option for \"{}\"", select.message),
+        )),
+    }
+}
+
+/// Check an answer with the validators of the prompt
+pub(crate) fn check_text(text: &Text, answer: &str, hint: &str) -> Result<String, PromptError> {
+    if answer.is_empty() {
+        return Err(PromptError::InputRequired(
+            text.message.to_string(),
+            hint.to_string(),
+        ));
+    }
+
+    for validator in &text.validators {
+        let validation = validator
+            .validate(answer)
+            .map_err(|e| PromptError::InvalidAnswer(answer.to_string(), e.to_string()))?;
+
+        if let Validation::Invalid(message) = validation {
+            let reason = match message {
+                ErrorMessage::Custom(reason) => reason,
+                ErrorMessage::Default => format!("not accepted for \"{}\"", text.message),
+            };
+            return Err(PromptError::InvalidAnswer(answer.to_string(), reason));
+        }
+    }
+
+    Ok(answer.to_string())
+}
+
+/// Read a secret from the first line of the standard input
+pub fn read_secret_from_stdin() -> Result<Secret, PromptError> {
+    let mut line = String::new();
+    io::stdin().lock().read_line(&mut line)?;
+
+    let secret = line.trim();
+    if secret.is_empty() {
+        return Err(PromptError::InvalidAnswer(
+            "from standard input".to_string(),
+            "empty token".to_string(),
+        ));
+    }
+
+    Ok(Secret::new(secret.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use inquire::required;
+
+    use super::*;
+    use crate::init::BranchNameValidator;
+
+    #[test]
+    fn test_confirm_uses_default() {
+        let prompt = InquirePrompter::new(false);
+        assert!(prompt
+            .confirm(Confirm::new("Continue?").with_default(true))
+            .unwrap());
+        assert!(!prompt.confirm(Confirm::new("Continue?")).unwrap());
+    }
+
+    #[test]
+    fn test_text_uses_answer_then_default() {
+        let prompt = InquirePrompter::new(false);
+        let text = || Text::new("Branch name:").with_initial_value("PROJ-1-fix");
+
+        assert_eq!(
+            prompt
+                .text(text(), Some("PROJ-1-other"), "--branch-name")
+                .unwrap(),
+            "PROJ-1-other"
+        );
+        assert_eq!(
+            prompt.text(text(), None, "--branch-name").unwrap(),
+            "PROJ-1-fix"
+        );
+    }
+
+    #[test]
+    fn test_text_without_default_requires_input() {
+        let result = InquirePrompter::new(false).text(
+            Text::new("What's your username?").with_validator(required!()),
+            None,
+            "--jira-user",
+        );
+        assert!(
+            matches!(result, Err(PromptError::InputRequired(_, hint)) if hint == "--jira-user")
+        );
+    }
+
+    #[test]
+    fn test_text_validates_answer() {
+        let result = InquirePrompter::new(true).text(
+            Text::new("Branch name:").with_validator(BranchNameValidator::default()),
+            Some("bad..name"),
+            "--branch-name",
+        );
+        assert!(
+            matches!(result, Err(PromptError::InvalidAnswer(answer, _)) if answer == "bad..name")
+        );
+    }
+
+    #[test]
+    fn test_select_answer_and_default() {
+        let prompt = InquirePrompter::new(false);
+        let select = || Select::new("Base branch?", vec!["main", "develop"]);
+
+        assert_eq!(prompt.select(select(), Some("develop")).unwrap(), "develop");
+        assert_eq!(prompt.select(select(), None).unwrap(), "main");
+        assert!(matches!(
+            prompt.select(select(), Some("other")),
+            Err(PromptError::InvalidAnswer(_, _))
+        ));
+    }
+
+    #[test]
+    fn test_password_requires_answer() {
+        let prompt = InquirePrompter::new(false);
+        let result = prompt.password(Password::new("Token?"), None, "--jira-token-stdin");
+        assert!(matches!(result, Err(PromptError::InputRequired(_, _))));
+
+        let secret = Secret::new("token".to_string());
+        let result = prompt.password(Password::new("Token?"), Some(&secret), "--jira-token-stdin");
+        assert_eq!(result.unwrap().expose(), "token");
+    }
+}
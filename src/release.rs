@@ -0,0 +1,436 @@
+use std::fmt::{self, Display};
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
+use regex::Regex;
+use thiserror::Error;
+
+use crate::conventional;
+use crate::git::CommitInfo;
+
+#[derive(Debug, Error)]
+pub enum ReleaseError {
+    #[error("No main branch configured, please set main in the branches section of .workflow")]
+    MainBranchNotSet,
+    #[error("Invalid version {0}, expected major.minor.patch")]
+    InvalidVersion(String),
+    #[error("Version {0} must be greater than the last release {1}")]
+    VersionNotIncreased(String, String),
+    #[error("{0} is not a release branch, expected {1}<version>")]
+    NotAReleaseBranch(String, String),
+    #[error("Cannot find the version in {0}")]
+    VersionNotFound(String),
+    #[error("{0} has version {1} but the last release is {2}, please fix it before releasing")]
+    UnexpectedVersion(String, String, String),
+    #[error("Cannot update the version in {0}: {1}")]
+    CannotUpdateVersionFile(String, io::Error),
+    #[error("{0} is merged into {1} but cannot be merged back into {2}, conflicts in: {}, please merge {1} into {2} yourself", .3.join(", "))]
+    MergeBackConflict(String, String, String, Vec<String>),
+    #[error("{0} is not a hotfix branch, expected {1}<name>")]
+    NotAHotfixBranch(String, String),
+    #[error("{0} cannot be merged into every branch, {}, please merge it yourself and finish again", describe_conflicts(.1))]
+    HotfixConflicts(String, Vec<(String, Vec<String>)>),
+}
+
+/// Conflicting files by target branch, e.g. `conflicts in a.rs on main`
+pub fn describe_conflicts(conflicts: &[(String, Vec<String>)]) -> String {
+    let conflicts: Vec<String> = conflicts
+        .iter()
+        .map(|(target, files)| format!("conflicts in {} on {}", files.join(", "), target))
+        .collect();
+    conflicts.join("; ")
+}
+
+/// Semantic version, `major.minor.patch`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+/// Part of the version to increment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Bump {
+    /// Breaking changes
+    Major,
+    /// New features
+    Minor,
+    /// Bug fixes only
+    Patch,
+}
+
+impl Version {
+    /// Parse a version, with or without the tag prefix, e.g. `v1.2.3` with the prefix `v`
+    pub fn parse(text: &str, prefix: &str) -> Result<Self, ReleaseError> {
+        let invalid = || ReleaseError::InvalidVersion(text.to_string());
+        let numbers: Vec<u64> = text
+            .strip_prefix(prefix)
+            .unwrap_or(text)
+            .split('.')
+            .map(|number| number.parse().map_err(|_| invalid()))
+            .collect::<Result<_, _>>()?;
+
+        match numbers[..] {
+            [major, minor, patch] => Ok(Self {
+                major,
+                minor,
+                patch,
+            }),
+            _ => Err(invalid()),
+        }
+    }
+
+    pub fn bump(&self, bump: Bump) -> Self {
+        match bump {
+            Bump::Major => Self {
+                major: self.major + 1,
+                minor: 0,
+                patch: 0,
+            },
+            Bump::Minor => Self {
+                minor: self.minor + 1,
+                patch: 0,
+                ..*self
+            },
+            Bump::Patch => Self {
+                patch: self.patch + 1,
+                ..*self
+            },
+        }
+    }
+}
+
+impl Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// Whether a tag is the tag prefix followed by a version, e.g. `v1.2.3` with the prefix `v`
+pub fn is_release_tag(tag: &str, prefix: &str) -> bool {
+    tag.starts_with(prefix) && Version::parse(tag, prefix).is_ok()
+}
+
+/// Bump required by the Conventional Commits types of the commits since the last release
+pub fn bump_for(commits: &[CommitInfo]) -> Bump {
+    let parsed: Vec<_> = commits
+        .iter()
+        .filter_map(|commit| conventional::parse(&commit.message).ok())
+        .collect();
+
+    if parsed.iter().any(|commit| commit.breaking) {
+        Bump::Major
+    } else if parsed.iter().any(|commit| commit.kind == "feat") {
+        Bump::Minor
+    } else {
+        Bump::Patch
+    }
+}
+
+/// Files holding the version when none is configured
+pub const DEFAULT_VERSION_FILES: [&str; 2] = ["Cargo.toml", "package.json"];
+
+/// Version of the package in `Cargo.toml` or `package.json`, `None` for other files
+pub fn read_version(workdir: &Path, file: &Path) -> Result<Option<Version>, ReleaseError> {
+    let content = read_version_file(&workdir.join(file))?;
+    match package_version(file, &content)? {
+        Some(span) => Version::parse(&content[span], "").map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Write the new version in a file of the working directory, returns the files changed.
+///
+/// The version of the package is changed in `Cargo.toml`, along with its entry in `Cargo.lock`,
+/// and in `package.json`, it must be the previous version. In other files the first occurrence
+/// of the previous version is replaced.
+pub fn update_version_file(
+    workdir: &Path,
+    file: &Path,
+    previous: &Version,
+    version: &Version,
+) -> Result<Vec<PathBuf>, ReleaseError> {
+    let path = workdir.join(file);
+    let content = read_version_file(&path)?;
+    let file_name = file.file_name().and_then(|name| name.to_str());
+    let version = version.to_string();
+
+    let updated = match package_version(file, &content)? {
+        Some(span) if content[span.clone()] != previous.to_string() => {
+            return Err(ReleaseError::UnexpectedVersion(
+                file.display().to_string(),
+                content[span].to_string(),
+                previous.to_string(),
+            ))
+        }
+        Some(span) => format!(
+            "{}{}{}",
+            &content[..span.start],
+            version,
+            &content[span.end..]
+        ),
+        None => content
+            .contains(&previous.to_string())
+            .then(|| content.replacen(&previous.to_string(), &version, 1))
+            .ok_or_else(|| ReleaseError::VersionNotFound(file.display().to_string()))?,
+    };
+    write_version_file(&path, &updated)?;
+
+    let mut changed = vec![file.to_path_buf()];
+    let lock_file = file.with_file_name("Cargo.lock");
+    if file_name == Some("Cargo.toml")
+        && update_cargo_lock(workdir, &lock_file, &content, &version)?
+    {
+        changed.push(lock_file);
+    }
+    Ok(changed)
+}
+
+/// Write the version of the package of `manifest` in the lock file, if there is one
+fn update_cargo_lock(
+    workdir: &Path,
+    lock_file: &Path,
+    manifest: &str,
+    version: &str,
+) -> Result<bool, ReleaseError> {
+    let path = workdir.join(lock_file);
+    let package = &manifest[manifest.find("[package]").unwrap_or(0)..];
+    let name = Regex::new(r#"(?m)^name\s*=\s*"([^"]+)""#)
+        .ok()
+        .and_then(|regex| regex.captures(package))
+        .map(|captures| captures[1].to_string());
+    let name = match name {
+        Some(name) if path.exists() => name,
+        _ => return Ok(false),
+    };
+
+    let lock = read_version_file(&path)?;
+    let pattern = format!(
+        r#"(name = "{}"\r?\nversion = ")[^"]*(")"#,
+        regex::escape(&name)
+    );
+    match replace_first(&lock, 0, &pattern, version) {
+        Some(updated) => write_version_file(&path, &updated).map(|_| true),
+        None => Ok(false),
+    }
+}
+
+/// Position of the package version in `Cargo.toml` or `package.json`, `None` for other files
+fn package_version(file: &Path, content: &str) -> Result<Option<Range<usize>>, ReleaseError> {
+    let not_found = || ReleaseError::VersionNotFound(file.display().to_string());
+    let (start, pattern) = match file.file_name().and_then(|name| name.to_str()) {
+        Some("Cargo.toml") => (
+            content.find("[package]").ok_or_else(not_found)?,
+            r#"(?m)^(version\s*=\s*")[^"]*(")"#,
+        ),
+        Some("package.json") => (0, r#"("version"\s*:\s*")[^"]*(")"#),
+        _ => return Ok(None),
+    };
+    find_between(content, start, pattern)
+        .map(Some)
+        .ok_or_else(not_found)
+}
+
+/// Position of the text between the two groups of the first match after `start`
+fn find_between(content: &str, start: usize, pattern: &str) -> Option<Range<usize>> {
+    let regex = Regex::new(pattern).ok()?;
+    let captures = regex.captures(&content[start..])?;
+    Some(start + captures.get(1)?.end()..start + captures.get(2)?.start())
+}
+
+/// Replace the text between the two groups of the first match after `start`
+fn replace_first(content: &str, start: usize, pattern: &str, value: &str) -> Option<String> {
+    let span = find_between(content, start, pattern)?;
+    Some(format!(
+        "{}{}{}",
+        &content[..span.start],
+        value,
+        &content[span.end..]
+    ))
+}
+
+fn read_version_file(path: &Path) -> Result<String, ReleaseError> {
+    fs::read_to_string(path)
+        .map_err(|e| ReleaseError::CannotUpdateVersionFile(path.display().to_string(), e))
+}
+
+fn write_version_file(path: &Path, content: &str) -> Result<(), ReleaseError> {
+    fs::write(path, content)
+        .map_err(|e| ReleaseError::CannotUpdateVersionFile(path.display().to_string(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commit(message: &str) -> CommitInfo {
+        CommitInfo {
+            id: "a1b2c3d4e5".to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    fn version(major: u64, minor: u64, patch: u64) -> Version {
+        Version {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    #[test]
+    fn test_parse_and_bump_version() {
+        let current = Version::parse("v1.2.3", "v").unwrap();
+
+        assert_eq!(current, version(1, 2, 3));
+        assert_eq!(current.bump(Bump::Major).to_string(), "2.0.0");
+        assert_eq!(current.bump(Bump::Minor).to_string(), "1.3.0");
+        assert_eq!(current.bump(Bump::Patch).to_string(), "1.2.4");
+        assert_eq!(Version::parse("1.2.3", "v").unwrap(), current);
+        assert!(matches!(
+            Version::parse("v1.2", "v"),
+            Err(ReleaseError::InvalidVersion(_))
+        ));
+        assert!(Version::parse("1.2.3-rc.1", "v").is_err());
+    }
+
+    #[test]
+    fn test_is_release_tag() {
+        assert!(is_release_tag("v1.2.3", "v"));
+        assert!(is_release_tag("1.2.3", ""));
+        assert!(!is_release_tag("1.2.3", "v"));
+        assert!(!is_release_tag("v-old", "v"));
+        assert!(!is_release_tag("vendor-x", "v"));
+    }
+
+    #[test]
+    fn test_bump_for_commit_types() {
+        assert_eq!(bump_for(&[commit("fix: a"), commit("Update")]), Bump::Patch);
+        assert_eq!(
+            bump_for(&[commit("fix: a"), commit("feat: b")]),
+            Bump::Minor
+        );
+        assert_eq!(
+            bump_for(&[commit("feat: b"), commit("fix!: c")]),
+            Bump::Major
+        );
+        assert_eq!(bump_for(&[]), Bump::Patch);
+    }
+
+    #[test]
+    fn test_update_cargo_version() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("Cargo.toml"),
+            "[package]\nname = \"app\"\nversion = \"1.2.3\"\n\n[dependencies]\nregex = { version = \"1.9.1\" }\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("Cargo.lock"),
+            "[[package]]\nname = \"aho\"\nversion = \"1.2.3\"\n\n[[package]]\nname = \"app\"\nversion = \"1.2.3\"\n",
+        )
+        .unwrap();
+
+        let changed = update_version_file(
+            dir.path(),
+            Path::new("Cargo.toml"),
+            &version(1, 2, 3),
+            &version(1, 3, 0),
+        )
+        .unwrap();
+
+        assert_eq!(
+            changed,
+            vec![PathBuf::from("Cargo.toml"), PathBuf::from("Cargo.lock")]
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("Cargo.toml")).unwrap(),
+            "[package]\nname = \"app\"\nversion = \"1.3.0\"\n\n[dependencies]\nregex = { version = \"1.9.1\" }\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("Cargo.lock")).unwrap(),
+            "[[package]]\nname = \"aho\"\nversion = \"1.2.3\"\n\n[[package]]\nname = \"app\"\nversion = \"1.3.0\"\n"
+        );
+    }
+
+    #[test]
+    fn test_update_package_json_and_other_files() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("package.json"),
+            "{\n  \"name\": \"app\",\n  \"version\": \"1.2.3\"\n}\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("VERSION"), "1.2.3\n").unwrap();
+
+        assert_eq!(
+            read_version(dir.path(), Path::new("package.json")).unwrap(),
+            Some(version(1, 2, 3))
+        );
+        assert_eq!(
+            read_version(dir.path(), Path::new("VERSION")).unwrap(),
+            None
+        );
+
+        update_version_file(
+            dir.path(),
+            Path::new("package.json"),
+            &version(1, 2, 3),
+            &version(2, 0, 0),
+        )
+        .unwrap();
+        update_version_file(
+            dir.path(),
+            Path::new("VERSION"),
+            &version(1, 2, 3),
+            &version(2, 0, 0),
+        )
+        .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(dir.path().join("package.json")).unwrap(),
+            "{\n  \"name\": \"app\",\n  \"version\": \"2.0.0\"\n}\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("VERSION")).unwrap(),
+            "2.0.0\n"
+        );
+        assert!(matches!(
+            update_version_file(
+                dir.path(),
+                Path::new("VERSION"),
+                &version(1, 2, 3),
+                &version(2, 0, 1),
+            ),
+            Err(ReleaseError::VersionNotFound(file)) if file == "VERSION"
+        ));
+    }
+
+    #[test]
+    fn test_refuse_to_update_another_version() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = "[package]\nname = \"app\"\nversion = \"1.2.3\"\n";
+        fs::write(dir.path().join("Cargo.toml"), manifest).unwrap();
+
+        let result = update_version_file(
+            dir.path(),
+            Path::new("Cargo.toml"),
+            &version(0, 0, 0),
+            &version(0, 1, 0),
+        );
+
+        assert!(matches!(
+            result,
+            Err(ReleaseError::UnexpectedVersion(file, found, previous))
+                if file == "Cargo.toml" && found == "1.2.3" && previous == "0.0.0"
+        ));
+        assert_eq!(
+            fs::read_to_string(dir.path().join("Cargo.toml")).unwrap(),
+            manifest
+        );
+    }
+}
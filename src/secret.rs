@@ -0,0 +1,465 @@
+use std::collections::BTreeMap;
+use std::fmt::{Debug, Display};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+
+use argon2::Argon2;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use confy::ConfyError;
+use inquire::Password;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::prompt::{InquirePrompter, PromptError, Prompter};
+
+/// Environment variable read before prompting for the passphrase of the encrypted secrets file
+pub const PASSPHRASE_ENV: &str = "WF_SECRETS_PASSPHRASE";
+
+const NONCE_LEN: usize = 12;
+
+/// Passphrase that decrypted the file, kept for the duration of the process to only prompt once
+static PASSPHRASE: Mutex<Option<String>> = Mutex::new(None);
+
+/// A secret value, never printed by `Debug`
+#[derive(Clone)]
+pub struct Secret(String);
+
+/// Where a secret is stored, this is what the configuration holds instead of the secret itself
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SecretRef {
+    /// Clear text value, as written by previous versions
+    Plain(String),
+    /// Entry in the OS keyring (Secret Service, macOS Keychain, Windows Credential Manager)
+    Keyring { keyring: String },
+    /// Entry in the passphrase protected secrets file
+    EncryptedFile { encrypted_file: String },
+    /// Command printing the secret on its standard output, e.g. `pass show jira`
+    Command { command: String },
+}
+
+/// Kind of storage offered when initializing the configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecretStorage {
+    Keyring,
+    EncryptedFile,
+    Command,
+}
+
+#[derive(Debug, Error)]
+pub enum SecretError {
+    #[error("Keyring error: {0}")]
+    KeyringError(#[from] keyring::Error),
+    #[error("Secrets file error: {0}")]
+    FileError(#[from] ConfyError),
+    #[error("Secrets file is corrupted: {0}")]
+    CorruptedFile(String),
+    #[error("Cannot decrypt secret {0}, is the passphrase correct?")]
+    DecryptionFailed(String),
+    #[error("Secret {0} not found")]
+    NotFound(String),
+    #[error("Secret command `{0}` failed: {1}")]
+    CommandFailed(String, String),
+    #[error("Secrets cannot be stored with a command, store it yourself and configure the command to read it")]
+    ReadOnly,
+    #[error("Input error {0}")]
+    PromptError(#[from] PromptError),
+}
+
+pub trait SecretBackend {
+    fn get(&self, name: &str) -> Result<Secret, SecretError>;
+
+    fn set(&self, name: &str, secret: &Secret) -> Result<(), SecretError>;
+}
+
+/// Secrets stored in the OS keyring under the application name
+pub struct KeyringBackend;
+
+/// Secrets encrypted with a key derived from a passphrase and stored in a local file
+pub struct EncryptedFileBackend {
+    path: PathBuf,
+    passphrase: Option<String>,
+    prompter: InquirePrompter,
+}
+
+/// Secrets read from the output of a command, the name being the command line
+pub struct CommandBackend;
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+struct EncryptedSecrets {
+    salt: Option<String>,
+    #[serde(default)]
+    entries: BTreeMap<String, String>,
+}
+
+impl Secret {
+    pub fn new(value: String) -> Self {
+        Self(value)
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Debug for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Secret(***)")
+    }
+}
+
+impl Debug for SecretRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SecretRef::Plain(_) => write!(f, "Plain(***)"),
+            SecretRef::Keyring { keyring } => write!(f, "Keyring({})", keyring),
+            SecretRef::EncryptedFile { encrypted_file } => {
+                write!(f, "EncryptedFile({})", encrypted_file)
+            }
+            SecretRef::Command { command } => write!(f, "Command({})", command),
+        }
+    }
+}
+
+impl Display for SecretStorage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SecretStorage::Keyring => write!(f, "OS keyring"),
+            SecretStorage::EncryptedFile => write!(f, "Encrypted file protected by a passphrase"),
+            SecretStorage::Command => write!(f, "External command (e.g. pass show jira)"),
+        }
+    }
+}
+
+impl SecretRef {
+    /// Read the secret from its storage, the prompter asks for the passphrase of the secrets file
+    pub fn resolve(&self, prompter: InquirePrompter) -> Result<Secret, SecretError> {
+        match self {
+            SecretRef::Plain(value) => Ok(Secret(value.clone())),
+            SecretRef::Keyring { keyring } => KeyringBackend.get(keyring),
+            SecretRef::EncryptedFile { encrypted_file } => {
+                EncryptedFileBackend::default_location(prompter)?.get(encrypted_file)
+            }
+            SecretRef::Command { command } => CommandBackend.get(command),
+        }
+    }
+
+    /// Store a secret in the given storage and return the reference to put in the configuration
+    pub fn store(
+        storage: SecretStorage,
+        name: &str,
+        secret: &Secret,
+        prompter: InquirePrompter,
+    ) -> Result<Self, SecretError> {
+        match storage {
+            SecretStorage::Keyring => {
+                KeyringBackend.set(name, secret)?;
+                Ok(SecretRef::Keyring {
+                    keyring: name.to_string(),
+                })
+            }
+            SecretStorage::EncryptedFile => {
+                EncryptedFileBackend::default_location(prompter)?.set(name, secret)?;
+                Ok(SecretRef::EncryptedFile {
+                    encrypted_file: name.to_string(),
+                })
+            }
+            SecretStorage::Command => Err(SecretError::ReadOnly),
+        }
+    }
+
+    pub fn storage(&self) -> Option<SecretStorage> {
+        match self {
+            SecretRef::Plain(_) => None,
+            SecretRef::Keyring { .. } => Some(SecretStorage::Keyring),
+            SecretRef::EncryptedFile { .. } => Some(SecretStorage::EncryptedFile),
+            SecretRef::Command { .. } => Some(SecretStorage::Command),
+        }
+    }
+}
+
+impl SecretBackend for KeyringBackend {
+    fn get(&self, name: &str) -> Result<Secret, SecretError> {
+        let entry = keyring::Entry::new(env!("CARGO_PKG_NAME"), name)?;
+        match entry.get_password() {
+            Ok(password) => Ok(Secret(password)),
+            Err(keyring::Error::NoEntry) => Err(SecretError::NotFound(name.to_string())),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn set(&self, name: &str, secret: &Secret) -> Result<(), SecretError> {
+        let entry = keyring::Entry::new(env!("CARGO_PKG_NAME"), name)?;
+        Ok(entry.set_password(secret.expose())?)
+    }
+}
+
+impl EncryptedFileBackend {
+    /// Secrets file next to the configuration file, the passphrase is asked when needed
+    pub fn default_location(prompter: InquirePrompter) -> Result<Self, SecretError> {
+        let path = confy::get_configuration_file_path(env!("CARGO_PKG_NAME"), Some("secrets"))?;
+        Ok(Self {
+            path,
+            passphrase: None,
+            prompter,
+        })
+    }
+
+    /// Secrets file at `path`, without a passphrase it is read from the environment
+    pub fn new(path: &Path, passphrase: Option<String>) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            passphrase,
+            prompter: InquirePrompter::new(false),
+        }
+    }
+
+    fn passphrase(&self, creating: bool) -> Result<String, SecretError> {
+        if let Some(passphrase) = &self.passphrase {
+            return Ok(passphrase.clone());
+        }
+
+        let cached = PASSPHRASE.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        if let Some(passphrase) = cached.or_else(|| std::env::var(PASSPHRASE_ENV).ok()) {
+            return Ok(passphrase);
+        }
+
+        let password = match creating {
+            true => Password::new("Choose a passphrase to protect your secrets:")
+                .with_custom_confirmation_message("Confirm the passphrase:"),
+            false => Password::new("Passphrase of your secrets file:").without_confirmation(),
+        };
+        let passphrase = self.prompter.password(password, None, PASSPHRASE_ENV)?;
+        Ok(passphrase.expose().to_string())
+    }
+
+    /// Keep a passphrase that decrypted the file, unless it was given to this backend
+    fn remember(&self, passphrase: &str) {
+        if self.passphrase.is_none() {
+            *PASSPHRASE.lock().unwrap_or_else(|e| e.into_inner()) = Some(passphrase.to_string());
+        }
+    }
+
+    fn key(passphrase: &str, salt: &[u8]) -> Result<Key, SecretError> {
+        let mut key = Key::default();
+        Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+            .map_err(|e| SecretError::CorruptedFile(e.to_string()))?;
+        Ok(key)
+    }
+
+    fn load(&self) -> Result<EncryptedSecrets, SecretError> {
+        Ok(confy::load_path(&self.path)?)
+    }
+
+    fn decrypt(key: &Key, name: &str, entry: &str) -> Result<Secret, SecretError> {
+        let data = BASE64
+            .decode(entry)
+            .map_err(|e| SecretError::CorruptedFile(e.to_string()))?;
+        if data.len() < NONCE_LEN {
+            return Err(SecretError::CorruptedFile(format!(
+                "entry {} is too short",
+                name
+            )));
+        }
+
+        let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+        let plaintext = ChaCha20Poly1305::new(key)
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| SecretError::DecryptionFailed(name.to_string()))?;
+
+        String::from_utf8(plaintext)
+            .map(Secret)
+            .map_err(|_| SecretError::DecryptionFailed(name.to_string()))
+    }
+}
+
+impl SecretBackend for EncryptedFileBackend {
+    fn get(&self, name: &str) -> Result<Secret, SecretError> {
+        let secrets = self.load()?;
+        let entry = secrets
+            .entries
+            .get(name)
+            .ok_or_else(|| SecretError::NotFound(name.to_string()))?;
+        let salt = secrets
+            .salt
+            .as_ref()
+            .and_then(|s| BASE64.decode(s).ok())
+            .ok_or_else(|| SecretError::CorruptedFile("missing salt".to_string()))?;
+
+        let passphrase = self.passphrase(false)?;
+        let secret = Self::decrypt(&Self::key(&passphrase, &salt)?, name, entry)?;
+        self.remember(&passphrase);
+        Ok(secret)
+    }
+
+    fn set(&self, name: &str, secret: &Secret) -> Result<(), SecretError> {
+        let mut secrets = self.load()?;
+
+        let salt = match secrets.salt.as_ref().and_then(|s| BASE64.decode(s).ok()) {
+            Some(salt) => salt,
+            None => {
+                let mut salt = vec![0u8; 16];
+                OsRng.fill_bytes(&mut salt);
+                secrets.salt = Some(BASE64.encode(&salt));
+                salt
+            }
+        };
+
+        let passphrase = self.passphrase(secrets.entries.is_empty())?;
+        let key = Self::key(&passphrase, &salt)?;
+
+        // Make sure the passphrase is the one used for the other entries
+        if let Some((other_name, other_entry)) = secrets.entries.iter().next() {
+            Self::decrypt(&key, other_name, other_entry)?;
+        }
+
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = ChaCha20Poly1305::new(&key)
+            .encrypt(&nonce, secret.expose().as_bytes())
+            .map_err(|_| SecretError::CorruptedFile("encryption failed".to_string()))?;
+
+        let mut data = nonce.to_vec();
+        data.extend(ciphertext);
+        secrets
+            .entries
+            .insert(name.to_string(), BASE64.encode(data));
+
+        confy::store_path(&self.path, secrets)?;
+        self.remember(&passphrase);
+        Ok(())
+    }
+}
+
+impl SecretBackend for CommandBackend {
+    fn get(&self, command: &str) -> Result<Secret, SecretError> {
+        let (shell, flag) = if cfg!(windows) {
+            ("cmd", "/C")
+        } else {
+            ("sh", "-c")
+        };
+
+        let output = Command::new(shell)
+            .args([flag, command])
+            .output()
+            .map_err(|e| SecretError::CommandFailed(command.to_string(), e.to_string()))?;
+
+        if !output.status.success() {
+            return Err(SecretError::CommandFailed(
+                command.to_string(),
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ));
+        }
+
+        // Like `pass`, the secret is the first line of the output
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        match stdout.lines().next().map(str::trim) {
+            Some(secret) if !secret.is_empty() => Ok(Secret(secret.to_string())),
+            _ => Err(SecretError::NotFound(command.to_string())),
+        }
+    }
+
+    fn set(&self, _name: &str, _secret: &Secret) -> Result<(), SecretError> {
+        Err(SecretError::ReadOnly)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_debug_redact_secrets() {
+        let secret = Secret::new("my-token".to_string());
+        let reference = SecretRef::Plain("my-token".to_string());
+
+        assert_eq!(format!("{:?}", secret), "Secret(***)");
+        assert_eq!(format!("{:?}", reference), "Plain(***)");
+    }
+
+    #[test]
+    fn test_encrypted_file_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("secrets.toml");
+        let backend = EncryptedFileBackend::new(&path, Some("passphrase".to_string()));
+
+        backend
+            .set("jira", &Secret::new("jira-token".to_string()))
+            .unwrap();
+        backend
+            .set("github", &Secret::new("github-token".to_string()))
+            .unwrap();
+
+        assert_eq!(backend.get("jira").unwrap().expose(), "jira-token");
+        assert_eq!(backend.get("github").unwrap().expose(), "github-token");
+        assert!(!std::fs::read_to_string(&path)
+            .unwrap()
+            .contains("jira-token"));
+    }
+
+    #[test]
+    fn test_encrypted_file_wrong_passphrase() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("secrets.toml");
+        EncryptedFileBackend::new(&path, Some("passphrase".to_string()))
+            .set("jira", &Secret::new("jira-token".to_string()))
+            .unwrap();
+
+        let backend = EncryptedFileBackend::new(&path, Some("wrong".to_string()));
+
+        assert!(matches!(
+            backend.get("jira"),
+            Err(SecretError::DecryptionFailed(_))
+        ));
+        assert!(matches!(
+            backend.set("github", &Secret::new("github-token".to_string())),
+            Err(SecretError::DecryptionFailed(_))
+        ));
+    }
+
+    #[test]
+    fn test_passphrase_kept_once_it_decrypts() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("secrets.toml");
+        EncryptedFileBackend::new(&path, Some("passphrase".to_string()))
+            .set("jira", &Secret::new("jira-token".to_string()))
+            .unwrap();
+        let backend = EncryptedFileBackend::new(&path, None);
+
+        std::env::remove_var(PASSPHRASE_ENV);
+        assert!(matches!(
+            backend.get("jira"),
+            Err(SecretError::PromptError(PromptError::InputRequired(_, _)))
+        ));
+
+        std::env::set_var(PASSPHRASE_ENV, "wrong");
+        assert!(matches!(
+            backend.get("jira"),
+            Err(SecretError::DecryptionFailed(_))
+        ));
+        assert!(PASSPHRASE.lock().unwrap().is_none());
+
+        std::env::set_var(PASSPHRASE_ENV, "passphrase");
+        assert_eq!(backend.get("jira").unwrap().expose(), "jira-token");
+        std::env::remove_var(PASSPHRASE_ENV);
+        assert_eq!(PASSPHRASE.lock().unwrap().as_deref(), Some("passphrase"));
+        assert_eq!(backend.get("jira").unwrap().expose(), "jira-token");
+        *PASSPHRASE.lock().unwrap() = None;
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_command_backend() {
+        let secret = CommandBackend.get("printf 'token\\nmetadata'").unwrap();
+        assert_eq!(secret.expose(), "token");
+
+        assert!(matches!(
+            CommandBackend.get("exit 1"),
+            Err(SecretError::CommandFailed(_, _))
+        ));
+    }
+}
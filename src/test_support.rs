@@ -0,0 +1,1131 @@
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::fmt::Display;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use git2::{BranchType, Repository, Signature};
+use inquire::{Confirm, Password, Select, Text};
+use mockito::{Matcher, Mock, ServerGuard};
+use tempfile::TempDir;
+
+use crate::config::{JiraConfig, MergeStrategy};
+use crate::git::{
+    push_rejection, CommitInfo, GitError, GitRepository, Operation, Worktree, DEFAULT_REMOTE,
+};
+use crate::jira::{issue_key_from_branch, JiraServer};
+use crate::prompt::{
+    check_text, default_confirm, default_select, default_text, select_option, InquirePrompter,
+    PromptError, Prompter,
+};
+use crate::release;
+use crate::secret::{Secret, SecretRef};
+use crate::tracker::{Issue, IssueTracker, TrackerError};
+
+/// Answer given by the [`ScriptedPrompter`] to the next question
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Answer {
+    Yes,
+    No,
+    /// Text typed by the user, also used for passwords
+    Text(String),
+    /// Option chosen by its displayed value
+    Choice(String),
+    /// Accept what the prompt proposes
+    Default,
+}
+
+/// Prompter replaying answers in order, panics when a question has no answer left
+pub struct ScriptedPrompter {
+    answers: RefCell<VecDeque<Answer>>,
+    questions: RefCell<Vec<String>>,
+}
+
+impl ScriptedPrompter {
+    pub fn new(answers: impl IntoIterator<Item = Answer>) -> Self {
+        Self {
+            answers: RefCell::new(answers.into_iter().collect()),
+            questions: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Questions asked so far
+    pub fn questions(&self) -> Vec<String> {
+        self.questions.borrow().clone()
+    }
+
+    fn next(&self, question: &str) -> Answer {
+        self.questions.borrow_mut().push(question.to_string());
+        self.answers
+            .borrow_mut()
+            .pop_front()
+            .unwrap_or_else(|| panic!("No scripted answer for \"{}\"", question))
+    }
+}
+
+impl Prompter for ScriptedPrompter {
+    fn is_interactive(&self) -> bool {
+        true
+    }
+
+    fn ask_confirm(&self, confirm: Confirm) -> Result<bool, PromptError> {
+        match self.next(confirm.message) {
+            Answer::Yes => Ok(true),
+            Answer::No => Ok(false),
+            Answer::Default => Ok(default_confirm(&confirm)),
+            answer => panic!("Unexpected answer {:?} to \"{}\"", answer, confirm.message),
+        }
+    }
+
+    fn ask_text(&self, text: Text) -> Result<String, PromptError> {
+        match self.next(text.message) {
+            Answer::Text(answer) => check_text(&text, &answer, "a scripted answer"),
+            Answer::Default => check_text(&text, default_text(&text), "a scripted answer"),
+            answer => panic!("Unexpected answer {:?} to \"{}\"", answer, text.message),
+        }
+    }
+
+    fn ask_select<T: Display>(&self, select: Select<T>) -> Result<T, PromptError> {
+        match self.next(select.message) {
+            Answer::Choice(answer) => select_option(select, &answer),
+            Answer::Default => default_select(select),
+            answer => panic!("Unexpected answer {:?} to \"{}\"", answer, select.message),
+        }
+    }
+
+    fn ask_password(&self, password: Password) -> Result<Secret, PromptError> {
+        match self.next(password.message) {
+            Answer::Text(answer) => Ok(Secret::new(answer)),
+            answer => panic!("Unexpected answer {:?} to \"{}\"", answer, password.message),
+        }
+    }
+}
+
+/// In-memory repository recording what the commands do
+pub struct FakeGitRepository {
+    state: Rc<FakeRepositoryState>,
+    remote: String,
+}
+
+/// State of a [`FakeGitRepository`], shared with the handle on its main worktree
+pub struct FakeRepositoryState {
+    workdir: Option<PathBuf>,
+    branches: RefCell<Vec<String>>,
+    current_branch: RefCell<String>,
+    remote_url: String,
+    created_branches: RefCell<Vec<(String, String)>>,
+    checkouts: RefCell<Vec<String>>,
+    worktrees: RefCell<Vec<Worktree>>,
+    removed_worktrees: RefCell<Vec<PathBuf>>,
+    pushes: RefCell<Vec<String>>,
+    forced_pushes: RefCell<Vec<String>>,
+    push_rejection: RefCell<Option<String>>,
+    dirty: Cell<bool>,
+    conflicts: RefCell<Vec<String>>,
+    fetches: RefCell<Vec<String>>,
+    stashes: RefCell<Vec<String>>,
+    checkout_conflict: Cell<bool>,
+    fast_forwards: RefCell<Vec<String>>,
+    merges: RefCell<Vec<Merge>>,
+    rebases: RefCell<Vec<String>>,
+    in_progress: Cell<Option<Operation>>,
+    deleted_branches: RefCell<Vec<String>>,
+    deleted_remote_branches: RefCell<Vec<String>>,
+    ahead_behind: RefCell<Vec<(String, String, usize, usize)>>,
+    commits: RefCell<Vec<CommitInfo>>,
+    commit_ranges: RefCell<Vec<String>>,
+    last_tag: RefCell<Option<String>>,
+    committed_files: RefCell<Vec<(Vec<PathBuf>, String)>>,
+    tags: RefCell<Vec<String>>,
+    pushed_refs: RefCell<Vec<String>>,
+    target_conflicts: RefCell<Vec<(String, Vec<String>)>>,
+    cherry_picks: RefCell<Vec<(String, Vec<String>)>>,
+}
+
+/// Merge done by the [`FakeGitRepository`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Merge {
+    pub branch: String,
+    pub target: String,
+    pub strategy: MergeStrategy,
+    pub message: String,
+}
+
+impl Deref for FakeGitRepository {
+    type Target = FakeRepositoryState;
+
+    fn deref(&self) -> &Self::Target {
+        &self.state
+    }
+}
+
+impl FakeGitRepository {
+    pub fn new(workdir: Option<&Path>, branches: &[&str], current_branch: &str) -> Self {
+        let state = FakeRepositoryState {
+            workdir: workdir.map(Path::to_path_buf),
+            branches: RefCell::new(branches.iter().map(|b| b.to_string()).collect()),
+            current_branch: RefCell::new(current_branch.to_string()),
+            remote_url: "git@github.com:owner/repo.git".to_string(),
+            created_branches: RefCell::new(Vec::new()),
+            checkouts: RefCell::new(Vec::new()),
+            worktrees: RefCell::new(Vec::new()),
+            removed_worktrees: RefCell::new(Vec::new()),
+            pushes: RefCell::new(Vec::new()),
+            forced_pushes: RefCell::new(Vec::new()),
+            push_rejection: RefCell::new(None),
+            dirty: Cell::new(false),
+            conflicts: RefCell::new(Vec::new()),
+            fetches: RefCell::new(Vec::new()),
+            stashes: RefCell::new(Vec::new()),
+            checkout_conflict: Cell::new(false),
+            fast_forwards: RefCell::new(Vec::new()),
+            merges: RefCell::new(Vec::new()),
+            rebases: RefCell::new(Vec::new()),
+            in_progress: Cell::new(None),
+            deleted_branches: RefCell::new(Vec::new()),
+            deleted_remote_branches: RefCell::new(Vec::new()),
+            ahead_behind: RefCell::new(Vec::new()),
+            commits: RefCell::new(Vec::new()),
+            commit_ranges: RefCell::new(Vec::new()),
+            last_tag: RefCell::new(None),
+            committed_files: RefCell::new(Vec::new()),
+            tags: RefCell::new(Vec::new()),
+            pushed_refs: RefCell::new(Vec::new()),
+            target_conflicts: RefCell::new(Vec::new()),
+            cherry_picks: RefCell::new(Vec::new()),
+        };
+        Self {
+            state: Rc::new(state),
+            remote: DEFAULT_REMOTE.to_string(),
+        }
+    }
+
+    /// Set the commit counts returned by `ahead_behind` for these branches
+    pub fn set_ahead_behind(&self, branch: &str, other: &str, ahead: usize, behind: usize) {
+        self.ahead_behind
+            .borrow_mut()
+            .push((branch.to_string(), other.to_string(), ahead, behind));
+    }
+
+    /// Pretend there are uncommitted changes
+    pub fn set_dirty(&self, dirty: bool) {
+        self.dirty.set(dirty);
+    }
+
+    /// Make the next merges and rebases stop with conflicts in these files, an empty list resolves them
+    pub fn set_conflicts(&self, files: &[&str]) {
+        *self.conflicts.borrow_mut() = files.iter().map(|f| f.to_string()).collect();
+    }
+
+    /// Make checkouts fail while the working tree is dirty, as if local changes conflicted
+    pub fn set_checkout_conflict(&self, conflict: bool) {
+        self.checkout_conflict.set(conflict);
+    }
+
+    /// Messages of the stashes not popped yet
+    pub fn stashes(&self) -> Vec<String> {
+        self.stashes.borrow().clone()
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.dirty.get()
+    }
+
+    /// Branches fetched so far
+    pub fn fetches(&self) -> Vec<String> {
+        self.fetches.borrow().clone()
+    }
+
+    pub fn fast_forwards(&self) -> Vec<String> {
+        self.fast_forwards.borrow().clone()
+    }
+
+    pub fn merges(&self) -> Vec<Merge> {
+        self.merges.borrow().clone()
+    }
+
+    /// Branches the current branch was rebased onto so far
+    pub fn rebases(&self) -> Vec<String> {
+        self.rebases.borrow().clone()
+    }
+
+    pub fn deleted_branches(&self) -> Vec<String> {
+        self.deleted_branches.borrow().clone()
+    }
+
+    pub fn deleted_remote_branches(&self) -> Vec<String> {
+        self.deleted_remote_branches.borrow().clone()
+    }
+
+    pub fn current(&self) -> String {
+        self.current_branch.borrow().clone()
+    }
+
+    /// Branches created so far with the branch they were created from
+    pub fn created_branches(&self) -> Vec<(String, String)> {
+        self.created_branches.borrow().clone()
+    }
+
+    /// Branches checked out so far
+    pub fn checkouts(&self) -> Vec<String> {
+        self.checkouts.borrow().clone()
+    }
+
+    /// Linked worktrees removed so far
+    pub fn removed_worktrees(&self) -> Vec<PathBuf> {
+        self.removed_worktrees.borrow().clone()
+    }
+
+    /// Branches pushed so far
+    pub fn pushes(&self) -> Vec<String> {
+        self.pushes.borrow().clone()
+    }
+
+    /// Branches pushed with `--force-with-lease` so far
+    pub fn forced_pushes(&self) -> Vec<String> {
+        self.forced_pushes.borrow().clone()
+    }
+
+    /// Set the commits returned by `commits` whatever the range, newest first, as ids and messages
+    pub fn set_commits(&self, commits: &[(&str, &str)]) {
+        *self.commits.borrow_mut() = commits
+            .iter()
+            .map(|(id, message)| CommitInfo {
+                id: id.to_string(),
+                message: message.to_string(),
+            })
+            .collect();
+    }
+
+    /// Ranges of the commits listed so far
+    pub fn commit_ranges(&self) -> Vec<String> {
+        self.commit_ranges.borrow().clone()
+    }
+
+    pub fn set_last_tag(&self, tag: &str) {
+        *self.last_tag.borrow_mut() = Some(tag.to_string());
+    }
+
+    /// Make merges into `target` and cherry-picks onto it stop with conflicts in these files
+    pub fn set_conflicts_into(&self, target: &str, files: &[&str]) {
+        self.target_conflicts.borrow_mut().push((
+            target.to_string(),
+            files.iter().map(|f| f.to_string()).collect(),
+        ));
+    }
+
+    /// Branches commits were cherry-picked onto so far, with the commits
+    pub fn cherry_picks(&self) -> Vec<(String, Vec<String>)> {
+        self.cherry_picks.borrow().clone()
+    }
+
+    /// Files committed so far with the message of their commit
+    pub fn committed_files(&self) -> Vec<(Vec<PathBuf>, String)> {
+        self.committed_files.borrow().clone()
+    }
+
+    /// Tags created so far
+    pub fn tags(&self) -> Vec<String> {
+        self.tags.borrow().clone()
+    }
+
+    /// Branches and tags pushed with `push_refs` so far
+    pub fn pushed_refs(&self) -> Vec<String> {
+        self.pushed_refs.borrow().clone()
+    }
+
+    /// Make the remote reject the next pushes with this reason, like "non-fast-forward"
+    pub fn set_push_rejection(&self, reason: &str) {
+        *self.push_rejection.borrow_mut() = Some(reason.to_string());
+    }
+}
+
+impl GitRepository for FakeGitRepository {
+    fn with_remote(self, remote: &str) -> Self {
+        Self {
+            remote: remote.to_string(),
+            ..self
+        }
+    }
+
+    fn remote(&self) -> &str {
+        &self.remote
+    }
+
+    fn create_and_checkout_branch(
+        &self,
+        new_branch: &str,
+        from_branch: &str,
+    ) -> Result<(), GitError> {
+        let mut branches = self.branches.borrow_mut();
+        if !branches.iter().any(|b| b == from_branch) {
+            return Err(GitError::BranchNotFound(from_branch.to_string()));
+        }
+        if branches.iter().any(|b| b == new_branch) {
+            return Err(GitError::BranchExists(new_branch.to_string()));
+        }
+        if self.dirty.get() && self.checkout_conflict.get() {
+            return Err(GitError::CommandFailed(
+                format!("checkout -b {}", new_branch),
+                "local changes would be overwritten".to_string(),
+            ));
+        }
+
+        branches.push(new_branch.to_string());
+        self.created_branches
+            .borrow_mut()
+            .push((new_branch.to_string(), from_branch.to_string()));
+        self.checkouts.borrow_mut().push(new_branch.to_string());
+        *self.current_branch.borrow_mut() = new_branch.to_string();
+        Ok(())
+    }
+
+    fn checkout_branch(&self, branch: &str) -> Result<(), GitError> {
+        if !self.branches.borrow().iter().any(|b| b == branch) {
+            return Err(GitError::BranchNotFound(branch.to_string()));
+        }
+        self.checkouts.borrow_mut().push(branch.to_string());
+        *self.current_branch.borrow_mut() = branch.to_string();
+        Ok(())
+    }
+
+    fn add_worktree(
+        &self,
+        path: &Path,
+        new_branch: &str,
+        from_branch: &str,
+    ) -> Result<(), GitError> {
+        if self.branches.borrow().iter().any(|b| b == new_branch) {
+            return Err(GitError::BranchExists(new_branch.to_string()));
+        }
+        self.created_branches
+            .borrow_mut()
+            .push((new_branch.to_string(), from_branch.to_string()));
+        self.branches.borrow_mut().push(new_branch.to_string());
+        self.worktrees.borrow_mut().push(Worktree {
+            path: path.to_path_buf(),
+            branch: Some(new_branch.to_string()),
+        });
+        Ok(())
+    }
+
+    fn hooks_dir(&self) -> Result<PathBuf, GitError> {
+        let workdir = self
+            .workdir
+            .as_ref()
+            .ok_or(GitError::CannotOpenRepository)?;
+        Ok(workdir.join(".git").join("hooks"))
+    }
+
+    fn worktrees(&self) -> Result<Vec<Worktree>, GitError> {
+        Ok(self.worktrees.borrow().clone())
+    }
+
+    fn main_worktree(&self) -> Result<Self, GitError> {
+        Ok(Self {
+            state: Rc::clone(&self.state),
+            remote: self.remote.clone(),
+        })
+    }
+
+    fn remove_worktree(&self, path: &Path) -> Result<(), GitError> {
+        let mut worktrees = self.worktrees.borrow_mut();
+        let index = worktrees
+            .iter()
+            .position(|w| w.path == path)
+            .ok_or_else(|| GitError::WorktreeNotFound(path.display().to_string()))?;
+        worktrees.remove(index);
+        self.removed_worktrees.borrow_mut().push(path.to_path_buf());
+        Ok(())
+    }
+
+    fn push(&self, force_with_lease: bool) -> Result<(), GitError> {
+        if let Some(reason) = self.push_rejection.borrow().as_deref() {
+            return Err(push_rejection(
+                &format!("refs/heads/{}", self.current()),
+                reason,
+            ));
+        }
+
+        self.pushes.borrow_mut().push(self.current());
+        if force_with_lease {
+            self.forced_pushes.borrow_mut().push(self.current());
+        }
+        Ok(())
+    }
+
+    fn workdir(&self) -> Option<&Path> {
+        self.workdir.as_deref()
+    }
+
+    fn branches(&self) -> Result<Vec<String>, GitError> {
+        Ok(self.branches.borrow().clone())
+    }
+
+    fn current_branch(&self) -> Result<String, GitError> {
+        Ok(self.current())
+    }
+
+    fn commits(&self, range: &str) -> Result<Vec<CommitInfo>, GitError> {
+        self.commit_ranges.borrow_mut().push(range.to_string());
+        Ok(self.commits.borrow().clone())
+    }
+
+    fn last_tag(&self, _revision: &str, prefix: &str) -> Result<Option<String>, GitError> {
+        let last_tag = self.last_tag.borrow();
+        Ok(last_tag
+            .clone()
+            .filter(|tag| release::is_release_tag(tag, prefix)))
+    }
+
+    fn commit_files(&self, files: &[&Path], message: &str) -> Result<(), GitError> {
+        self.committed_files.borrow_mut().push((
+            files.iter().map(|file| file.to_path_buf()).collect(),
+            message.to_string(),
+        ));
+        Ok(())
+    }
+
+    fn create_tag(&self, tag: &str, _message: &str) -> Result<(), GitError> {
+        let mut tags = self.tags.borrow_mut();
+        if tags.iter().any(|t| t == tag) {
+            return Err(GitError::TagExists(tag.to_string()));
+        }
+        tags.push(tag.to_string());
+        Ok(())
+    }
+
+    fn push_refs(&self, refs: &[String]) -> Result<(), GitError> {
+        if let Some(reason) = self.push_rejection.borrow().as_deref() {
+            return Err(push_rejection(&refs[0], reason));
+        }
+        self.pushed_refs.borrow_mut().extend(refs.iter().cloned());
+        Ok(())
+    }
+
+    fn remote_url(&self) -> Result<String, GitError> {
+        Ok(self.remote_url.clone())
+    }
+
+    fn needs_push(&self) -> Result<bool, GitError> {
+        Ok(!self.pushes.borrow().contains(&self.current()))
+    }
+
+    fn ahead_behind(&self, branch: &str, other: &str) -> Result<Option<(usize, usize)>, GitError> {
+        Ok(self
+            .ahead_behind
+            .borrow()
+            .iter()
+            .find(|(b, o, _, _)| b == branch && o == other)
+            .map(|(_, _, ahead, behind)| (*ahead, *behind)))
+    }
+
+    fn is_clean(&self) -> Result<bool, GitError> {
+        Ok(!self.dirty.get())
+    }
+
+    fn stash(&self, message: &str) -> Result<(), GitError> {
+        self.stashes.borrow_mut().push(message.to_string());
+        self.dirty.set(false);
+        Ok(())
+    }
+
+    fn stash_pop(&self) -> Result<(), GitError> {
+        match self.stashes.borrow_mut().pop() {
+            Some(_) => {
+                self.dirty.set(true);
+                Ok(())
+            }
+            None => Err(GitError::CannotRestoreStash(
+                self.current(),
+                "no stash".to_string(),
+            )),
+        }
+    }
+
+    fn fetch(&self, branch: &str) -> Result<(), GitError> {
+        self.fetches.borrow_mut().push(branch.to_string());
+        Ok(())
+    }
+
+    fn fast_forward(&self, branch: &str) -> Result<(), GitError> {
+        if !self.branches.borrow().iter().any(|b| b == branch) {
+            return Err(GitError::BranchNotFound(branch.to_string()));
+        }
+        let remote_branch = self.remote_branch(branch);
+        if let Some((ahead, behind)) = self.ahead_behind(branch, &remote_branch)? {
+            if ahead > 0 && behind > 0 {
+                return Err(GitError::Diverged(branch.to_string(), remote_branch));
+            }
+        }
+
+        self.fast_forwards.borrow_mut().push(branch.to_string());
+        Ok(())
+    }
+
+    fn merge_into(
+        &self,
+        branch: &str,
+        target: &str,
+        strategy: MergeStrategy,
+        message: &str,
+    ) -> Result<(), GitError> {
+        let target_conflicts = self.target_conflicts.borrow();
+        let conflicts = target_conflicts
+            .iter()
+            .find(|(t, _)| t == target)
+            .map(|(_, files)| files.clone())
+            .unwrap_or_else(|| self.conflicts.borrow().clone());
+        if !conflicts.is_empty() {
+            return Err(GitError::MergeConflict(
+                branch.to_string(),
+                target.to_string(),
+                conflicts,
+            ));
+        }
+
+        self.merges.borrow_mut().push(Merge {
+            branch: branch.to_string(),
+            target: target.to_string(),
+            strategy,
+            message: message.to_string(),
+        });
+        self.checkouts.borrow_mut().push(target.to_string());
+        *self.current_branch.borrow_mut() = target.to_string();
+        Ok(())
+    }
+
+    fn cherry_pick(&self, commits: &[String]) -> Result<(), GitError> {
+        let branch = self.current();
+        let target_conflicts = self.target_conflicts.borrow();
+        if let Some((_, files)) = target_conflicts.iter().find(|(t, _)| *t == branch) {
+            return Err(GitError::CherryPickConflict(
+                commits.first().cloned().unwrap_or_default(),
+                branch,
+                files.clone(),
+            ));
+        }
+
+        self.cherry_picks
+            .borrow_mut()
+            .push((branch, commits.to_vec()));
+        Ok(())
+    }
+
+    fn rebase(&self, onto: &str) -> Result<(), GitError> {
+        self.rebases.borrow_mut().push(onto.to_string());
+
+        let conflicts = self.conflicts.borrow();
+        if !conflicts.is_empty() {
+            self.in_progress.set(Some(Operation::Rebase));
+            return Err(GitError::RebaseConflict(self.current(), conflicts.clone()));
+        }
+        Ok(())
+    }
+
+    fn merge(&self, branch: &str, message: &str) -> Result<(), GitError> {
+        self.merges.borrow_mut().push(Merge {
+            branch: branch.to_string(),
+            target: self.current(),
+            strategy: MergeStrategy::Merge,
+            message: message.to_string(),
+        });
+
+        let conflicts = self.conflicts.borrow();
+        if !conflicts.is_empty() {
+            self.in_progress.set(Some(Operation::Merge));
+            return Err(GitError::MergeConflict(
+                branch.to_string(),
+                self.current(),
+                conflicts.clone(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn operation_in_progress(&self) -> Result<Option<Operation>, GitError> {
+        Ok(self.in_progress.get())
+    }
+
+    fn continue_operation(&self) -> Result<(), GitError> {
+        if self.in_progress.get().is_none() {
+            return Err(GitError::NothingInProgress);
+        }
+
+        let conflicts = self.conflicts.borrow();
+        if !conflicts.is_empty() {
+            return Err(GitError::UnresolvedConflicts(conflicts.clone()));
+        }
+        self.in_progress.set(None);
+        Ok(())
+    }
+
+    fn abort_operation(&self) -> Result<(), GitError> {
+        match self.in_progress.take() {
+            Some(_) => Ok(()),
+            None => Err(GitError::NothingInProgress),
+        }
+    }
+
+    fn delete_branch(&self, branch: &str) -> Result<(), GitError> {
+        self.branches.borrow_mut().retain(|b| b != branch);
+        self.deleted_branches.borrow_mut().push(branch.to_string());
+        Ok(())
+    }
+
+    fn delete_remote_branch(&self, branch: &str) -> Result<(), GitError> {
+        self.deleted_remote_branches
+            .borrow_mut()
+            .push(branch.to_string());
+        Ok(())
+    }
+}
+
+/// In-memory tracker with Jira-like keys
+pub struct FakeIssueTracker {
+    issues: Vec<Issue>,
+    transitions: Mutex<Vec<(String, String)>>,
+    comments: Mutex<Vec<(String, String)>>,
+}
+
+impl FakeIssueTracker {
+    pub fn new(issues: Vec<Issue>) -> Self {
+        Self {
+            issues,
+            transitions: Mutex::new(Vec::new()),
+            comments: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Issue keys and statuses they were moved to
+    pub fn transitions(&self) -> Vec<(String, String)> {
+        self.transitions.lock().unwrap().clone()
+    }
+
+    /// Issue keys and bodies of the comments added
+    pub fn comments(&self) -> Vec<(String, String)> {
+        self.comments.lock().unwrap().clone()
+    }
+}
+
+pub fn issue(key: &str, summary: &str) -> Issue {
+    Issue {
+        key: key.to_string(),
+        summary: summary.to_string(),
+        status: "To Do".to_string(),
+        issue_type: None,
+        url: format!("https://jira.example.com/browse/{}", key),
+    }
+}
+
+#[async_trait]
+impl IssueTracker for FakeIssueTracker {
+    async fn get_issue(&self, key: &str) -> Result<Issue, TrackerError> {
+        self.issues
+            .iter()
+            .find(|i| i.key == key)
+            .cloned()
+            .ok_or_else(|| TrackerError::IssueNotFound(key.to_string()))
+    }
+
+    async fn search_issues(&self, _query: &str) -> Result<Vec<Issue>, TrackerError> {
+        Ok(self.issues.clone())
+    }
+
+    async fn transition_to_status(
+        &self,
+        issue: &Issue,
+        status: &str,
+    ) -> Result<bool, TrackerError> {
+        if issue.status == status {
+            return Ok(false);
+        }
+
+        self.transitions
+            .lock()
+            .unwrap()
+            .push((issue.key.clone(), status.to_string()));
+        Ok(true)
+    }
+
+    async fn add_comment(&self, issue: &Issue, body: &str) -> Result<(), TrackerError> {
+        self.comments
+            .lock()
+            .unwrap()
+            .push((issue.key.clone(), body.to_string()));
+        Ok(())
+    }
+
+    fn default_query(&self) -> &str {
+        "assignee = currentUser()"
+    }
+
+    fn find_issue_key(&self, text: &str) -> Option<String> {
+        issue_key_from_branch(text)
+    }
+}
+
+/// Throwaway on-disk repository cloned from a bare "origin" repository.
+///
+/// Both start with a `main` and a `develop` branch, `main` is checked out in the working copy.
+pub struct TestRepository {
+    // Removed with its content when dropped
+    _dir: TempDir,
+    pub workdir: PathBuf,
+    pub origin: PathBuf,
+}
+
+impl Default for TestRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TestRepository {
+    pub fn new() -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        let origin = dir.path().join("origin.git");
+        let workdir = dir.path().join("work");
+
+        Repository::init_bare(&origin).unwrap();
+        let repo = Repository::init(&workdir).unwrap();
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Test").unwrap();
+        config.set_str("user.email", "test@example.com").unwrap();
+        repo.set_head("refs/heads/main").unwrap();
+
+        let test_repo = Self {
+            _dir: dir,
+            workdir,
+            origin,
+        };
+        test_repo.commit("README.md", "Initial commit");
+
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        repo.branch("develop", &head, false).unwrap();
+
+        let mut remote = repo
+            .remote("origin", test_repo.origin.to_str().unwrap())
+            .unwrap();
+        remote
+            .push(
+                &[
+                    "refs/heads/main:refs/heads/main",
+                    "refs/heads/develop:refs/heads/develop",
+                ],
+                None,
+            )
+            .unwrap();
+
+        test_repo
+    }
+
+    pub fn repository(&self) -> Repository {
+        Repository::open(&self.workdir).unwrap()
+    }
+
+    /// Write a file and commit it on the current branch
+    pub fn commit(&self, file: &str, message: &str) {
+        std::fs::write(self.workdir.join(file), message).unwrap();
+
+        let repo = self.repository();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new(file)).unwrap();
+        index.write().unwrap();
+
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::now("Test", "test@example.com").unwrap();
+        let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+        let parents: Vec<_> = parent.iter().collect();
+
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &parents,
+        )
+        .unwrap();
+    }
+
+    /// Write the resolved content of a conflicting file and add it to the index, like `git add`
+    pub fn resolve(&self, file: &str, content: &str) {
+        std::fs::write(self.workdir.join(file), content).unwrap();
+
+        let repo = self.repository();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new(file)).unwrap();
+        index.write().unwrap();
+    }
+
+    /// Add a commit with a new file directly on a branch of origin, as if someone else pushed it
+    pub fn commit_on_origin(&self, branch: &str, file: &str, content: &str) {
+        let origin = Repository::open_bare(&self.origin).unwrap();
+        let branch_ref = format!("refs/heads/{}", branch);
+        let parent = origin
+            .find_reference(&branch_ref)
+            .unwrap()
+            .peel_to_commit()
+            .unwrap();
+
+        let blob = origin.blob(content.as_bytes()).unwrap();
+        let mut builder = origin.treebuilder(Some(&parent.tree().unwrap())).unwrap();
+        builder.insert(file, blob, 0o100644).unwrap();
+        let tree = origin.find_tree(builder.write().unwrap()).unwrap();
+
+        let signature = Signature::now("Someone", "someone@example.com").unwrap();
+        origin
+            .commit(
+                Some(&branch_ref),
+                &signature,
+                &signature,
+                content,
+                &tree,
+                &[&parent],
+            )
+            .unwrap();
+    }
+
+    /// Add an annotated tag on HEAD
+    pub fn tag(&self, name: &str) {
+        let repo = self.repository();
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        let signature = Signature::now("Test", "test@example.com").unwrap();
+        repo.tag(name, head.as_object(), &signature, name, false)
+            .unwrap();
+    }
+
+    /// Checkout an existing local branch
+    pub fn checkout(&self, branch: &str) {
+        let repo = self.repository();
+        let branch_ref = format!("refs/heads/{}", branch);
+        let tree = repo.revparse_single(&branch_ref).unwrap();
+        repo.checkout_tree(&tree, None).unwrap();
+        repo.set_head(&branch_ref).unwrap();
+    }
+
+    /// Subject of the last commit of a local branch and its number of parents
+    pub fn last_commit(&self, branch: &str) -> (String, usize) {
+        let repo = self.repository();
+        let commit = repo
+            .find_branch(branch, BranchType::Local)
+            .unwrap()
+            .get()
+            .peel_to_commit()
+            .unwrap();
+        (commit.summary().unwrap().to_string(), commit.parent_count())
+    }
+
+    pub fn file_content(&self, file: &str) -> Option<String> {
+        std::fs::read_to_string(self.workdir.join(file)).ok()
+    }
+
+    /// Branches of the origin repository
+    pub fn origin_branches(&self) -> Vec<String> {
+        let origin = Repository::open_bare(&self.origin).unwrap();
+        let branches = origin.branches(Some(BranchType::Local)).unwrap();
+        let mut names: Vec<String> = branches
+            .map(|b| b.unwrap().0.name().unwrap().unwrap().to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Tags of the origin repository
+    pub fn origin_tags(&self) -> Vec<String> {
+        let origin = Repository::open_bare(&self.origin).unwrap();
+        let tags = origin.tag_names(None).unwrap();
+        tags.iter().flatten().map(String::from).collect()
+    }
+
+    /// Remote tracking branch a local branch is configured to track, e.g. `origin/develop`
+    pub fn upstream(&self, branch: &str) -> Option<String> {
+        let repo = self.repository();
+        let branch = repo.find_branch(branch, BranchType::Local).unwrap();
+        let upstream = branch.upstream().ok()?;
+        upstream.name().unwrap().map(String::from)
+    }
+
+    /// Rename the origin remote, its remote tracking branches follow
+    pub fn rename_origin(&self, name: &str) {
+        self.repository().remote_rename("origin", name).unwrap();
+    }
+
+    pub fn head_branch(&self) -> String {
+        self.repository()
+            .head()
+            .unwrap()
+            .shorthand()
+            .unwrap()
+            .to_string()
+    }
+}
+
+pub const JIRA_USER: &str = "jdoe";
+pub const JIRA_TOKEN: &str = "jira-token";
+
+/// In-process Jira REST api serving canned responses.
+///
+/// Every mock only answers requests authenticated as [`JIRA_USER`] with [`JIRA_TOKEN`], other
+/// requests get mockito's default 501 response.
+pub struct MockJira {
+    server: ServerGuard,
+}
+
+/// A Jira issue as served by the mock: key, summary and status name
+pub type JiraIssueFixture<'a> = (&'a str, &'a str, &'a str);
+
+impl MockJira {
+    pub async fn new() -> Self {
+        Self {
+            server: mockito::Server::new_async().await,
+        }
+    }
+
+    pub fn config(&self) -> JiraConfig {
+        JiraConfig {
+            host: self.server.url(),
+            user: JIRA_USER.to_string(),
+            token: SecretRef::Plain(JIRA_TOKEN.to_string()),
+        }
+    }
+
+    pub fn jira(&self) -> JiraServer {
+        JiraServer::from_config(&self.config(), InquirePrompter::new(false)).unwrap()
+    }
+
+    pub fn url(&self) -> String {
+        self.server.url()
+    }
+
+    fn mock(&mut self, method: &str, path: &str) -> Mock {
+        let credentials = STANDARD.encode(format!("{}:{}", JIRA_USER, JIRA_TOKEN));
+        self.server
+            .mock(method, path)
+            .match_query(Matcher::Any)
+            .match_header("authorization", format!("Basic {}", credentials).as_str())
+    }
+
+    fn json(mock: Mock, body: &str) -> Mock {
+        mock.with_header("content-type", "application/json")
+            .with_body(body)
+    }
+
+    /// Body of an issue as returned by the issue and search endpoints
+    pub fn issue_json((key, summary, status): JiraIssueFixture) -> String {
+        format!(
+            r#"{{"id": "10001", "key": "{}", "fields": {{"summary": "{}", "status": {{"id": "3", "name": "{}"}}, "issuetype": {{"name": "Story"}}}}}}"#,
+            key, summary, status
+        )
+    }
+
+    pub async fn issue(&mut self, issue: JiraIssueFixture<'_>) -> Mock {
+        let mock = self.mock("GET", &format!("/rest/api/2/issue/{}", issue.0));
+        Self::json(mock, &Self::issue_json(issue))
+            .create_async()
+            .await
+    }
+
+    /// Serve all the issues in a single page for any JQL query
+    pub async fn search(&mut self, issues: &[JiraIssueFixture<'_>]) -> Mock {
+        let page = Self::search_page(0, issues.len(), issues);
+        let mock = self.mock("GET", "/rest/api/2/search");
+        Self::json(mock, &page).create_async().await
+    }
+
+    /// Serve one page of search results for the JQL query, starting at the given index
+    pub async fn search_page_at(
+        &mut self,
+        jql: &str,
+        start_at: usize,
+        total: usize,
+        issues: &[JiraIssueFixture<'_>],
+    ) -> Mock {
+        let page = Self::search_page(start_at, total, issues);
+        let mock = self
+            .mock("GET", "/rest/api/2/search")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("jql".to_string(), jql.to_string()),
+                Matcher::UrlEncoded("startAt".to_string(), start_at.to_string()),
+            ]));
+        Self::json(mock, &page).create_async().await
+    }
+
+    fn search_page(start_at: usize, total: usize, issues: &[JiraIssueFixture]) -> String {
+        let issues: Vec<String> = issues.iter().map(|i| Self::issue_json(*i)).collect();
+        format!(
+            r#"{{"startAt": {}, "maxResults": 50, "total": {}, "issues": [{}]}}"#,
+            start_at,
+            total,
+            issues.join(",")
+        )
+    }
+
+    /// Serve the transitions of an issue, given as transition id and target status name
+    pub async fn transitions(&mut self, key: &str, transitions: &[(&str, &str)]) -> Mock {
+        let transitions: Vec<String> = transitions
+            .iter()
+            .map(|(id, to)| {
+                format!(
+                    r#"{{"id": "{}", "name": "Move to {}", "to": {{"id": "{}", "name": "{}"}}}}"#,
+                    id, to, id, to
+                )
+            })
+            .collect();
+        let body = format!(r#"{{"transitions": [{}]}}"#, transitions.join(","));
+        let mock = self.mock("GET", &format!("/rest/api/2/issue/{}/transitions", key));
+        Self::json(mock, &body).create_async().await
+    }
+
+    /// Accept the given transition, assert the returned mock to check it was applied
+    pub async fn expect_transition(&mut self, key: &str, transition_id: &str) -> Mock {
+        self.mock("POST", &format!("/rest/api/2/issue/{}/transitions", key))
+            .match_body(Matcher::JsonString(format!(
+                r#"{{"transition": {{"id": "{}"}}}}"#,
+                transition_id
+            )))
+            .with_status(204)
+            .create_async()
+            .await
+    }
+
+    /// Accept the given comment, assert the returned mock to check it was added
+    pub async fn expect_comment(&mut self, key: &str, body: &str) -> Mock {
+        let mock = self
+            .mock("POST", &format!("/rest/api/2/issue/{}/comment", key))
+            .match_body(Matcher::JsonString(format!(r#"{{"body": "{}"}}"#, body)))
+            .with_status(201);
+        Self::json(mock, r#"{"id": "10000"}"#).create_async().await
+    }
+
+    /// Answer requests to the path with an error status and a Jira error payload
+    pub async fn error(&mut self, method: &str, path: &str, status: usize, message: &str) -> Mock {
+        let body = format!(r#"{{"errorMessages": ["{}"], "errors": {{}}}}"#, message);
+        let mock = self.mock(method, path).with_status(status);
+        Self::json(mock, &body).create_async().await
+    }
+
+    /// Refuse requests to the path authenticated with `token` like Jira does for a wrong token
+    pub async fn unauthorized(&mut self, method: &str, path: &str, token: &str) -> Mock {
+        let credentials = STANDARD.encode(format!("{}:{}", JIRA_USER, token));
+        self.server
+            .mock(method, path)
+            .match_header("authorization", format!("Basic {}", credentials).as_str())
+            .with_status(401)
+            .create_async()
+            .await
+    }
+
+    /// Answer requests to the path with a body that is not valid JSON
+    pub async fn malformed(&mut self, method: &str, path: &str) -> Mock {
+        let mock = self.mock(method, path);
+        Self::json(mock, r#"{"id": "10001", "key": "#)
+            .create_async()
+            .await
+    }
+}
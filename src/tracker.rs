@@ -0,0 +1,142 @@
+use std::fmt::Display;
+
+use async_trait::async_trait;
+use reqwest::Error as ReqwestError;
+use thiserror::Error;
+use url::ParseError;
+
+use crate::config::IssueTrackerKind;
+use crate::jira::{issue_key_from_branch, JiraError};
+use crate::secret::SecretError;
+
+/// Issue as seen by the workflow, whatever the tracker it comes from
+#[derive(Debug, Clone)]
+pub struct Issue {
+    /// Key used to reference the issue, e.g. `PROJ-123` for Jira or `123` for GitHub
+    pub key: String,
+    pub summary: String,
+    pub status: String,
+    /// Type of the issue (e.g. `Bug`, `Story`), or its first label for trackers without types
+    pub issue_type: Option<String>,
+    /// Url of the issue in the tracker web interface
+    pub url: String,
+}
+
+#[derive(Debug, Error)]
+pub enum TrackerError {
+    #[error(transparent)]
+    JiraError(#[from] JiraError),
+    #[error("{0}")]
+    RequestError(#[from] ReqwestError),
+    #[error("Invalid server url {0}")]
+    InvalidUrl(#[from] ParseError),
+    #[error("Issue {0} not found")]
+    IssueNotFound(String),
+    #[error("Issue {0} cannot be moved to status {1}, available statuses are: {2}")]
+    TransitionNotFound(String, String, String),
+    #[error("Authentication refused by issue tracker, please check your token")]
+    Unauthorized,
+    #[error("Cannot read token: {0}")]
+    SecretError(#[from] SecretError),
+}
+
+#[async_trait]
+pub trait IssueTracker: Send + Sync {
+    async fn get_issue(&self, key: &str) -> Result<Issue, TrackerError>;
+
+    /// Search issues, the query syntax depends on the tracker (JQL for Jira, search qualifiers for GitHub...)
+    async fn search_issues(&self, query: &str) -> Result<Vec<Issue>, TrackerError>;
+
+    /// Move an issue to the given status.
+    ///
+    /// Returns `false` when the issue is already in that status and nothing was done.
+    async fn transition_to_status(&self, issue: &Issue, status: &str)
+        -> Result<bool, TrackerError>;
+
+    async fn add_comment(&self, issue: &Issue, body: &str) -> Result<(), TrackerError>;
+
+    /// Query used to list the issues of the current user when none is configured
+    fn default_query(&self) -> &str;
+
+    /// Find the key of an issue in some text, typically a branch name
+    fn find_issue_key(&self, text: &str) -> Option<String>;
+}
+
+impl Display for Issue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} [{}]", self.key, self.summary, self.status)
+    }
+}
+
+/// Find the first issue number in a branch name, e.g. `123` in `feature/123-some-fix`.
+///
+/// Used by trackers where issues are referenced by number, like GitHub or GitLab.
+pub fn issue_number_from_branch(branch: &str) -> Option<String> {
+    branch
+        .split(['/', '-', '_'])
+        .find(|token| !token.is_empty() && token.chars().all(|c| c.is_ascii_digit()))
+        .map(String::from)
+}
+
+impl IssueTrackerKind {
+    /// Find the key of an issue in some text with the key format of this kind of tracker, like
+    /// [`IssueTracker::find_issue_key`] without having to connect to the tracker
+    pub fn find_issue_key(self, text: &str) -> Option<String> {
+        match self {
+            IssueTrackerKind::Jira => issue_key_from_branch(text),
+            IssueTrackerKind::GitHub | IssueTrackerKind::GitLab => issue_number_from_branch(text),
+        }
+    }
+}
+
+/// Map a status name to the open/closed state of trackers that only know these two
+pub fn open_or_closed(status: &str) -> Option<bool> {
+    match status.to_ascii_lowercase().as_str() {
+        "open" | "opened" | "reopen" | "reopened" => Some(true),
+        "closed" | "close" | "done" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_issue_number_from_branch() {
+        assert_eq!(
+            issue_number_from_branch("123-some-fix"),
+            Some("123".to_string())
+        );
+        assert_eq!(
+            issue_number_from_branch("feature/42_other"),
+            Some("42".to_string())
+        );
+    }
+
+    #[test]
+    fn test_issue_number_from_branch_without_number() {
+        assert_eq!(issue_number_from_branch("release/1.2"), None);
+        assert_eq!(issue_number_from_branch("develop"), None);
+    }
+
+    #[test]
+    fn test_find_issue_key_by_tracker_kind() {
+        let branch = "feature/42-PROJ-7-fix";
+        assert_eq!(
+            IssueTrackerKind::Jira.find_issue_key(branch),
+            Some("PROJ-7".to_string())
+        );
+        assert_eq!(
+            IssueTrackerKind::GitHub.find_issue_key(branch),
+            Some("42".to_string())
+        );
+    }
+
+    #[test]
+    fn test_open_or_closed() {
+        assert_eq!(open_or_closed("Done"), Some(false));
+        assert_eq!(open_or_closed("opened"), Some(true));
+        assert_eq!(open_or_closed("In Review"), None);
+    }
+}